@@ -2,14 +2,27 @@
 //!
 //! This crate wraps around the [`temp_dir`](https://docs.rs/temp_dir) crate to create a temporary
 //! Firefox profile directory which is automatically removed when dropped. It also creates a `user.js` file
-//! configured with a free Marionette address for remote control.
-
-use std::{fmt::Display, io, net::SocketAddr, result};
+//! configured with a free Marionette address for remote control, and optionally overrides the timezone and locale.
+//! The preferences of the `user.js` file come from an embedded template, unless a custom [`UserJs`] is provided.
+
+use std::{
+    fmt,
+    fmt::{Debug, Display, Formatter},
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    result,
+    sync::Arc,
+};
 
 use temp_dir::TempDir;
 use thiserror::Error;
-use tokio::{fs::write, net::TcpListener};
-use tracing::debug;
+use tokio::{
+    fs::{OpenOptions, write},
+    io::AsyncWriteExt,
+    net::TcpListener,
+};
+use tracing::{debug, warn};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -21,6 +34,8 @@ pub enum Error {
     GetFreeLocalAddress(#[source] io::Error),
     #[error("create temporary profile 'user.js' file failed")]
     CreateUserJsFile(#[source] io::Error),
+    #[error("read 'user.js' template file {0:?} failed")]
+    ReadUserJsTemplate(PathBuf, #[source] io::Error),
     #[error("create temporary profile 'userChrome.css' file failed")]
     CreateUserChromeCssFile(#[source] io::Error),
     #[error("temporary profile directory path is undefined")]
@@ -33,6 +48,56 @@ static USER_JS: [u8; include_bytes!("user.js").len()] = *include_bytes!("user.js
 static USER_CHROME_CSS: [u8; include_bytes!("userChrome.css").len()] =
     *include_bytes!("userChrome.css");
 
+/// The `user.js` template: the preferences written to every profile, before the Marionette port.
+///
+/// The default template is embedded in the binary. Cloning a [`UserJs`] is cheap, all clones share the same bytes.
+#[derive(Clone, PartialEq, Eq)]
+pub struct UserJs(Arc<[u8]>);
+
+impl UserJs {
+    /// Creates a template holding the given preferences.
+    pub fn new(bytes: impl Into<Arc<[u8]>>) -> Self {
+        Self(bytes.into())
+    }
+
+    /// Reads a template from the given file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::ReadUserJsTemplate`] if reading the file fails (e.g. it does not exist).
+    pub async fn read(path: impl AsRef<Path> + Send) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = tokio::fs::read(path)
+            .await
+            .map_err(|error| Error::ReadUserJsTemplate(path.to_path_buf(), error))?;
+        debug!("Read 'user.js' template at: {:?}", path);
+
+        Ok(Self::new(bytes))
+    }
+
+    /// Returns the preferences of the template.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Default for UserJs {
+    /// Returns the embedded template.
+    fn default() -> Self {
+        Self::new(USER_JS)
+    }
+}
+
+impl Debug for UserJs {
+    // The preferences are not printed, as the options holding the template are logged with each browser.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserJs")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
 /// Represents a temporary Firefox profile.
 ///
 /// This structure wraps a temporary directory (provided by the [`temp_dir`](https://docs.rs/temp_dir) crate)
@@ -42,31 +107,97 @@ static USER_CHROME_CSS: [u8; include_bytes!("userChrome.css").len()] =
 pub struct Profile {
     directory: TempDir,
     marionette_address: SocketAddr,
+    timezone: Option<String>,
+    locale: Option<String>,
 }
 
 impl Profile {
     /// Creates a new Firefox profile.
     ///
     /// This function creates a temporary directory for the profile and writes a `user.js` file inside it,
-    /// which includes a free Marionette port for remote control.
+    /// from the embedded template, which includes a free Marionette port for remote control.
     ///
     /// # Errors
     ///
     /// Returns an [`Error`] if creating the directory, writing the file, or obtaining a free local address fails.
     pub async fn new() -> Result<Self> {
+        Self::from_user_js(&UserJs::default()).await
+    }
+
+    /// Creates a new Firefox profile like [`Profile::new`], but writes the `user.js` file from the given template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if creating the directory, writing the file, or obtaining a free local address fails.
+    pub async fn from_user_js(user_js: &UserJs) -> Result<Self> {
         debug!("Creating a new Profile instance...");
         let directory = create_directory()?;
         debug!("Created profile directory at: {:?}", directory.path());
-        let marionette_address = create_user_js_file(&directory).await?;
+        let marionette_address = create_user_js_file(&directory, user_js).await?;
 
         create_user_chrome_css_file(&directory).await?;
 
         Ok(Self {
             directory,
             marionette_address,
+            timezone: None,
+            locale: None,
         })
     }
 
+    /// Sets the timezone of the browser, as an IANA name (e.g. `Europe/Paris`).
+    ///
+    /// Firefox reads its timezone from the `TZ` environment variable, which must be set when spawning
+    /// the browser process, see [`Profile::envs`].
+    #[must_use]
+    pub fn with_timezone(mut self, timezone: String) -> Self {
+        self.timezone = Some(timezone);
+        self
+    }
+
+    /// Sets the locale of the browser, as a BCP 47 language tag (e.g. `fr-CH`).
+    ///
+    /// This appends the `intl.accept_languages` and `intl.locale.requested` preferences to the `user.js` file,
+    /// so it must be called before the browser is launched with this profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if writing the preferences to the `user.js` file fails.
+    pub async fn with_locale(mut self, locale: String) -> Result<Self> {
+        let value = serde_json::Value::from(locale.as_str());
+        let user_prefs = [
+            user_pref("intl.accept_languages", &value),
+            user_pref("intl.locale.requested", &value),
+        ]
+        .concat();
+
+        append_user_js_file(&self.directory, user_prefs.as_bytes()).await?;
+        self.locale = Some(locale);
+
+        Ok(self)
+    }
+
+    /// Returns the timezone set with [`Profile::with_timezone`], if any.
+    #[must_use]
+    pub fn timezone(&self) -> Option<&str> {
+        self.timezone.as_deref()
+    }
+
+    /// Returns the locale set with [`Profile::with_locale`], if any.
+    #[must_use]
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// Returns the environment variables the browser process must be spawned with (e.g. `TZ`).
+    #[must_use]
+    pub fn envs(&self) -> Vec<(&'static str, &str)> {
+        self.timezone()
+            .map(|timezone| ("TZ", timezone))
+            .into_iter()
+            .collect()
+    }
+
     /// Returns the Marionette address associated with this profile.
     #[must_use]
     pub const fn marionette_address(&self) -> SocketAddr {
@@ -100,21 +231,33 @@ impl Profile {
         debug!("Removing profile directory at: {:?}", self.directory.path());
         self.directory.cleanup().map_err(Error::RemoveDirectory)
     }
+
+    /// Keeps the profile directory on disk, instead of removing it when dropped, and returns its path.
+    ///
+    /// This preserves the profile (and the logs it holds) to diagnose a failure, its path is logged at `warn`.
+    pub fn leak(self) -> PathBuf {
+        let path = self.directory.path().to_path_buf();
+        warn!("Keeping profile directory at: {:?}", path);
+        self.directory.leak();
+
+        path
+    }
 }
 
 /// Creates the `user.js` file within the temporary profile directory.
 ///
-/// This function writes the default `user.js` content concatenated with a configured Marionette port.
+/// This function writes the `user.js` template concatenated with a configured Marionette port.
 /// It returns the free Marionette address used in the file.
 ///
 /// # Arguments
 ///
 /// * `directory` - The temporary directory where the `user.js` file will be created.
+/// * `user_js` - The template of the preferences.
 ///
 /// # Errors
 ///
 /// Returns an [`Error`] if writing the file or obtaining a free local address fails.
-async fn create_user_js_file(directory: &TempDir) -> Result<SocketAddr> {
+async fn create_user_js_file(directory: &TempDir, user_js: &UserJs) -> Result<SocketAddr> {
     debug!("Creating 'user.js' inside the temporary profile directory.");
     let marionette_address = get_free_local_address().await?;
     debug!(
@@ -124,7 +267,7 @@ async fn create_user_js_file(directory: &TempDir) -> Result<SocketAddr> {
     let marionette_port_pref = user_pref("marionette.port", marionette_address.port());
 
     let user_js_path = directory.child("user.js");
-    let user_js_data = [&USER_JS, marionette_port_pref.as_bytes()].concat();
+    let user_js_data = [user_js.as_bytes(), marionette_port_pref.as_bytes()].concat();
 
     debug!("Write 'user.js' file at: {:?}", user_js_path);
     write(&user_js_path, user_js_data)
@@ -134,6 +277,29 @@ async fn create_user_js_file(directory: &TempDir) -> Result<SocketAddr> {
     Ok(marionette_address)
 }
 
+/// Appends the given preferences to the `user.js` file within the temporary profile directory.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if opening or writing the file fails.
+async fn append_user_js_file(directory: &TempDir, user_prefs: &[u8]) -> Result<()> {
+    let user_js_path = directory.child("user.js");
+
+    debug!(
+        "Append preferences to 'user.js' file at: {:?}",
+        user_js_path
+    );
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(&user_js_path)
+        .await
+        .map_err(Error::CreateUserJsFile)?;
+
+    file.write_all(user_prefs)
+        .await
+        .map_err(Error::CreateUserJsFile)
+}
+
 /// Creates the `chrome/userChrome.css` file within the temporary profile directory.
 ///
 /// # Arguments
@@ -254,6 +420,63 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_profile_from_user_js() {
+        let template = TempDir::with_prefix("pantin-user-js").expect("Failed to create directory");
+        let template_path = template.child("user.js");
+        tokio::fs::write(&template_path, "user_pref(\"pantin.custom\", true);\n")
+            .await
+            .expect("Failed to write template");
+
+        let user_js = UserJs::read(&template_path)
+            .await
+            .expect("Failed to read template");
+        let profile = Profile::from_user_js(&user_js)
+            .await
+            .expect("Failed to create profile");
+
+        let path_str = profile.path().expect("Profile path should be valid");
+        let content = tokio::fs::read_to_string(std::path::Path::new(path_str).join("user.js"))
+            .await
+            .expect("Failed to read user.js file");
+
+        assert!(content.starts_with("user_pref(\"pantin.custom\", true);\n"));
+        assert!(content.contains("user_pref(\"marionette.port\""));
+        assert!(
+            !content.contains("app.normandy.api_url"),
+            "user.js should not contain the embedded template"
+        );
+
+        assert!(matches!(
+            UserJs::read(template.child("missing.js")).await,
+            Err(Error::ReadUserJsTemplate(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_profile_timezone_and_locale() {
+        let profile = Profile::new()
+            .await
+            .expect("Failed to create profile")
+            .with_timezone("Asia/Tokyo".to_string())
+            .with_locale("fr-CH".to_string())
+            .await
+            .expect("Failed to set locale");
+
+        assert_eq!(profile.timezone(), Some("Asia/Tokyo"));
+        assert_eq!(profile.locale(), Some("fr-CH"));
+        assert_eq!(profile.envs(), [("TZ", "Asia/Tokyo")]);
+
+        let path_str = profile.path().expect("Profile path should be valid");
+        let content = tokio::fs::read_to_string(std::path::Path::new(path_str).join("user.js"))
+            .await
+            .expect("Failed to read user.js file");
+
+        assert!(content.contains("user_pref(\"marionette.port\""));
+        assert!(content.contains("user_pref(\"intl.accept_languages\", \"fr-CH\");"));
+        assert!(content.contains("user_pref(\"intl.locale.requested\", \"fr-CH\");"));
+    }
+
     #[tokio::test]
     async fn test_profile_remove() {
         let profile = Profile::new().await.expect("Failed to create profile");
@@ -272,6 +495,19 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_profile_leak() {
+        let profile = Profile::new().await.expect("Failed to create profile");
+
+        let path = profile.leak();
+
+        assert!(path.exists(), "Profile directory should be kept");
+        assert!(logs_contain("Keeping profile directory at:"));
+
+        std::fs::remove_dir_all(&path).expect("Failed to remove kept profile");
+    }
+
     #[tokio::test]
     #[tracing_test::traced_test]
     async fn test_profile_tracing() {
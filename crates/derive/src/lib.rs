@@ -4,14 +4,21 @@
 
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
-use syn::{DeriveInput, parse_macro_input};
+use syn::{Attribute, DeriveInput, LitStr, parse_macro_input};
 
-#[proc_macro_derive(WebDriverCommand)]
+/// Derives the `new` constructor and the `Command` trait of a command struct.
+///
+/// The command is named `WebDriver:<Ident>` unless another name is set with the `#[command(name = "...")]`
+/// attribute (e.g. for the `Marionette:*` commands).
+#[proc_macro_derive(WebDriverCommand, attributes(command))]
 pub fn derive_web_driver_command(input: TokenStream) -> TokenStream {
-    let DeriveInput { ident, .. } = parse_macro_input!(input);
+    let DeriveInput { ident, attrs, .. } = parse_macro_input!(input);
     let parameters = format_ident!("{}Parameters", ident);
     let response = format_ident!("{}Response", ident);
-    let command = format!("WebDriver:{ident}");
+    let command = match command_name(&attrs) {
+        Ok(name) => name.unwrap_or_else(|| format!("WebDriver:{ident}")),
+        Err(error) => return error.to_compile_error().into(),
+    };
 
     let expanded = quote! {
         impl #ident {
@@ -38,3 +45,21 @@ pub fn derive_web_driver_command(input: TokenStream) -> TokenStream {
 
     expanded.into()
 }
+
+/// Returns the name set with the `#[command(name = "...")]` attribute, if any.
+fn command_name(attrs: &[Attribute]) -> syn::Result<Option<String>> {
+    let mut name = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("command")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported command attribute, expected `name`"))
+            }
+        })?;
+    }
+
+    Ok(name)
+}
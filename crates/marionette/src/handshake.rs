@@ -1,14 +1,15 @@
 //! This module implements the handshake procedure for Marionette.
 //!
 //! It reads a handshake message from a TCP stream using the Marionette JSON response format
-//! and verifies that the response contains the expected values.
+//! and verifies that the response contains the expected values. Protocol versions higher than the known one
+//! are accepted, as they are expected to stay compatible, with a warning.
 
 use std::result;
 
 use serde::Deserialize;
 use thiserror::Error;
-use tokio::io::AsyncRead;
-use tracing::{debug, error};
+use tokio::io::AsyncBufRead;
+use tracing::{debug, warn};
 
 use crate::response;
 
@@ -18,19 +19,25 @@ pub enum Error {
     ParseResponse(#[from] response::Error),
     #[error("expected application type 'gecko', got: {0}")]
     UnexpectedApplicationType(String),
-    #[error("expected marionette protocol version 3, got: {0}")]
+    #[error("expected marionette protocol version {MIN_MARIONETTE_PROTOCOL} or higher, got: {0}")]
     UnexpectedMarionetteProtocolVersion(u8),
 }
 
+/// The minimum Marionette protocol version accepted by the handshake.
+pub const MIN_MARIONETTE_PROTOCOL: u8 = 3;
+
+/// The latest Marionette protocol version known to work, higher versions are accepted with a warning.
+pub const KNOWN_MARIONETTE_PROTOCOL: u8 = 3;
+
 pub type Result<T, E = Error> = result::Result<T, E>;
 
 /// Represents a handshake message received from the Marionette server.
 ///
 /// The handshake message is deserialized from JSON and contains:
 ///
-/// - `marionette_protocol`: the version of the Marionette protocol, expected to be `3`.
+/// - `marionette_protocol`: the version of the Marionette protocol, expected to be at least `3`.
 /// - `application_type`: the type of the application, expected to be `"gecko"`.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all(deserialize = "camelCase"))]
 pub struct Handshake {
     pub marionette_protocol: u8,
@@ -43,7 +50,8 @@ impl Handshake {
     /// This function:
     /// 1. Reads a JSON-formatted response from the stream using [`response::read`].
     /// 2. Parses the JSON into a [`Handshake`] using [`response::parse_raw`].
-    /// 3. Verifies that `application_type` is `"gecko"` and `marionette_protocol` equals 3.
+    /// 3. Verifies that `application_type` is `"gecko"` and `marionette_protocol` is at least
+    ///    [`MIN_MARIONETTE_PROTOCOL`], logging a warning if it is higher than [`KNOWN_MARIONETTE_PROTOCOL`].
     ///
     /// # Arguments
     ///
@@ -54,8 +62,8 @@ impl Handshake {
     /// Returns an [`Error`] if:
     /// - Parsing the response fails.
     /// - The `application_type` is not `"gecko"`.
-    /// - The `marionette_protocol` is not 3.
-    pub async fn read<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Self> {
+    /// - The `marionette_protocol` is lower than [`MIN_MARIONETTE_PROTOCOL`].
+    pub async fn read<S: AsyncBufRead + Unpin>(stream: &mut S) -> Result<Self> {
         debug!("Reading Handshake...");
         let json = response::read(stream).await?;
         let handshake: Self = response::parse_raw(json)?;
@@ -65,12 +73,20 @@ impl Handshake {
             return Err(Error::UnexpectedApplicationType(handshake.application_type));
         }
 
-        if handshake.marionette_protocol != 3 {
+        if handshake.marionette_protocol < MIN_MARIONETTE_PROTOCOL {
             return Err(Error::UnexpectedMarionetteProtocolVersion(
                 handshake.marionette_protocol,
             ));
         }
 
+        if handshake.marionette_protocol > KNOWN_MARIONETTE_PROTOCOL {
+            warn!(
+                version = handshake.marionette_protocol,
+                known = KNOWN_MARIONETTE_PROTOCOL,
+                "Unknown marionette protocol version, assuming it is compatible"
+            );
+        }
+
         Ok(handshake)
     }
 }
@@ -79,7 +95,7 @@ impl Handshake {
 #[cfg_attr(coverage, coverage(off))]
 #[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 mod tests {
-    use tokio::io::{AsyncWriteExt, duplex};
+    use tokio::io::{AsyncWriteExt, BufReader, duplex};
 
     use super::*;
 
@@ -89,7 +105,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_handshake_read_success() {
-        let (mut client, mut server) = duplex(1024);
+        let (client, mut server) = duplex(1024);
         let json = r#"{"marionetteProtocol":3,"applicationType":"gecko"}"#;
         let message = format_message(json);
 
@@ -98,25 +114,46 @@ mod tests {
             server.shutdown().await.unwrap();
         });
 
-        let handshake = Handshake::read(&mut client)
+        let handshake = Handshake::read(&mut BufReader::new(client))
             .await
             .expect("Expected valid handshake");
         assert_eq!(handshake.marionette_protocol, 3);
         assert_eq!(handshake.application_type, "gecko");
     }
 
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_handshake_read_higher_marionette_protocol() {
+        let (client, mut server) = duplex(1024);
+        let json = r#"{"marionetteProtocol":4,"applicationType":"gecko"}"#;
+        let message = format_message(json);
+
+        tokio::spawn(async move {
+            server.write_all(message.as_bytes()).await.unwrap();
+            server.shutdown().await.unwrap();
+        });
+
+        let handshake = Handshake::read(&mut BufReader::new(client))
+            .await
+            .expect("Expected a compatible handshake");
+        assert_eq!(handshake.marionette_protocol, 4);
+        assert!(logs_contain(
+            "Unknown marionette protocol version, assuming it is compatible"
+        ));
+    }
+
     #[tokio::test]
     async fn test_handshake_unexpected_application_type() {
         let json = r#"{"marionetteProtocol":3,"applicationType":"not-gecko"}"#;
         let message = format_message(json);
-        let (mut client, mut server) = duplex(1024);
+        let (client, mut server) = duplex(1024);
 
         tokio::spawn(async move {
             server.write_all(message.as_bytes()).await.unwrap();
             server.shutdown().await.unwrap();
         });
 
-        let error = Handshake::read(&mut client)
+        let error = Handshake::read(&mut BufReader::new(client))
             .await
             .expect_err("Expected an UnexpectedApplicationType error");
         match error {
@@ -127,7 +164,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_handshake_unexpected_marionette_protocol() {
-        let (mut client, mut server) = duplex(1024);
+        let (client, mut server) = duplex(1024);
         let json = r#"{"marionetteProtocol":2,"applicationType":"gecko"}"#;
         let message = format_message(json);
 
@@ -136,12 +173,12 @@ mod tests {
             server.shutdown().await.unwrap();
         });
 
-        let error = Handshake::read(&mut client)
+        let error = Handshake::read(&mut BufReader::new(client))
             .await
             .expect_err("Expected an UnexpectedMarionetteProtocolVersion error");
         match error {
             Error::UnexpectedMarionetteProtocolVersion(version) => assert_eq!(version, 2),
-            _ => panic!("Expected UnexpectedMarionetteProtocolVersion error, got {error:?}",),
+            _ => panic!("Expected UnexpectedMarionetteProtocolVersion error, got {error:?}"),
         }
     }
 }
@@ -21,7 +21,7 @@
 //!     let addr: SocketAddr = "127.0.0.1:2828".parse()?;
 //!     
 //!     // Create a new Marionette client instance.
-//!     let mut client = Marionette::new(&addr).await?;
+//!     let client = Marionette::new(&addr).await?;
 //!     println!("Connected with session id: {}", client.session_id());
 //!     
 //!     // Send a Navigate command to open a URL.
@@ -6,17 +6,28 @@
 //! It integrates functionality from the [`handshake`], [`request`], and [`webdriver`] modules to provide
 //! a unified interface for interacting with the Marionette protocol.
 
-use std::{fmt::Debug, io, net::SocketAddr, result, time::Duration};
+use std::{
+    collections::hash_map::RandomState,
+    fmt::Debug,
+    hash::{BuildHasher, Hasher},
+    io,
+    net::SocketAddr,
+    result,
+    sync::Arc,
+    time::Duration,
+};
 
+use serde_json::{Map, Value};
 use thiserror::Error;
 use tokio::{
-    io::{AsyncRead, AsyncWrite},
+    io::{AsyncBufRead, AsyncWrite, BufReader},
     net::TcpStream,
-    time::{Instant, sleep},
+    sync::Mutex,
+    time::{Instant, sleep, timeout_at},
 };
-use tracing::{debug, error};
+use tracing::debug;
 
-use crate::{handshake, request, webdriver};
+use crate::{handshake, request, response, webdriver};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -32,17 +43,76 @@ pub enum Error {
     Request(#[from] request::Error),
 }
 
+impl Error {
+    /// Returns the failure reported by the Marionette server, if the error is a command failure.
+    #[must_use]
+    pub const fn command_failure(&self) -> Option<&response::Failure> {
+        match self {
+            Self::Request(request::Error::Response(response::Error::CommandFailure(
+                _,
+                failure,
+            ))) => Some(failure),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the error is likely transient and the command may succeed on a fresh session.
+    ///
+    /// This covers the broken connections (failed writes or reads, truncated responses)
+    /// and the `invalid session id` command failures, but not the other command failures.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Request(
+                request::Error::FailedToWriteRequest(_)
+                | request::Error::Response(
+                    response::Error::ReadByteCount(_)
+                    | response::Error::ReadByte(_)
+                    | response::Error::UnexpectedEndOfResponse,
+                ),
+            ) => true,
+            _ => self
+                .command_failure()
+                .is_some_and(|failure| failure.error == "invalid session id"),
+        }
+    }
+
+    /// Returns `true` if the error is a navigation failure likely to succeed if the navigation is sent again.
+    ///
+    /// This covers the loads aborted by the browser (`NS_BINDING_ABORTED` or `NS_ERROR_ABORT`, e.g. when
+    /// the navigation is superseded by a late redirect of the previous page), but not the network errors like
+    /// an unknown host or a refused connection, which would fail the same way again.
+    #[must_use]
+    pub fn is_transient_navigation(&self) -> bool {
+        self.command_failure().is_some_and(|failure| {
+            TRANSIENT_NAVIGATION_ERRORS
+                .iter()
+                .any(|error| failure.message.contains(error))
+        })
+    }
+}
+
+/// The browser error codes of the aborted loads, reported in the message of the navigation failures.
+const TRANSIENT_NAVIGATION_ERRORS: [&str; 2] = ["NS_BINDING_ABORTED", "NS_ERROR_ABORT"];
+
 pub type Result<T, E = Error> = result::Result<T, E>;
 
 /// Represents a Marionette client connected to a Marionette server.
 ///
-/// The client holds a TCP stream, the result of the handshake, and the session information
+/// The client holds a buffered TCP stream, the result of the handshake, and the session information
 /// obtained from starting a new Marionette session.
-#[derive(Debug)]
+///
+/// Cloning a [`Marionette`] client is cheap and all the clones share the same stream. The stream is guarded
+/// by a mutex held for a whole request/response exchange, so the commands sent concurrently through
+/// several handles are never interleaved: they are sent one after the other, in the order the lock is acquired,
+/// and each one receives its own response.
+#[derive(Debug, Clone)]
 pub struct Marionette {
-    stream: TcpStream,
+    stream: Arc<Mutex<BufReader<TcpStream>>>,
     handshake: handshake::Handshake,
     session: webdriver::NewSessionResponse,
+    max_request_size: usize,
+    max_response_size: usize,
 }
 
 impl Marionette {
@@ -62,32 +132,91 @@ impl Marionette {
     /// - The handshake fails.
     /// - The new session request fails.
     pub async fn new(address: &SocketAddr) -> Result<Self> {
+        Self::connect(address, None).await
+    }
+
+    /// Creates a new Marionette client like [`Marionette::new`], requesting the given capabilities for the session.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The socket address of the Marionette server.
+    /// * `capabilities` - The session capabilities, e.g. built with a [`webdriver::CapabilitiesBuilder`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if:
+    /// - The connection to the server times out.
+    /// - The handshake fails.
+    /// - The new session request fails.
+    pub async fn with_capabilities<C>(address: &SocketAddr, capabilities: C) -> Result<Self>
+    where
+        C: Into<webdriver::NewSessionCapabilities> + Send,
+    {
+        Self::connect(address, Some(capabilities.into())).await
+    }
+
+    async fn connect(
+        address: &SocketAddr,
+        capabilities: webdriver::NewSessionParameters,
+    ) -> Result<Self> {
         debug!("Creating a new Marionette Client instance...");
-        let mut stream = connect(address, 2000, 100).await?;
+        let mut stream = BufReader::new(connect(address, 2000, 100).await?);
         let handshake = read_handshake(&mut stream).await?;
-        let session = new_session(&mut stream).await?;
+        let session = new_session(&mut stream, capabilities).await?;
 
         Ok(Self {
-            stream,
+            stream: Arc::new(Mutex::new(stream)),
             handshake,
             session,
+            max_request_size: request::DEFAULT_MAX_REQUEST_SIZE,
+            max_response_size: response::DEFAULT_MAX_RESPONSE_SIZE,
         })
     }
 
+    /// Sets the maximum size in bytes of the serialized command requests
+    /// (default: [`request::DEFAULT_MAX_REQUEST_SIZE`]).
+    ///
+    /// A request exceeding it (e.g. a script with huge arguments) is rejected with
+    /// [`request::Error::RequestTooLarge`] before anything is written, so the connection stays usable.
+    #[must_use]
+    pub const fn with_max_request_size(mut self, max_request_size: usize) -> Self {
+        self.max_request_size = max_request_size;
+        self
+    }
+
+    /// Sets the maximum size in bytes of the command responses (default: [`response::DEFAULT_MAX_RESPONSE_SIZE`]).
+    ///
+    /// A response whose length prefix exceeds it is rejected with [`response::Error::ResponseTooLarge`]
+    /// before anything is allocated for its body. The body is left unread, so the connection is then unusable.
+    #[must_use]
+    pub const fn with_max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
     /// Returns the Marionette protocol version obtained during the handshake.
+    #[must_use]
     pub const fn protocol(&self) -> u8 {
         self.handshake.marionette_protocol
     }
 
     /// Returns the current session identifier.
+    #[must_use]
     pub fn session_id(&self) -> &str {
         self.session.session_id.as_str()
     }
 
+    /// Returns the capabilities of the current session, as negotiated with the server
+    /// (e.g. `browserVersion` or `platformName`).
+    #[must_use]
+    pub const fn capabilities(&self) -> &Map<String, Value> {
+        &self.session.capabilities
+    }
+
     /// Sends a command to the Marionette server.
     ///
     /// This method delegates to the [`request::send`] function to send the command
-    /// and receive the corresponding response.
+    /// and receive the corresponding response, holding the stream lock for the whole exchange.
     ///
     /// # Type Parameters
     ///
@@ -100,13 +229,75 @@ impl Marionette {
     /// # Errors
     ///
     /// Returns an [`Error::Request`] if the request fails.
-    pub async fn send<C>(&mut self, command: &C) -> Result<C::Response>
+    pub async fn send<C>(&self, command: &C) -> Result<C::Response>
+    where
+        C: webdriver::Command + Send + Sync,
+    {
+        let mut stream = self.stream.lock().await;
+
+        request::send_with_max(
+            &mut *stream,
+            command.name(),
+            &command.parameters(),
+            self.max_request_size,
+            self.max_response_size,
+        )
+        .await
+        .map_err(Error::Request)
+    }
+
+    /// Sends a command like [`Marionette::send`], unless the connection is busy with another command.
+    ///
+    /// Returns `None` without sending the command if the stream lock is held (e.g. by a long navigation),
+    /// so the caller can tell a busy connection from a slow answer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Some` [`Error::Request`] if the request fails.
+    pub async fn try_send<C>(&self, command: &C) -> Option<Result<C::Response>>
     where
         C: webdriver::Command + Send + Sync,
     {
-        request::send(&mut self.stream, command.name(), &command.parameters())
+        let mut stream = self.stream.try_lock().ok()?;
+
+        Some(
+            request::send_with_max(
+                &mut *stream,
+                command.name(),
+                &command.parameters(),
+                self.max_request_size,
+                self.max_response_size,
+            )
             .await
-            .map_err(Error::Request)
+            .map_err(Error::Request),
+        )
+    }
+
+    /// Sends a command by name to the Marionette server and returns the raw value of its response.
+    ///
+    /// This is an escape hatch for the commands without a [`webdriver::Command`] (e.g. `WebDriver:GetCapabilities`),
+    /// the parameters and the response are not checked against any schema.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The command name, e.g. `WebDriver:GetCapabilities`.
+    /// * `parameters` - The parameters of the command.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::Request`] if the request fails.
+    pub async fn send_raw(&self, name: &str, parameters: &Value) -> Result<Value> {
+        let mut stream = self.stream.lock().await;
+
+        request::send_with_max(
+            &mut *stream,
+            name,
+            parameters,
+            self.max_request_size,
+            self.max_response_size,
+        )
+        .await
+        .map_err(Error::Request)
     }
 }
 
@@ -119,7 +310,7 @@ impl Marionette {
 /// # Errors
 ///
 /// Returns an [`Error::Handshake`] if the handshake fails.
-async fn read_handshake<S: AsyncRead + Unpin>(stream: &mut S) -> Result<handshake::Handshake> {
+async fn read_handshake<S: AsyncBufRead + Unpin>(stream: &mut S) -> Result<handshake::Handshake> {
     handshake::Handshake::read(stream)
         .await
         .map_err(Error::Handshake)
@@ -141,7 +332,7 @@ async fn read_handshake<S: AsyncRead + Unpin>(stream: &mut S) -> Result<handshak
 /// Returns an [`Error::Request`] if sending the command fails.
 async fn send<S, C>(stream: &mut S, command: &C) -> Result<C::Response>
 where
-    S: AsyncRead + AsyncWrite + Unpin,
+    S: AsyncBufRead + AsyncWrite + Unpin,
     C: webdriver::Command + Send + Sync,
 {
     request::send(stream, command.name(), &command.parameters())
@@ -154,25 +345,69 @@ where
 /// # Arguments
 ///
 /// * `stream` - A mutable reference to the stream.
+/// * `capabilities` - The optional capabilities requested for the session.
 ///
 /// # Errors
 ///
 /// Returns an [`Error::Request`] if the request fails.
-async fn new_session<S: AsyncRead + AsyncWrite + Unpin>(
+async fn new_session<S: AsyncBufRead + AsyncWrite + Unpin>(
     stream: &mut S,
+    capabilities: webdriver::NewSessionParameters,
 ) -> Result<webdriver::NewSessionResponse> {
-    send(stream, &webdriver::NewSession::new(None)).await
+    send(stream, &webdriver::NewSession::new(capabilities)).await
+}
+
+/// Maximum delay between two connection attempts.
+const MAX_CONNECT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An exponential backoff with jitter, computing the delays between the connection attempts.
+#[derive(Debug)]
+struct Backoff {
+    interval: Duration,
+    max_interval: Duration,
 }
 
-/// Attempts to connect to the given address with a timeout and retry interval.
+impl Backoff {
+    /// Creates a backoff starting at the given interval, doubled after each attempt up to the maximum one.
+    fn new(interval: Duration, max_interval: Duration) -> Self {
+        Self {
+            interval: interval.min(max_interval),
+            max_interval,
+        }
+    }
+
+    /// Returns the delay before the next attempt, randomly picked between the half and the whole
+    /// of the current interval so the retries of several clients do not stay in sync, then doubles the interval.
+    fn next_delay(&mut self) -> Duration {
+        let interval = self.interval;
+        self.interval = interval.saturating_mul(2).min(self.max_interval);
+
+        let half = interval / 2;
+        half + jitter(interval.saturating_sub(half))
+    }
+}
+
+/// Returns a random duration between zero and the given one.
+fn jitter(max: Duration) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+    let max_nanos = u64::try_from(max.as_nanos()).unwrap_or(u64::MAX);
+
+    Duration::from_nanos(random % max_nanos.saturating_add(1))
+}
+
+/// Attempts to connect to the given address with a timeout, retrying with an exponential backoff.
 ///
-/// The function continuously retries to connect until the specified timeout is reached.
+/// The function retries to connect until the specified timeout is reached, waiting between the attempts
+/// an interval doubled after each of them (up to [`MAX_CONNECT_INTERVAL`]), with some jitter, so a port
+/// that is not ready yet is not hammered.
+/// Each attempt is bounded by the remaining time and the last sleep is capped to the deadline,
+/// so the total wait never exceeds the timeout.
 ///
 /// # Arguments
 ///
 /// * `address` - The socket address of the Marionette server.
 /// * `timeout_ms` - The total timeout in milliseconds.
-/// * `interval_ms` - The retry interval in milliseconds.
+/// * `interval_ms` - The initial retry interval in milliseconds.
 ///
 /// # Errors
 ///
@@ -180,7 +415,9 @@ async fn new_session<S: AsyncRead + AsyncWrite + Unpin>(
 async fn connect(address: &SocketAddr, timeout_ms: u64, interval_ms: u64) -> Result<TcpStream> {
     let interval = Duration::from_millis(interval_ms);
     let timeout = Duration::from_millis(timeout_ms);
-    let now = Instant::now();
+    let deadline = Instant::now() + timeout;
+    let mut backoff = Backoff::new(interval, MAX_CONNECT_INTERVAL);
+    let mut attempt = 1_u32;
 
     debug!(
         ?address,
@@ -190,26 +427,29 @@ async fn connect(address: &SocketAddr, timeout_ms: u64, interval_ms: u64) -> Res
     );
 
     loop {
-        let stream = TcpStream::connect(address).await;
-
-        match stream {
-            Ok(stream) => {
+        let source = match timeout_at(deadline, TcpStream::connect(address)).await {
+            Ok(Ok(stream)) => {
                 debug!(?address, "Connected !");
                 return Ok(stream);
             },
-            Err(source) => {
-                if now.elapsed() < timeout {
-                    debug!(?address, "Retrying in {interval_ms}ms...",);
-                    sleep(interval).await;
-                } else {
-                    return Err(Error::ConnectionTimeout {
-                        address: address.to_string(),
-                        timeout,
-                        source,
-                    });
-                }
-            },
+            Ok(Err(error)) => error,
+            Err(elapsed) => io::Error::from(elapsed),
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        if remaining.is_zero() {
+            return Err(Error::ConnectionTimeout {
+                address: address.to_string(),
+                timeout,
+                source,
+            });
         }
+
+        let delay = backoff.next_delay().min(remaining);
+        debug!(?address, attempt, "Retrying in {}ms...", delay.as_millis());
+        sleep(delay).await;
+        attempt += 1;
     }
 }
 
@@ -219,103 +459,82 @@ async fn connect(address: &SocketAddr, timeout_ms: u64, interval_ms: u64) -> Res
 mod tests {
     use std::net::SocketAddr;
 
-    use tokio::{io::AsyncWriteExt, net::TcpListener};
+    use tokio::{io::AsyncWriteExt, net::TcpListener, task::JoinHandle};
 
     use super::*;
-    use crate::response;
 
-    fn format_message(body: &str) -> String {
-        format!("{}:{}", body.len(), body)
-    }
-
-    #[tokio::test]
-    async fn test_marionette_client() {
+    /// Spawns a mocked Marionette server accepting one connection: it sends the handshake, then answers each
+    /// command with the result returned by `answer` for its name and parameters, until the client disconnects.
+    ///
+    /// The task returns the names and parameters of the received commands, the `WebDriver:NewSession` included.
+    async fn mock_marionette<F>(answer: F) -> (SocketAddr, JoinHandle<Vec<(String, Value)>>)
+    where
+        F: Fn(&str, &Value) -> String + Send + 'static,
+    {
         let listener = TcpListener::bind("127.0.0.1:0")
             .await
             .expect("Failed to bind listener");
         let addr: SocketAddr = listener.local_addr().expect("Failed to get local address");
 
-        tokio::spawn(async move {
-            let (mut socket, _) = listener
+        let handle = tokio::spawn(async move {
+            let (socket, _) = listener
                 .accept()
                 .await
                 .expect("Failed to accept connection");
-
-            // --- Send Handshake response ---
+            let mut socket = BufReader::new(socket);
 
             let handshake_json = r#"{"marionetteProtocol":3,"applicationType":"gecko"}"#;
-            let handshake_msg = format_message(handshake_json);
             socket
-                .write_all(handshake_msg.as_bytes())
+                .write_all(format_message(handshake_json).as_bytes())
                 .await
                 .expect("Failed to write handshake");
 
-            socket.flush().await.expect("Flush handshake");
-            sleep(Duration::from_millis(50)).await;
-
-            // --- Wait to receive the NewSession command from the client ---
-
-            let new_session = response::read(&mut socket)
-                .await
-                .expect("Failed to read ExecuteScript command");
-
-            let (_, command_id, _, _): (u8, u32, String, serde_json::Value) =
-                serde_json::from_str(new_session.as_str()).expect("Response array");
-
-            // --- Send NewSession response ---
-
-            let new_session_json = format!(
-                r#"[1,{command_id},null,{{"sessionId": "test-session-id", "capabilities": {{"browserName": "firefox", "version": "85.0"}}}}]"#,
-            );
-            let new_session_msg = format_message(new_session_json.as_str());
-            socket
-                .write_all(new_session_msg.as_bytes())
-                .await
-                .expect("Failed to write ExecuteScript");
-
-            socket.flush().await.expect("Flush ExecuteScript");
-            sleep(Duration::from_millis(50)).await;
-
-            // --- Wait to receive the ExecuteScript command from the client ---
-
-            let execute_script = response::read(&mut socket)
-                .await
-                .expect("Failed to read ExecuteScript command");
-
-            let (_, command_id, _, value): (u8, u32, String, serde_json::Value) =
-                serde_json::from_str(execute_script.as_str()).expect("Response array");
+            let mut commands = Vec::new();
+            while let Ok(request) = response::read(&mut socket).await {
+                let (_, command_id, name, parameters): (u8, u32, String, Value) =
+                    serde_json::from_str(request.as_str()).expect("Request array");
 
-            assert_eq!(
-                value.to_string(),
-                r#"{"args":[],"script":"return window.title;"}"#
-            );
+                let response_json = format!("[1,{command_id},null,{}]", answer(&name, &parameters));
+                socket
+                    .write_all(format_message(response_json.as_str()).as_bytes())
+                    .await
+                    .expect("Failed to write response");
 
-            // --- Send new ExecuteScript response ---
+                commands.push((name, parameters));
+            }
 
-            let execute_script_json =
-                format!(r#"[1,{command_id},null,{{"value": "Window title"}}]"#,);
-            let execute_script_msg = format_message(execute_script_json.as_str());
-            socket
-                .write_all(execute_script_msg.as_bytes())
-                .await
-                .expect("Failed to write ExecuteScript");
+            commands
+        });
 
-            socket.flush().await.expect("Flush ExecuteScript");
-            sleep(Duration::from_millis(50)).await;
+        (addr, handle)
+    }
 
-            // --- Shutdown
+    fn format_message(body: &str) -> String {
+        format!("{}:{}", body.len(), body)
+    }
 
-            socket.shutdown().await.expect("Server shutdown failed");
-        });
+    #[tokio::test]
+    async fn test_marionette_client() {
+        let (addr, server) = mock_marionette(|name, _| {
+            if name == "WebDriver:NewSession" {
+                r#"{"sessionId": "test-session-id", "capabilities": {"browserName": "firefox", "version": "85.0"}}"#
+                    .to_string()
+            } else {
+                r#"{"value": "Window title"}"#.to_string()
+            }
+        })
+        .await;
 
         // --- Client side: Create a Marionette client ---
 
-        let mut client = Marionette::new(&addr)
+        let client = Marionette::new(&addr)
             .await
             .expect("Marionette::new should succeed");
 
         assert_eq!(client.protocol(), 3);
         assert_eq!(client.session_id(), "test-session-id");
+        assert_eq!(client.capabilities()["browserName"], "firefox");
+        assert_eq!(client.capabilities()["version"], "85.0");
 
         // --- Test to send a command  ---
 
@@ -329,5 +548,289 @@ mod tests {
             .expect("Sending ExecuteScript command should succeed");
 
         assert_eq!(response.value, "Window title");
+
+        drop(client);
+        let commands = server.await.expect("Join server");
+        assert_eq!(commands[1].0, "WebDriver:ExecuteScript");
+        assert_eq!(
+            commands[1].1.to_string(),
+            r#"{"args":[],"script":"return window.title;"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_marionette_with_capabilities() {
+        // The capabilities of the session are the requested ones.
+        let (addr, server) = mock_marionette(|_, capabilities| {
+            format!(r#"{{"sessionId":"test-session-id","capabilities":{capabilities}}}"#)
+        })
+        .await;
+
+        let capabilities = webdriver::CapabilitiesBuilder::new()
+            .accept_insecure_certs(true)
+            .page_load_strategy(webdriver::PageLoadStrategy::Eager);
+        let client = Marionette::with_capabilities(&addr, capabilities)
+            .await
+            .expect("Marionette::with_capabilities should succeed");
+
+        assert_eq!(client.session_id(), "test-session-id");
+
+        drop(client);
+        let commands = server.await.expect("Join server");
+        assert_eq!(commands[0].0, "WebDriver:NewSession");
+        assert_eq!(
+            commands[0].1.to_string(),
+            r#"{"acceptInsecureCerts":true,"pageLoadStrategy":"eager"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_marionette_concurrent_send() {
+        // Answers the NewSession command, then echoes the script of each ExecuteScript command.
+        let (addr, _server) = mock_marionette(|name, parameters| {
+            if name == "WebDriver:NewSession" {
+                r#"{"sessionId":"test-session-id","capabilities":{}}"#.to_string()
+            } else {
+                format!(r#"{{"value":{}}}"#, parameters["script"])
+            }
+        })
+        .await;
+
+        let client = Marionette::new(&addr)
+            .await
+            .expect("Marionette::new should succeed");
+
+        let send_script = |client: Marionette, script: &'static str| async move {
+            let command = webdriver::ExecuteScript::new(webdriver::ExecuteScriptParameters {
+                script: script.to_string(),
+                args: vec![],
+            });
+
+            client.send(&command).await.map(|response| response.value)
+        };
+
+        let (first, second) = tokio::join!(
+            tokio::spawn(send_script(client.clone(), "first")),
+            tokio::spawn(send_script(client.clone(), "second"))
+        );
+
+        assert_eq!(first.expect("Join first").expect("Send first"), "first");
+        assert_eq!(second.expect("Join second").expect("Send second"), "second");
+    }
+
+    #[tokio::test]
+    async fn test_marionette_try_send() {
+        let (addr, _server) = mock_marionette(|name, parameters| {
+            if name == "WebDriver:NewSession" {
+                r#"{"sessionId":"test-session-id","capabilities":{}}"#.to_string()
+            } else {
+                format!(r#"{{"value":{}}}"#, parameters["script"])
+            }
+        })
+        .await;
+
+        let client = Marionette::new(&addr)
+            .await
+            .expect("Marionette::new should succeed");
+        let command = webdriver::ExecuteScript::new(webdriver::ExecuteScriptParameters {
+            script: "ping".to_string(),
+            args: vec![],
+        });
+
+        {
+            let _busy = client.stream.lock().await;
+            assert!(
+                client.try_send(&command).await.is_none(),
+                "A busy connection should not be used"
+            );
+        }
+
+        let response = client
+            .try_send(&command)
+            .await
+            .expect("An idle connection should be used")
+            .expect("Send");
+        assert_eq!(response.value, "ping");
+    }
+
+    #[tokio::test]
+    async fn test_marionette_send_raw() {
+        let (addr, server) = mock_marionette(|name, _| {
+            if name == "WebDriver:NewSession" {
+                r#"{"sessionId":"test-session-id","capabilities":{}}"#.to_string()
+            } else {
+                r#"{"capabilities":{"browserName":"firefox","acceptInsecureCerts":false}}"#
+                    .to_string()
+            }
+        })
+        .await;
+
+        let client = Marionette::new(&addr)
+            .await
+            .expect("Marionette::new should succeed");
+
+        let response = client
+            .send_raw("WebDriver:GetCapabilities", &serde_json::json!({}))
+            .await
+            .expect("Sending a raw command should succeed");
+        assert_eq!(
+            response,
+            serde_json::json!({"capabilities": {"browserName": "firefox", "acceptInsecureCerts": false}})
+        );
+
+        drop(client);
+        let commands = server.await.expect("Join server");
+        assert_eq!(
+            commands[1..],
+            [(
+                "WebDriver:GetCapabilities".to_string(),
+                serde_json::json!({})
+            )]
+        );
+    }
+
+    #[test]
+    fn test_error_is_transient() {
+        let failure = |error: &str| {
+            Error::Request(request::Error::Response(response::Error::CommandFailure(
+                42,
+                response::Failure {
+                    error: error.into(),
+                    message: "test-message".into(),
+                    stacktrace: "test-trace".into(),
+                },
+            )))
+        };
+
+        assert!(
+            Error::Request(request::Error::FailedToWriteRequest(io::Error::from(
+                io::ErrorKind::BrokenPipe
+            )))
+            .is_transient()
+        );
+        assert!(
+            Error::Request(request::Error::Response(
+                response::Error::UnexpectedEndOfResponse
+            ))
+            .is_transient()
+        );
+        assert!(failure("invalid session id").is_transient());
+        assert!(!failure("no such element").is_transient());
+        assert!(
+            !Error::Request(request::Error::CommandIdMismatch {
+                request_id: 1,
+                response_id: 2
+            })
+            .is_transient()
+        );
+    }
+
+    #[test]
+    fn test_error_is_transient_navigation() {
+        let failure = |message: &str| {
+            Error::Request(request::Error::Response(response::Error::CommandFailure(
+                42,
+                response::Failure {
+                    error: "unknown error".into(),
+                    message: message.into(),
+                    stacktrace: "test-trace".into(),
+                },
+            )))
+        };
+
+        assert!(failure("Error: NS_BINDING_ABORTED").is_transient_navigation());
+        assert!(failure("Navigation failed: NS_ERROR_ABORT").is_transient_navigation());
+        assert!(
+            !failure("Reached error page: about:neterror?e=dnsNotFound").is_transient_navigation()
+        );
+        assert!(
+            !failure("Reached error page: about:neterror?e=connectionFailure")
+                .is_transient_navigation()
+        );
+        assert!(
+            !Error::Request(request::Error::Response(
+                response::Error::UnexpectedEndOfResponse
+            ))
+            .is_transient_navigation()
+        );
+    }
+
+    #[test]
+    fn test_backoff_grows_with_jitter() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(500));
+
+        for interval in [100, 200, 400, 500, 500] {
+            let interval = Duration::from_millis(interval);
+            let delay = backoff.next_delay();
+
+            assert!(
+                delay >= interval / 2 && delay <= interval,
+                "Delay {delay:?} should be within the half and the whole of {interval:?}"
+            );
+        }
+
+        let mut backoff = Backoff::new(Duration::from_secs(2), Duration::from_millis(500));
+        assert!(backoff.next_delay() <= Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_connect_backoff_bounds_attempts() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind listener");
+        let addr: SocketAddr = listener.local_addr().expect("Failed to get local address");
+        drop(listener);
+
+        let result = connect(&addr, 1000, 50).await;
+        assert!(
+            matches!(result, Err(Error::ConnectionTimeout { .. })),
+            "Expected ConnectionTimeout, got: {result:?}"
+        );
+
+        logs_assert(|lines: &[&str]| {
+            let delays: Vec<u128> = lines
+                .iter()
+                .filter_map(|line| line.split("Retrying in ").nth(1))
+                .filter_map(|rest| rest.split("ms").next()?.parse().ok())
+                .collect();
+
+            // A fixed 50ms interval would retry about 20 times within the second.
+            if delays.is_empty() || delays.len() > 8 {
+                return Err(format!("Unexpected number of retries: {delays:?}"));
+            }
+            // Until the cap, a delay is at least the previous interval, so never shorter than the previous delay.
+            if delays.windows(2).take(3).any(|pair| pair[1] < pair[0]) {
+                return Err(format!("The delays should grow: {delays:?}"));
+            }
+
+            Ok(())
+        });
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout_honors_budget() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind listener");
+        let addr: SocketAddr = listener.local_addr().expect("Failed to get local address");
+        drop(listener);
+
+        let start = Instant::now();
+        let result = connect(&addr, 250, 200).await;
+        let elapsed = start.elapsed();
+
+        assert!(
+            matches!(result, Err(Error::ConnectionTimeout { .. })),
+            "Expected ConnectionTimeout, got: {result:?}"
+        );
+        assert!(
+            elapsed >= Duration::from_millis(250),
+            "Should wait the whole budget, waited: {elapsed:?}"
+        );
+        assert!(
+            elapsed < Duration::from_millis(350),
+            "Should not wait past the budget, waited: {elapsed:?}"
+        );
     }
 }
@@ -2,11 +2,13 @@
 //!
 //! This module provides functions to write command requests to a TCP stream and send them, waiting for the corresponding response.
 //! It serializes commands to JSON with a length prefix and expects the response to include an identifier matching the request.
+//! The serialized requests are bounded in size, so a huge payload (e.g. the arguments of a script) fails before
+//! being fully built in memory.
 
-use std::{fmt::Debug, io, result};
+use std::{fmt::Debug, io, io::Write, result};
 
 use serde::{Serialize, de::DeserializeOwned};
-use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
 use tracing::debug;
 
 use crate::{command::Command, response};
@@ -17,6 +19,8 @@ pub enum Error {
     ConvertJson(#[source] serde_json::Error),
     #[error("write request to stream failed")]
     FailedToWriteRequest(#[source] io::Error),
+    #[error("request too large: the maximum is {max} bytes")]
+    RequestTooLarge { max: usize },
     #[error("command id mismatch: expected {request_id}, got: {response_id}")]
     CommandIdMismatch { request_id: u32, response_id: u32 },
     #[error(transparent)]
@@ -25,7 +29,32 @@ pub enum Error {
 
 pub type Result<T, E = Error> = result::Result<T, E>;
 
-/// Writes a command request to the provided TCP stream.
+/// The maximum size in bytes of a serialized request written with [`write`] (64 MiB).
+pub const DEFAULT_MAX_REQUEST_SIZE: usize = 64 * 1024 * 1024;
+
+/// A buffer failing the writes beyond its maximum size, so an oversized request is never fully serialized.
+struct BoundedBuffer {
+    bytes: Vec<u8>,
+    max: usize,
+}
+
+impl Write for BoundedBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        if self.bytes.len() + bytes.len() > self.max {
+            return Err(io::Error::other("request too large"));
+        }
+
+        self.bytes.extend_from_slice(bytes);
+
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes a command request to the provided TCP stream, up to [`DEFAULT_MAX_REQUEST_SIZE`] bytes.
 ///
 /// This function creates a new request using [`Command::new_request`], serializes it to JSON,
 /// and writes the message with a length prefix to the TCP stream. The message format is
@@ -43,16 +72,54 @@ pub type Result<T, E = Error> = result::Result<T, E>;
 ///
 /// # Errors
 ///
-/// Returns an [`Error::ConvertJson`] if JSON conversion fails or an [`Error::FailedToWriteRequest`]
-/// if writing to the stream fails.
+/// Returns an [`Error::ConvertJson`] if JSON conversion fails, an [`Error::RequestTooLarge`] if the request
+/// exceeds the maximum size, or an [`Error::FailedToWriteRequest`] if writing to the stream fails.
 pub async fn write<S, C, D>(stream: &mut S, command: C, data: &D) -> Result<u32>
 where
-    S: AsyncRead + AsyncWrite + Unpin,
+    S: AsyncWrite + Unpin,
+    C: Into<String> + Send,
+    D: Serialize + Send + Sync,
+{
+    write_with_max(stream, command, data, DEFAULT_MAX_REQUEST_SIZE).await
+}
+
+/// Writes a command request like [`write`], rejecting the requests larger than `max_request_size` bytes.
+///
+/// The request is serialized in a buffer bounded by the maximum size, so the check happens before
+/// anything is written to the stream, and without building the whole oversized request.
+///
+/// # Errors
+///
+/// Returns an [`Error::ConvertJson`] if JSON conversion fails, an [`Error::RequestTooLarge`] if the serialized
+/// request (without its length prefix) exceeds `max_request_size` bytes, or an [`Error::FailedToWriteRequest`]
+/// if writing to the stream fails.
+pub async fn write_with_max<S, C, D>(
+    stream: &mut S,
+    command: C,
+    data: &D,
+    max_request_size: usize,
+) -> Result<u32>
+where
+    S: AsyncWrite + Unpin,
     C: Into<String> + Send,
     D: Serialize + Send + Sync,
 {
     let request = Command::new_request(command, data);
-    let body = serde_json::to_string(&request).map_err(Error::ConvertJson)?;
+    let mut buffer = BoundedBuffer {
+        bytes: Vec::new(),
+        max: max_request_size,
+    };
+    serde_json::to_writer(&mut buffer, &request).map_err(|error| {
+        if error.is_io() {
+            Error::RequestTooLarge {
+                max: max_request_size,
+            }
+        } else {
+            Error::ConvertJson(error)
+        }
+    })?;
+    // The JSON serializer only writes valid UTF-8.
+    let body = String::from_utf8_lossy(&buffer.bytes);
     let data = format!("{}:{}", body.len(), body);
 
     debug!(?data, "Write request");
@@ -87,13 +154,44 @@ where
 /// or if there is a mismatch between the command IDs in the request and response.
 pub async fn send<S, C, D, T>(stream: &mut S, command: C, data: &D) -> Result<T>
 where
-    S: AsyncRead + AsyncWrite + Unpin,
+    S: AsyncBufRead + AsyncWrite + Unpin,
     C: Into<String> + Send,
     D: Serialize + Send + Sync,
     T: DeserializeOwned + Debug,
 {
-    let request_id = write(stream, command, data).await?;
-    let json_string = response::read(stream).await?;
+    send_with_max(
+        stream,
+        command,
+        data,
+        DEFAULT_MAX_REQUEST_SIZE,
+        response::DEFAULT_MAX_RESPONSE_SIZE,
+    )
+    .await
+}
+
+/// Sends a command request like [`send`], rejecting the requests larger than `max_request_size` bytes
+/// (see [`write_with_max`]) and the responses larger than `max_response_size` bytes.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if the request is too large or writing it fails, the response is too large
+/// (see [`response::read_with_max`]), reading or parsing the response fails, or if there is a mismatch
+/// between the command IDs.
+pub async fn send_with_max<S, C, D, T>(
+    stream: &mut S,
+    command: C,
+    data: &D,
+    max_request_size: usize,
+    max_response_size: usize,
+) -> Result<T>
+where
+    S: AsyncBufRead + AsyncWrite + Unpin,
+    C: Into<String> + Send,
+    D: Serialize + Send + Sync,
+    T: DeserializeOwned + Debug,
+{
+    let request_id = write_with_max(stream, command, data, max_request_size).await?;
+    let json_string = response::read_with_max(stream, max_response_size).await?;
     let (response_id, response) = response::parse(json_string)?;
 
     if request_id == response_id {
@@ -111,7 +209,7 @@ where
 #[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 mod tests {
     use serde_json::Value;
-    use tokio::io::{AsyncReadExt, duplex};
+    use tokio::io::{AsyncReadExt, BufReader, duplex};
 
     use super::*;
 
@@ -139,7 +237,7 @@ mod tests {
             "Length prefix must match JSON body length"
         );
 
-        let expected_json = format!("[0,{command_id},\"test-write\",42]",);
+        let expected_json = format!("[0,{command_id},\"test-write\",42]");
         assert_eq!(parts[1], expected_json);
     }
 
@@ -158,8 +256,27 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_send_success() {
+    async fn test_write_request_too_large() {
         let (mut client, mut server) = duplex(1024);
+        let args = vec!["pantin".repeat(100); 1000];
+
+        let result = write_with_max(&mut client, "test-write-too-large", &args, 1000).await;
+        assert!(
+            matches!(result, Err(Error::RequestTooLarge { max: 1000 })),
+            "Expected RequestTooLarge, got: {result:?}"
+        );
+
+        client.shutdown().await.expect("Client shutdown");
+
+        let mut buf = Vec::new();
+        server.read_to_end(&mut buf).await.expect("Server read");
+        assert!(buf.is_empty(), "Nothing should be written");
+    }
+
+    #[tokio::test]
+    async fn test_send_success() {
+        let (client, server) = duplex(1024);
+        let (mut client, mut server) = (BufReader::new(client), BufReader::new(server));
 
         tokio::spawn(async move {
             let req_msg = response::read(&mut server).await.expect("Server read");
@@ -185,7 +302,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_send_command_id_mismatch() {
-        let (mut client, mut server) = duplex(1024);
+        let (client, server) = duplex(1024);
+        let (mut client, mut server) = (BufReader::new(client), BufReader::new(server));
 
         tokio::spawn(async move {
             let req_msg = response::read(&mut server).await.expect("Server read");
@@ -1,6 +1,6 @@
 //! Module for reading and parsing responses from the Marionette server over a TCP stream.
 //!
-//! This module provides functions to read raw responses asynchronously,
+//! This module provides functions to read raw responses asynchronously from a buffered stream,
 //! convert them into UTF-8 strings, and parse them into strongly-typed responses.
 //!
 //! It also defines error types and structures to handle command failures.
@@ -9,15 +9,13 @@ use std::{fmt::Debug, io, result, string};
 
 use serde::{Deserialize, de::DeserializeOwned};
 use thiserror::Error;
-use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
 use tracing::{debug, error};
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("read byte count failed")]
     ReadByteCount(#[source] io::Error),
-    #[error("expected one byte, got: {count}")]
-    ReadByteCountLength { count: usize },
     #[error("expected byte: {byte}")]
     UnexpectedByte { byte: char },
     #[error("unexpected end of response")]
@@ -30,47 +28,67 @@ pub enum Error {
     ParseResponse(#[source] serde_json::Error),
     #[error("command (id={0}) failed: {1:?}")]
     CommandFailure(u32, Failure),
+    #[error("response too large: {declared} bytes declared, the maximum is {max} bytes")]
+    ResponseTooLarge { declared: usize, max: usize },
 }
 
 pub type Result<T, E = Error> = result::Result<T, E>;
 
+/// The maximum size in bytes of a response read with [`read`] (256 MiB), large enough for a full-page screenshot.
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 256 * 1024 * 1024;
+
 /// Reads the length prefix of a message from the TCP stream.
 ///
-/// The length is encoded as ASCII digits terminated by a colon (`:`). This function reads bytes
-/// until it encounters the colon and returns the accumulated length as a [`usize`].
+/// The length is encoded as ASCII digits terminated by a colon (`:`). This function parses the digits
+/// directly from the stream buffer, consuming them up to and including the colon, and returns the
+/// accumulated length as a [`usize`], saturated at [`usize::MAX`]. The bytes following the colon are left
+/// in the buffer for the body.
 ///
 /// # Errors
 ///
 /// Returns an [`Error`] if the reading fails or an unexpected byte is encountered.
-async fn read_length<S: AsyncRead + Unpin>(stream: &mut S) -> Result<usize> {
+async fn read_length<S: AsyncBufRead + Unpin>(stream: &mut S) -> Result<usize> {
     let mut bytes = 0usize;
 
     loop {
-        let buffer = &mut [0u8];
-        let byte_count = stream.read(buffer).await.map_err(Error::ReadByteCount)?;
-        let byte = match byte_count {
-            1 => Ok(buffer[0]),
-            0 => Err(Error::UnexpectedEndOfResponse),
-            count => Err(Error::ReadByteCountLength { count }),
-        }? as char;
-
-        match byte {
-            '0'..='9' => {
-                bytes *= 10;
-                bytes += byte as usize - '0' as usize;
-            },
-            ':' => break,
-            byte => return Err(Error::UnexpectedByte { byte }),
+        let buffer = stream.fill_buf().await.map_err(Error::ReadByteCount)?;
+
+        if buffer.is_empty() {
+            return Err(Error::UnexpectedEndOfResponse);
+        }
+
+        let mut consumed = 0;
+        let mut complete = false;
+
+        for byte in buffer.iter().map(|byte| *byte as char) {
+            consumed += 1;
+
+            match byte {
+                '0'..='9' => {
+                    bytes = bytes
+                        .saturating_mul(10)
+                        .saturating_add(byte as usize - '0' as usize);
+                },
+                ':' => {
+                    complete = true;
+                    break;
+                },
+                byte => return Err(Error::UnexpectedByte { byte }),
+            }
         }
-    }
 
-    Ok(bytes)
+        stream.consume(consumed);
+
+        if complete {
+            return Ok(bytes);
+        }
+    }
 }
 
 /// Reads a string of a given length from the TCP stream.
 ///
-/// This function continuously reads from the stream until it has read the specified number of bytes,
-/// then converts the byte vector into a UTF-8 string.
+/// This function reads exactly the specified number of bytes from the stream, leaving any following
+/// message in the buffer, then converts the byte vector into a UTF-8 string.
 ///
 /// # Arguments
 ///
@@ -79,39 +97,56 @@ async fn read_length<S: AsyncRead + Unpin>(stream: &mut S) -> Result<usize> {
 /// # Errors
 ///
 /// Returns an [`Error`] if reading fails or the conversion to UTF-8 fails.
-async fn read_string<S: AsyncRead + Unpin>(stream: &mut S, bytes: usize) -> Result<String> {
-    let mut total_byte_read = 0;
-    let buffer = &mut [0u8; 8192];
+async fn read_string<S: AsyncBufRead + Unpin>(stream: &mut S, bytes: usize) -> Result<String> {
     let mut payload = Vec::with_capacity(bytes);
 
-    while total_byte_read < bytes {
-        let byte_read = stream.read(buffer).await.map_err(Error::ReadByte)?;
-
-        if byte_read == 0 {
-            return Err(Error::UnexpectedEndOfResponse);
-        }
-
-        total_byte_read += byte_read;
+    stream
+        .take(bytes as u64)
+        .read_to_end(&mut payload)
+        .await
+        .map_err(Error::ReadByte)?;
 
-        for x in &buffer[..byte_read] {
-            payload.push(*x);
-        }
+    if payload.len() < bytes {
+        return Err(Error::UnexpectedEndOfResponse);
     }
 
     String::from_utf8(payload).map_err(Error::ResponseToString)
 }
 
-/// Reads a complete response from the TCP stream.
+/// Reads a complete response from the TCP stream, up to [`DEFAULT_MAX_RESPONSE_SIZE`] bytes.
 ///
-/// This function first reads the length prefix using [`read_length`],
-/// then reads the corresponding string using [`read_string`].
+/// See [`read_with_max`].
 ///
 /// # Errors
 ///
-/// Returns an [`Error`] if reading fails or the conversion to UTF-8 fails.
-pub async fn read<S: AsyncRead + Unpin>(stream: &mut S) -> Result<String> {
+/// Returns an [`Error`] if reading fails, the response is too large or the conversion to UTF-8 fails.
+pub async fn read<S: AsyncBufRead + Unpin>(stream: &mut S) -> Result<String> {
+    read_with_max(stream, DEFAULT_MAX_RESPONSE_SIZE).await
+}
+
+/// Reads a complete response of at most `max` bytes from the TCP stream.
+///
+/// This function first reads the length prefix using [`read_length`], checks it against the maximum
+/// before allocating anything, then reads the corresponding string using [`read_string`].
+///
+/// # Arguments
+///
+/// * `max` - The maximum size of the response in bytes.
+///
+/// # Errors
+///
+/// Returns an [`Error::ResponseTooLarge`] if the length prefix exceeds the maximum,
+/// or an [`Error`] if reading fails or the conversion to UTF-8 fails.
+pub async fn read_with_max<S: AsyncBufRead + Unpin>(stream: &mut S, max: usize) -> Result<String> {
     let bytes = read_length(stream).await?;
 
+    if bytes > max {
+        return Err(Error::ResponseTooLarge {
+            declared: bytes,
+            max,
+        });
+    }
+
     read_string(stream, bytes).await
 }
 
@@ -175,7 +210,7 @@ pub fn parse<J: AsRef<str> + Debug, T: DeserializeOwned + Debug>(json: J) -> Res
 #[cfg_attr(coverage, coverage(off))]
 #[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 mod tests {
-    use tokio::io::{AsyncWriteExt, DuplexStream, duplex};
+    use tokio::io::{AsyncWriteExt, BufReader, DuplexStream, duplex};
 
     use super::*;
 
@@ -235,7 +270,7 @@ mod tests {
         }
     }
 
-    fn fake_stream(data: Vec<u8>) -> DuplexStream {
+    fn fake_stream(data: Vec<u8>) -> BufReader<DuplexStream> {
         let (mut client, server) = duplex(128);
 
         tokio::spawn(async move {
@@ -245,7 +280,7 @@ mod tests {
                 .expect("Client write");
         });
 
-        server
+        BufReader::new(server)
     }
 
     #[tokio::test]
@@ -324,4 +359,69 @@ mod tests {
         let message = read(&mut server).await.expect("Failed to read message");
         assert_eq!(message, "Hello");
     }
+
+    #[tokio::test]
+    async fn test_read_too_large() {
+        let mut server = fake_stream(b"99999999999999999999999999:{}".to_vec());
+
+        let message = read(&mut server).await;
+        assert!(
+            matches!(
+                message,
+                Err(Error::ResponseTooLarge {
+                    declared: usize::MAX,
+                    max: DEFAULT_MAX_RESPONSE_SIZE
+                })
+            ),
+            "Expected ResponseTooLarge, got: {message:?}"
+        );
+
+        let mut server = fake_stream(b"6:Hello!".to_vec());
+
+        let message = read_with_max(&mut server, 5).await;
+        assert!(
+            matches!(
+                message,
+                Err(Error::ResponseTooLarge {
+                    declared: 6,
+                    max: 5
+                })
+            ),
+            "Expected ResponseTooLarge, got: {message:?}"
+        );
+
+        let mut server = fake_stream(b"5:Hello".to_vec());
+
+        let message = read_with_max(&mut server, 5).await;
+        assert_eq!(message.expect("Failed to read message"), "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_read_pipelined() {
+        let messages = (0..1000)
+            .map(|index| {
+                format!(
+                    r#"[1,{index},null,{{"value":"{}"}}]"#,
+                    "x".repeat(index % 97)
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut data = Vec::new();
+
+        for message in &messages {
+            data.extend_from_slice(format!("{}:{message}", message.len()).as_bytes());
+        }
+
+        let mut server = fake_stream(data);
+
+        for expected in &messages {
+            let message = read(&mut server).await.expect("Failed to read message");
+            assert_eq!(&message, expected);
+        }
+
+        assert!(
+            matches!(read(&mut server).await, Err(Error::UnexpectedEndOfResponse)),
+            "Expected UnexpectedEndOfResponse after the last message"
+        );
+    }
 }
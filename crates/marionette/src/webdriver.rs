@@ -2,16 +2,17 @@
 //!
 //! This module defines a trait for `WebDriver` commands and several concrete command types,
 //! including commands to create a new session, execute scripts, set the window rectangle,
-//! navigate to a URL, find an element, and take a screenshot.
+//! navigate to a URL or through the history, manage windows, frames and dialogs, find elements, and take a screenshot.
 //!
 //! Each command is annotated with the [`WebDriverCommand`] derive macro,
 //! which automates boilerplate code for serializing and deserializing the command messages.
+//! The Marionette specific commands set their name with the `#[command(name = "Marionette:...")]` attribute.
 
 use std::fmt::Debug;
 
 use pantin_derive::WebDriverCommand;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
-use serde_json::{Map, Value};
+use serde_json::{Map, Value, json};
 
 /// A trait representing a `WebDriver` command.
 ///
@@ -25,12 +26,30 @@ pub trait Command {
     fn parameters(&self) -> &Self::Parameters;
 }
 
+// --- Shared command types ---
+
+/// Parameters of the commands that don't take any, serialized as an empty object.
+#[derive(Debug, Default, Serialize)]
+pub struct EmptyParameters {}
+
+/// Response of the commands that don't return any value.
+#[derive(Debug, Deserialize)]
+pub struct EmptyResponse {
+    pub value: (),
+}
+
+/// Response of the commands returning a boolean state.
+#[derive(Debug, Deserialize)]
+pub struct BooleanResponse {
+    pub value: bool,
+}
+
 // --- NewSession command types ---
 
 pub type NewSessionCapabilities = Map<String, Value>;
 pub type NewSessionParameters = Option<NewSessionCapabilities>;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all(deserialize = "camelCase"))]
 pub struct NewSessionResponse {
     pub session_id: String,
@@ -42,6 +61,129 @@ pub struct NewSession {
     parameters: NewSessionParameters,
 }
 
+/// The [page load strategy](https://www.w3.org/TR/webdriver2/#dfn-page-load-strategy) of a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageLoadStrategy {
+    None,
+    Eager,
+    Normal,
+}
+
+/// The kind of [proxy configuration](https://www.w3.org/TR/webdriver2/#dfn-proxy-configuration) of a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyType {
+    Pac,
+    Direct,
+    Autodetect,
+    System,
+    Manual,
+}
+
+/// The [proxy configuration](https://www.w3.org/TR/webdriver2/#dfn-proxy-configuration) of a session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Proxy {
+    pub proxy_type: ProxyType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_autoconfig_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssl_proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socks_proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socks_version: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<Vec<String>>,
+}
+
+impl Proxy {
+    /// Creates a proxy configuration of the given type, without any proxy address.
+    #[must_use]
+    pub const fn new(proxy_type: ProxyType) -> Self {
+        Self {
+            proxy_type,
+            proxy_autoconfig_url: None,
+            http_proxy: None,
+            ssl_proxy: None,
+            socks_proxy: None,
+            socks_version: None,
+            no_proxy: None,
+        }
+    }
+}
+
+/// A builder for the [`NewSessionCapabilities`], which spells the capability keys expected by Marionette.
+///
+/// ```
+/// use pantin_marionette::webdriver::{CapabilitiesBuilder, PageLoadStrategy};
+///
+/// let capabilities = CapabilitiesBuilder::new()
+///     .accept_insecure_certs(true)
+///     .page_load_strategy(PageLoadStrategy::Eager)
+///     .build();
+///
+/// assert_eq!(capabilities["pageLoadStrategy"], "eager");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapabilitiesBuilder {
+    capabilities: NewSessionCapabilities,
+}
+
+impl CapabilitiesBuilder {
+    /// Creates a builder without any capability.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether untrusted and self-signed TLS certificates are accepted (`acceptInsecureCerts`).
+    #[must_use]
+    pub fn accept_insecure_certs(self, accept: bool) -> Self {
+        self.capability("acceptInsecureCerts", json!(accept))
+    }
+
+    /// Sets the page load strategy of the session (`pageLoadStrategy`).
+    #[must_use]
+    pub fn page_load_strategy(self, strategy: PageLoadStrategy) -> Self {
+        self.capability("pageLoadStrategy", json!(strategy))
+    }
+
+    /// Sets the proxy configuration of the session (`proxy`).
+    #[must_use]
+    pub fn proxy(self, proxy: &Proxy) -> Self {
+        self.capability("proxy", json!(proxy))
+    }
+
+    /// Sets the Firefox specific options of the session (`moz:firefoxOptions`).
+    #[must_use]
+    pub fn moz_firefox_options(self, options: Map<String, Value>) -> Self {
+        self.capability("moz:firefoxOptions", Value::Object(options))
+    }
+
+    /// Sets any other capability, replacing the previous value of the same key.
+    #[must_use]
+    pub fn capability<K: Into<String>>(mut self, key: K, value: Value) -> Self {
+        self.capabilities.insert(key.into(), value);
+        self
+    }
+
+    /// Returns the built capabilities.
+    #[must_use]
+    pub fn build(self) -> NewSessionCapabilities {
+        self.capabilities
+    }
+}
+
+impl From<CapabilitiesBuilder> for NewSessionCapabilities {
+    fn from(builder: CapabilitiesBuilder) -> Self {
+        builder.build()
+    }
+}
+
 // --- ExecuteScript command types ---
 
 #[derive(Debug, Serialize)]
@@ -104,6 +246,152 @@ pub struct Navigate {
     parameters: NavigateParameters,
 }
 
+// --- GetCurrentURL command types ---
+
+pub type GetCurrentURLParameters = EmptyParameters;
+
+#[derive(Debug, Deserialize)]
+pub struct GetCurrentURLResponse {
+    pub value: String,
+}
+
+#[derive(Debug, WebDriverCommand)]
+pub struct GetCurrentURL {
+    parameters: GetCurrentURLParameters,
+}
+
+// --- SetTimeouts command types ---
+
+/// Timeouts in milliseconds, only the provided ones are updated.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct SetTimeoutsParameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_load: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub implicit: Option<u64>,
+}
+
+pub type SetTimeoutsResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct SetTimeouts {
+    parameters: SetTimeoutsParameters,
+}
+
+// --- GetTimeouts command types ---
+
+pub type GetTimeoutsParameters = EmptyParameters;
+
+/// Timeouts in milliseconds, the script timeout is `None` when scripts never time out.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct GetTimeoutsResponse {
+    pub script: Option<u64>,
+    pub page_load: u64,
+    pub implicit: u64,
+}
+
+#[derive(Debug, WebDriverCommand)]
+pub struct GetTimeouts {
+    parameters: GetTimeoutsParameters,
+}
+
+// --- Refresh command types ---
+
+pub type RefreshParameters = EmptyParameters;
+pub type RefreshResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct Refresh {
+    parameters: RefreshParameters,
+}
+
+// --- Back command types ---
+
+pub type BackParameters = EmptyParameters;
+pub type BackResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct Back {
+    parameters: BackParameters,
+}
+
+// --- Forward command types ---
+
+pub type ForwardParameters = EmptyParameters;
+pub type ForwardResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct Forward {
+    parameters: ForwardParameters,
+}
+
+// --- GetPageSource command types ---
+
+pub type GetPageSourceParameters = EmptyParameters;
+
+#[derive(Debug, Deserialize)]
+pub struct GetPageSourceResponse {
+    pub value: String,
+}
+
+#[derive(Debug, WebDriverCommand)]
+pub struct GetPageSource {
+    parameters: GetPageSourceParameters,
+}
+
+// --- GetWindowHandle command types ---
+
+pub type GetWindowHandleParameters = EmptyParameters;
+
+#[derive(Debug, Deserialize)]
+pub struct GetWindowHandleResponse {
+    pub value: String,
+}
+
+#[derive(Debug, WebDriverCommand)]
+pub struct GetWindowHandle {
+    parameters: GetWindowHandleParameters,
+}
+
+// --- GetWindowHandles command types ---
+
+pub type GetWindowHandlesParameters = EmptyParameters;
+pub type GetWindowHandlesResponse = Vec<String>;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct GetWindowHandles {
+    parameters: GetWindowHandlesParameters,
+}
+
+// --- CloseWindow command types ---
+
+pub type CloseWindowParameters = EmptyParameters;
+/// The handles of the remaining windows.
+pub type CloseWindowResponse = Vec<String>;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct CloseWindow {
+    parameters: CloseWindowParameters,
+}
+
+// --- SwitchToWindow command types ---
+
+#[derive(Debug, Serialize)]
+pub struct SwitchToWindowParameters {
+    pub handle: String,
+}
+
+pub type SwitchToWindowResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct SwitchToWindow {
+    parameters: SwitchToWindowParameters,
+}
+
 // --- FindElement command types ---
 
 #[derive(Debug, Serialize)]
@@ -120,7 +408,7 @@ pub struct FindElementParameters {
     pub value: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Element {
     #[serde(rename = "element-6066-11e4-a52e-4f735466cecf")]
     pub id: String,
@@ -136,148 +424,902 @@ pub struct FindElement {
     pub parameters: FindElementParameters,
 }
 
-// --- TakeScreenshot command types ---
+// --- FindElements command types ---
 
-#[must_use]
-#[derive(Debug, Serialize)]
-pub struct TakeScreenshotParameters {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    full: Option<bool>,
-    #[serde(skip_serializing_if = "Option::is_none", rename = "id")]
-    element_id: Option<String>,
+pub type FindElementsParameters = FindElementParameters;
+
+/// Marionette answers with the bare list of the matching elements.
+pub type FindElementsResponse = Vec<Element>;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct FindElements {
+    pub parameters: FindElementsParameters,
 }
 
-impl TakeScreenshotParameters {
-    pub const fn new(full: Option<bool>, element_id: Option<String>) -> Self {
-        Self { full, element_id }
-    }
+// --- GetActiveElement command types ---
 
-    pub const fn full() -> Self {
-        Self::new(Some(true), None)
-    }
+pub type GetActiveElementParameters = EmptyParameters;
 
-    pub const fn viewport() -> Self {
-        Self::new(Some(false), None)
-    }
+/// The element of the document that currently has the focus.
+pub type GetActiveElementResponse = FindElementResponse;
 
-    pub const fn element(id: String) -> Self {
-        Self::new(Some(false), Some(id))
-    }
+#[derive(Debug, WebDriverCommand)]
+pub struct GetActiveElement {
+    parameters: GetActiveElementParameters,
+}
+
+// --- GetElementAttribute command types ---
+
+#[derive(Debug, Serialize)]
+pub struct GetElementAttributeParameters {
+    pub id: String,
+    pub name: String,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct TakeScreenshotResponse {
-    #[serde(rename = "value")]
-    pub base64_png: String,
+pub struct GetElementAttributeResponse {
+    pub value: Option<String>,
 }
 
 #[derive(Debug, WebDriverCommand)]
-pub struct TakeScreenshot {
-    pub parameters: TakeScreenshotParameters,
+pub struct GetElementAttribute {
+    parameters: GetElementAttributeParameters,
 }
 
-#[cfg(test)]
-#[cfg_attr(coverage, coverage(off))]
-#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
-mod tests {
-    use super::*;
+// --- GetElementProperty command types ---
 
-    #[test]
-    fn test_new_session() {
-        let command = NewSession::new(Some(Map::new()));
+#[derive(Debug, Serialize)]
+pub struct GetElementPropertyParameters {
+    pub id: String,
+    pub name: String,
+}
 
-        assert_eq!(command.name(), "WebDriver:NewSession");
-        assert_eq!(command.parameters(), &Some(Map::new()));
+#[derive(Debug, Deserialize)]
+pub struct GetElementPropertyResponse {
+    pub value: Value,
+}
 
-        let json_data = r#"
-        {
-            "sessionId": "abc123",
-            "capabilities": {
-                "browserName": "firefox",
-                "version": "85.0"
-            }
-        }
-        "#;
-        let response: NewSessionResponse =
-            serde_json::from_str(json_data).expect("Deserialization should succeed");
-        let capabilities = response.capabilities;
+#[derive(Debug, WebDriverCommand)]
+pub struct GetElementProperty {
+    parameters: GetElementPropertyParameters,
+}
 
-        assert_eq!(response.session_id, "abc123");
-        assert_eq!(
-            capabilities.get("browserName").unwrap(),
-            &Value::String("firefox".to_string())
-        );
-        assert_eq!(
-            capabilities.get("version").unwrap(),
-            &Value::String("85.0".to_string())
-        );
-    }
+// --- GetElementText command types ---
 
-    #[test]
-    fn test_execute_script() {
-        let command = ExecuteScript::new(ExecuteScriptParameters {
-            script: "return 42;".to_string(),
-            args: vec![],
-        });
+#[derive(Debug, Serialize)]
+pub struct GetElementTextParameters {
+    pub id: String,
+}
 
-        assert_eq!(command.name(), "WebDriver:ExecuteScript");
-        assert_eq!(command.parameters().script, "return 42;");
-        assert!(command.parameters().args.is_empty());
+#[derive(Debug, Deserialize)]
+pub struct GetElementTextResponse {
+    pub value: String,
+}
 
-        let json_data = r#"{"value":42}"#;
-        let response: ExecuteScriptResponse =
-            serde_json::from_str(json_data).expect("Deserialization should succeed");
+#[derive(Debug, WebDriverCommand)]
+pub struct GetElementText {
+    parameters: GetElementTextParameters,
+}
 
-        assert_eq!(response.value, 42);
-    }
+// --- GetElementCSSValue command types ---
 
-    #[test]
-    fn test_execute_set_window_rect() {
-        let command = SetWindowRect::new(SetWindowRectParameters {
-            x: Some(100),
-            y: Some(200),
-            width: Some(800),
-            height: Some(600),
-        });
+#[derive(Debug, Serialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct GetElementCSSValueParameters {
+    pub id: String,
+    pub property_name: String,
+}
 
-        assert_eq!(command.name(), "WebDriver:SetWindowRect");
-        assert_eq!(command.parameters().x, Some(100));
-        assert_eq!(command.parameters().y, Some(200));
-        assert_eq!(command.parameters().width, Some(800));
-        assert_eq!(command.parameters().height, Some(600));
+#[derive(Debug, Deserialize)]
+pub struct GetElementCSSValueResponse {
+    pub value: String,
+}
 
-        let json_data = r#"
-        {
-            "x": 100,
-            "y": 200,
-            "width": 800,
-            "height": 600
-        }
-        "#;
-        let response: SetWindowRectResponse =
-            serde_json::from_str(json_data).expect("Deserialization should succeed");
+#[derive(Debug, WebDriverCommand)]
+pub struct GetElementCSSValue {
+    parameters: GetElementCSSValueParameters,
+}
 
-        assert_eq!(response.x, 100);
+// --- ElementClear command types ---
+
+#[derive(Debug, Serialize)]
+pub struct ElementClearParameters {
+    pub id: String,
+}
+
+pub type ElementClearResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct ElementClear {
+    parameters: ElementClearParameters,
+}
+
+// --- ElementClick command types ---
+
+#[derive(Debug, Serialize)]
+pub struct ElementClickParameters {
+    pub id: String,
+}
+
+pub type ElementClickResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct ElementClick {
+    parameters: ElementClickParameters,
+}
+
+// --- IsElementEnabled command types ---
+
+#[derive(Debug, Serialize)]
+pub struct IsElementEnabledParameters {
+    pub id: String,
+}
+
+pub type IsElementEnabledResponse = BooleanResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct IsElementEnabled {
+    parameters: IsElementEnabledParameters,
+}
+
+// --- IsElementSelected command types ---
+
+#[derive(Debug, Serialize)]
+pub struct IsElementSelectedParameters {
+    pub id: String,
+}
+
+pub type IsElementSelectedResponse = BooleanResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct IsElementSelected {
+    parameters: IsElementSelectedParameters,
+}
+
+// --- IsElementDisplayed command types ---
+
+#[derive(Debug, Serialize)]
+pub struct IsElementDisplayedParameters {
+    pub id: String,
+}
+
+pub type IsElementDisplayedResponse = BooleanResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct IsElementDisplayed {
+    parameters: IsElementDisplayedParameters,
+}
+
+// --- SwitchToFrame command types ---
+
+#[derive(Debug, Serialize)]
+pub struct SwitchToFrameParameters {
+    pub id: Element,
+}
+
+pub type SwitchToFrameResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct SwitchToFrame {
+    parameters: SwitchToFrameParameters,
+}
+
+// --- SwitchToParentFrame command types ---
+
+pub type SwitchToParentFrameParameters = EmptyParameters;
+pub type SwitchToParentFrameResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct SwitchToParentFrame {
+    parameters: SwitchToParentFrameParameters,
+}
+
+// --- DismissAlert command types ---
+
+pub type DismissAlertParameters = EmptyParameters;
+pub type DismissAlertResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct DismissAlert {
+    parameters: DismissAlertParameters,
+}
+
+// --- AcceptAlert command types ---
+
+pub type AcceptAlertParameters = EmptyParameters;
+pub type AcceptAlertResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct AcceptAlert {
+    parameters: AcceptAlertParameters,
+}
+
+// --- GetAlertText command types ---
+
+pub type GetAlertTextParameters = EmptyParameters;
+
+#[derive(Debug, Deserialize)]
+pub struct GetAlertTextResponse {
+    pub value: String,
+}
+
+#[derive(Debug, WebDriverCommand)]
+pub struct GetAlertText {
+    parameters: GetAlertTextParameters,
+}
+
+// --- SendAlertText command types ---
+
+#[derive(Debug, Serialize)]
+pub struct SendAlertTextParameters {
+    pub text: String,
+}
+
+pub type SendAlertTextResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct SendAlertText {
+    parameters: SendAlertTextParameters,
+}
+
+// --- DeleteAllCookies command types ---
+
+pub type DeleteAllCookiesParameters = EmptyParameters;
+pub type DeleteAllCookiesResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct DeleteAllCookies {
+    parameters: DeleteAllCookiesParameters,
+}
+
+// --- DeleteCookie command types ---
+
+#[derive(Debug, Serialize)]
+pub struct DeleteCookieParameters {
+    pub name: String,
+}
+
+pub type DeleteCookieResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct DeleteCookie {
+    parameters: DeleteCookieParameters,
+}
+
+// --- SetContext command types ---
+
+/// The context the commands run in: the web content, or the privileged browser chrome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Context {
+    Chrome,
+    Content,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetContextParameters {
+    pub value: Context,
+}
+
+pub type SetContextResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+#[command(name = "Marionette:SetContext")]
+pub struct SetContext {
+    parameters: SetContextParameters,
+}
+
+// --- PerformActions command types ---
+
+#[derive(Debug, Serialize)]
+pub struct PerformActionsParameters {
+    pub actions: Vec<ActionSequence>,
+}
+
+/// A sequence of actions performed by one input source, as defined by the W3C actions API.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all(serialize = "camelCase"))]
+pub enum ActionSequence {
+    None {
+        id: String,
+        actions: Vec<PauseAction>,
+    },
+    Key {
+        id: String,
+        actions: Vec<KeyAction>,
+    },
+    Pointer {
+        id: String,
+        parameters: PointerParameters,
+        actions: Vec<PointerAction>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct PointerParameters {
+    pub pointer_type: PointerType,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all(serialize = "lowercase"))]
+pub enum PointerType {
+    Mouse,
+    Pen,
+    Touch,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all(serialize = "camelCase"))]
+pub enum PauseAction {
+    Pause {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        duration: Option<u64>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all(serialize = "camelCase"))]
+pub enum KeyAction {
+    Pause {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        duration: Option<u64>,
+    },
+    KeyDown {
+        value: String,
+    },
+    KeyUp {
+        value: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all(serialize = "camelCase"))]
+pub enum PointerAction {
+    Pause {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        duration: Option<u64>,
+    },
+    PointerDown {
+        button: u8,
+    },
+    PointerUp {
+        button: u8,
+    },
+    PointerMove {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        duration: Option<u64>,
+        origin: PointerOrigin,
+        x: i64,
+        y: i64,
+    },
+}
+
+/// The origin of a pointer move, the coordinates are relative to it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all(serialize = "lowercase"))]
+pub enum PointerOrigin {
+    Viewport,
+    Pointer,
+    #[serde(untagged)]
+    Element(Element),
+}
+
+pub type PerformActionsResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct PerformActions {
+    parameters: PerformActionsParameters,
+}
+
+// --- ReleaseActions command types ---
+
+pub type ReleaseActionsParameters = EmptyParameters;
+pub type ReleaseActionsResponse = EmptyResponse;
+
+#[derive(Debug, WebDriverCommand)]
+pub struct ReleaseActions {
+    parameters: ReleaseActionsParameters,
+}
+
+// --- TakeScreenshot command types ---
+
+#[must_use]
+#[derive(Debug, Serialize)]
+pub struct TakeScreenshotParameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    full: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "id")]
+    element_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scroll: Option<bool>,
+}
+
+impl TakeScreenshotParameters {
+    pub const fn new(full: Option<bool>, element_id: Option<String>) -> Self {
+        Self {
+            full,
+            element_id,
+            scroll: None,
+        }
+    }
+
+    pub const fn full() -> Self {
+        Self::new(Some(true), None)
+    }
+
+    pub const fn viewport() -> Self {
+        Self::new(Some(false), None)
+    }
+
+    /// Captures the element, scrolled into view first.
+    pub const fn element(id: String) -> Self {
+        Self {
+            full: Some(false),
+            element_id: Some(id),
+            scroll: Some(true),
+        }
+    }
+
+    /// Returns a builder of screenshot parameters, capturing the viewport by default.
+    ///
+    /// ```
+    /// use pantin_marionette::webdriver::TakeScreenshotParameters;
+    ///
+    /// let parameters = TakeScreenshotParameters::builder()
+    ///     .element("element-42".into())
+    ///     .scroll(false)
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     serde_json::to_string(&parameters).unwrap(),
+    ///     r#"{"full":false,"id":"element-42","scroll":false}"#
+    /// );
+    /// ```
+    #[must_use]
+    pub fn builder() -> TakeScreenshotParametersBuilder {
+        TakeScreenshotParametersBuilder::default()
+    }
+}
+
+/// A builder for the [`TakeScreenshotParameters`], see [`TakeScreenshotParameters::builder`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TakeScreenshotParametersBuilder {
+    full: bool,
+    element_id: Option<String>,
+    scroll: Option<bool>,
+}
+
+impl TakeScreenshotParametersBuilder {
+    /// Captures the whole document instead of the viewport.
+    #[must_use]
+    pub fn full(mut self) -> Self {
+        self.full = true;
+        self.element_id = None;
+        self
+    }
+
+    /// Captures the element with the given identifier instead of the viewport.
+    #[must_use]
+    pub fn element(mut self, id: String) -> Self {
+        self.full = false;
+        self.element_id = Some(id);
+        self
+    }
+
+    /// Sets whether the element is scrolled into view before the capture (default: `true` for an element,
+    /// left to Marionette otherwise).
+    #[must_use]
+    pub const fn scroll(mut self, scroll: bool) -> Self {
+        self.scroll = Some(scroll);
+        self
+    }
+
+    /// Returns the built parameters.
+    pub fn build(self) -> TakeScreenshotParameters {
+        let scroll = self
+            .scroll
+            .or_else(|| self.element_id.is_some().then_some(true));
+
+        TakeScreenshotParameters {
+            full: Some(self.full),
+            element_id: self.element_id,
+            scroll,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TakeScreenshotResponse {
+    #[serde(rename = "value")]
+    pub base64_png: String,
+}
+
+#[derive(Debug, WebDriverCommand)]
+pub struct TakeScreenshot {
+    pub parameters: TakeScreenshotParameters,
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_builder() {
+        let capabilities = CapabilitiesBuilder::new()
+            .accept_insecure_certs(true)
+            .page_load_strategy(PageLoadStrategy::None)
+            .proxy(&Proxy {
+                http_proxy: Some("127.0.0.1:8080".to_string()),
+                no_proxy: Some(vec!["localhost".to_string()]),
+                ..Proxy::new(ProxyType::Manual)
+            })
+            .moz_firefox_options(Map::from_iter([(
+                "prefs".to_string(),
+                serde_json::json!({"dom.disable_open_during_load": false}),
+            )]))
+            .build();
+
+        assert_eq!(
+            Value::Object(capabilities),
+            serde_json::json!({
+                "acceptInsecureCerts": true,
+                "pageLoadStrategy": "none",
+                "proxy": {
+                    "proxyType": "manual",
+                    "httpProxy": "127.0.0.1:8080",
+                    "noProxy": ["localhost"]
+                },
+                "moz:firefoxOptions": {
+                    "prefs": {"dom.disable_open_during_load": false}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_capabilities_builder_overrides() {
+        let capabilities: NewSessionCapabilities = CapabilitiesBuilder::new()
+            .page_load_strategy(PageLoadStrategy::Eager)
+            .page_load_strategy(PageLoadStrategy::Normal)
+            .into();
+
+        assert_eq!(
+            Value::Object(capabilities),
+            serde_json::json!({"pageLoadStrategy": "normal"})
+        );
+        assert!(CapabilitiesBuilder::new().build().is_empty());
+    }
+
+    #[test]
+    fn test_new_session() {
+        let command = NewSession::new(Some(Map::new()));
+
+        assert_eq!(command.name(), "WebDriver:NewSession");
+        assert_eq!(command.parameters(), &Some(Map::new()));
+
+        let json_data = r#"
+        {
+            "sessionId": "abc123",
+            "capabilities": {
+                "browserName": "firefox",
+                "version": "85.0"
+            }
+        }
+        "#;
+        let response: NewSessionResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+        let capabilities = response.capabilities;
+
+        assert_eq!(response.session_id, "abc123");
+        assert_eq!(
+            capabilities.get("browserName").unwrap(),
+            &Value::String("firefox".to_string())
+        );
+        assert_eq!(
+            capabilities.get("version").unwrap(),
+            &Value::String("85.0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_execute_script() {
+        let command = ExecuteScript::new(ExecuteScriptParameters {
+            script: "return 42;".to_string(),
+            args: vec![],
+        });
+
+        assert_eq!(command.name(), "WebDriver:ExecuteScript");
+        assert_eq!(command.parameters().script, "return 42;");
+        assert!(command.parameters().args.is_empty());
+
+        let json_data = r#"{"value":42}"#;
+        let response: ExecuteScriptResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, 42);
+    }
+
+    #[test]
+    fn test_execute_set_window_rect() {
+        let command = SetWindowRect::new(SetWindowRectParameters {
+            x: Some(100),
+            y: Some(200),
+            width: Some(800),
+            height: Some(600),
+        });
+
+        assert_eq!(command.name(), "WebDriver:SetWindowRect");
+        assert_eq!(command.parameters().x, Some(100));
+        assert_eq!(command.parameters().y, Some(200));
+        assert_eq!(command.parameters().width, Some(800));
+        assert_eq!(command.parameters().height, Some(600));
+
+        let json_data = r#"
+        {
+            "x": 100,
+            "y": 200,
+            "width": 800,
+            "height": 600
+        }
+        "#;
+        let response: SetWindowRectResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.x, 100);
         assert_eq!(response.y, 200);
         assert_eq!(response.width, 800);
         assert_eq!(response.height, 600);
     }
 
     #[test]
-    #[allow(clippy::unit_cmp)]
-    fn test_navigate() {
-        let command = Navigate::new(NavigateParameters {
-            url: "http://example.com".into(),
-        });
+    fn test_set_window_rect_position_only() {
+        let command = SetWindowRect::new(SetWindowRectParameters {
+            x: Some(10),
+            y: Some(20),
+            width: None,
+            height: None,
+        });
+
+        let json =
+            serde_json::to_value(command.parameters()).expect("Serialization should succeed");
+
+        assert_eq!(json, serde_json::json!({ "x": 10, "y": 20 }));
+    }
+
+    #[test]
+    #[allow(clippy::unit_cmp)]
+    fn test_navigate() {
+        let command = Navigate::new(NavigateParameters {
+            url: "http://example.com".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:Navigate");
+        assert_eq!(command.parameters().url, "http://example.com");
+
+        let json_data = r#"{"value":null}"#;
+        let response: NavigateResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, ());
+    }
+
+    #[test]
+    fn test_get_timeouts() {
+        let command = GetTimeouts::new(GetTimeoutsParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:GetTimeouts");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            "{}"
+        );
+
+        let json_data = r#"{"implicit":0,"pageLoad":300000,"script":30000}"#;
+        let response: GetTimeoutsResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.script, Some(30_000));
+        assert_eq!(response.page_load, 300_000);
+        assert_eq!(response.implicit, 0);
+
+        let json_data = r#"{"implicit":0,"pageLoad":300000,"script":null}"#;
+        let response: GetTimeoutsResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.script, None);
+
+        let json_data = r#"{"implicit":0,"pageLoad":300000}"#;
+        assert!(
+            serde_json::from_str::<GetTimeoutsResponse>(json_data).is_ok(),
+            "A missing script timeout should be accepted"
+        );
+        assert!(
+            serde_json::from_str::<GetTimeoutsResponse>(r#"{"script":30000}"#).is_err(),
+            "The page load and implicit timeouts are required"
+        );
+    }
+
+    #[test]
+    #[allow(clippy::unit_cmp)]
+    fn test_set_timeouts() {
+        let command = SetTimeouts::new(SetTimeoutsParameters {
+            page_load: Some(5000),
+            ..SetTimeoutsParameters::default()
+        });
+
+        assert_eq!(command.name(), "WebDriver:SetTimeouts");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"pageLoad":5000}"#
+        );
+
+        let command = SetTimeouts::new(SetTimeoutsParameters {
+            script: Some(1000),
+            page_load: Some(2000),
+            implicit: Some(0),
+        });
+
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"script":1000,"pageLoad":2000,"implicit":0}"#
+        );
+
+        let json_data = r#"{"value":null}"#;
+        let response: SetTimeoutsResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, ());
+    }
+
+    #[test]
+    #[allow(clippy::unit_cmp)]
+    fn test_refresh() {
+        let command = Refresh::new(RefreshParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:Refresh");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            "{}"
+        );
+
+        let json_data = r#"{"value":null}"#;
+        let response: RefreshResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, ());
+    }
+
+    #[test]
+    #[allow(clippy::unit_cmp)]
+    fn test_back() {
+        let command = Back::new(BackParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:Back");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            "{}"
+        );
+
+        let json_data = r#"{"value":null}"#;
+        let response: BackResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, ());
+    }
+
+    #[test]
+    #[allow(clippy::unit_cmp)]
+    fn test_forward() {
+        let command = Forward::new(ForwardParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:Forward");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            "{}"
+        );
+
+        let json_data = r#"{"value":null}"#;
+        let response: ForwardResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, ());
+    }
+
+    #[test]
+    fn test_get_current_url() {
+        let command = GetCurrentURL::new(GetCurrentURLParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:GetCurrentURL");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            "{}"
+        );
+
+        let json_data = r#"{"value":"https://example.com/"}"#;
+        let response: GetCurrentURLResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, "https://example.com/");
+    }
+
+    #[test]
+    fn test_get_page_source() {
+        let command = GetPageSource::new(GetPageSourceParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:GetPageSource");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            "{}"
+        );
+
+        let json_data = r#"{"value":"<html><head></head><body>Hello</body></html>"}"#;
+        let response: GetPageSourceResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(
+            response.value,
+            "<html><head></head><body>Hello</body></html>"
+        );
+    }
+
+    #[test]
+    fn test_get_window_handle() {
+        let command = GetWindowHandle::new(GetWindowHandleParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:GetWindowHandle");
+
+        let json_data = r#"{"value":"handle-1"}"#;
+        let response: GetWindowHandleResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, "handle-1");
+    }
+
+    #[test]
+    fn test_get_window_handles() {
+        let command = GetWindowHandles::new(GetWindowHandlesParameters::default());
 
-        assert_eq!(command.name(), "WebDriver:Navigate");
-        assert_eq!(command.parameters().url, "http://example.com");
+        assert_eq!(command.name(), "WebDriver:GetWindowHandles");
 
-        let json_data = r#"{"value":null}"#;
-        let response: NavigateResponse =
+        let json_data = r#"["handle-1","handle-2"]"#;
+        let response: GetWindowHandlesResponse =
             serde_json::from_str(json_data).expect("Deserialization should succeed");
 
-        assert_eq!(response.value, ());
+        assert_eq!(response, ["handle-1", "handle-2"]);
+    }
+
+    #[test]
+    fn test_close_window() {
+        let command = CloseWindow::new(CloseWindowParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:CloseWindow");
+
+        let json_data = r#"["handle-1"]"#;
+        let response: CloseWindowResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response, ["handle-1"]);
+
+        let json_data = "[]";
+        let response: CloseWindowResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert!(response.is_empty());
+    }
+
+    #[test]
+    fn test_switch_to_window() {
+        let command = SwitchToWindow::new(SwitchToWindowParameters {
+            handle: "handle-2".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:SwitchToWindow");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"handle":"handle-2"}"#
+        );
     }
 
     #[test]
@@ -324,6 +1366,466 @@ mod tests {
         assert_eq!(response.value.id, "element-id-test");
     }
 
+    #[test]
+    fn test_get_active_element() {
+        let command = GetActiveElement::new(GetActiveElementParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:GetActiveElement");
+
+        let json_data = r#"{"value":{"element-6066-11e4-a52e-4f735466cecf":"element-id-focused"}}"#;
+        let response: GetActiveElementResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value.id, "element-id-focused");
+    }
+
+    #[test]
+    fn test_set_context() {
+        let command = SetContext::new(SetContextParameters {
+            value: Context::Chrome,
+        });
+
+        assert_eq!(command.name(), "Marionette:SetContext");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"value":"chrome"}"#
+        );
+
+        let command = SetContext::new(SetContextParameters {
+            value: Context::Content,
+        });
+
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"value":"content"}"#
+        );
+    }
+
+    #[test]
+    fn test_find_elements() {
+        let command = FindElements::new(FindElementsParameters {
+            using: FindElementUsing::CssSelector,
+            value: ".item".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:FindElements");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"using":"css selector","value":".item"}"#
+        );
+
+        let json_data = r#"[
+            {"element-6066-11e4-a52e-4f735466cecf":"element-1"},
+            {"element-6066-11e4-a52e-4f735466cecf":"element-2"}
+        ]"#;
+        let response: FindElementsResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+        let ids: Vec<_> = response.into_iter().map(|element| element.id).collect();
+
+        assert_eq!(ids, ["element-1", "element-2"]);
+    }
+
+    #[test]
+    fn test_get_element_attribute() {
+        let command = GetElementAttribute::new(GetElementAttributeParameters {
+            id: "element-42".into(),
+            name: "src".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:GetElementAttribute");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"id":"element-42","name":"src"}"#
+        );
+
+        let json_data = r#"{"value":"image.png"}"#;
+        let response: GetElementAttributeResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, Some("image.png".into()));
+
+        let json_data = r#"{"value":null}"#;
+        let response: GetElementAttributeResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, None);
+    }
+
+    #[test]
+    fn test_get_element_property() {
+        let command = GetElementProperty::new(GetElementPropertyParameters {
+            id: "element-42".into(),
+            name: "value".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:GetElementProperty");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"id":"element-42","name":"value"}"#
+        );
+
+        let json_data = r#"{"value":"typed text"}"#;
+        let response: GetElementPropertyResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, "typed text");
+
+        let json_data = r#"{"value":null}"#;
+        let response: GetElementPropertyResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, Value::Null);
+    }
+
+    #[test]
+    fn test_get_element_text() {
+        let command = GetElementText::new(GetElementTextParameters {
+            id: "element-42".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:GetElementText");
+        assert_eq!(command.parameters().id, "element-42");
+
+        let json_data = r#"{"value":"Hello world"}"#;
+        let response: GetElementTextResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, "Hello world");
+    }
+
+    #[test]
+    fn test_get_element_css_value() {
+        let command = GetElementCSSValue::new(GetElementCSSValueParameters {
+            id: "element-42".into(),
+            property_name: "background-color".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:GetElementCSSValue");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"id":"element-42","propertyName":"background-color"}"#
+        );
+
+        let json_data = r#"{"value":"rgb(255, 255, 255)"}"#;
+        let response: GetElementCSSValueResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, "rgb(255, 255, 255)");
+    }
+
+    #[test]
+    #[allow(clippy::unit_cmp)]
+    fn test_element_clear() {
+        let command = ElementClear::new(ElementClearParameters {
+            id: "element-42".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:ElementClear");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"id":"element-42"}"#
+        );
+
+        let json_data = r#"{"value":null}"#;
+        let response: ElementClearResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, ());
+    }
+
+    #[test]
+    #[allow(clippy::unit_cmp)]
+    fn test_element_click() {
+        let command = ElementClick::new(ElementClickParameters {
+            id: "element-42".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:ElementClick");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"id":"element-42"}"#
+        );
+
+        let json_data = r#"{"value":null}"#;
+        let response: ElementClickResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, ());
+    }
+
+    #[test]
+    fn test_is_element_enabled() {
+        let command = IsElementEnabled::new(IsElementEnabledParameters {
+            id: "element-42".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:IsElementEnabled");
+        assert_eq!(command.parameters().id, "element-42");
+
+        let response: IsElementEnabledResponse =
+            serde_json::from_str(r#"{"value":true}"#).expect("Deserialization should succeed");
+        assert!(response.value);
+
+        let response: IsElementEnabledResponse =
+            serde_json::from_str(r#"{"value":false}"#).expect("Deserialization should succeed");
+        assert!(!response.value);
+    }
+
+    #[test]
+    fn test_is_element_selected() {
+        let command = IsElementSelected::new(IsElementSelectedParameters {
+            id: "element-42".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:IsElementSelected");
+        assert_eq!(command.parameters().id, "element-42");
+
+        let response: IsElementSelectedResponse =
+            serde_json::from_str(r#"{"value":true}"#).expect("Deserialization should succeed");
+        assert!(response.value);
+
+        let response: IsElementSelectedResponse =
+            serde_json::from_str(r#"{"value":false}"#).expect("Deserialization should succeed");
+        assert!(!response.value);
+    }
+
+    #[test]
+    fn test_is_element_displayed() {
+        let command = IsElementDisplayed::new(IsElementDisplayedParameters {
+            id: "element-42".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:IsElementDisplayed");
+        assert_eq!(command.parameters().id, "element-42");
+
+        let response: IsElementDisplayedResponse =
+            serde_json::from_str(r#"{"value":true}"#).expect("Deserialization should succeed");
+        assert!(response.value);
+
+        let response: IsElementDisplayedResponse =
+            serde_json::from_str(r#"{"value":false}"#).expect("Deserialization should succeed");
+        assert!(!response.value);
+    }
+
+    #[test]
+    fn test_switch_to_frame() {
+        let command = SwitchToFrame::new(SwitchToFrameParameters {
+            id: Element {
+                id: "frame-42".into(),
+            },
+        });
+
+        assert_eq!(command.name(), "WebDriver:SwitchToFrame");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"id":{"element-6066-11e4-a52e-4f735466cecf":"frame-42"}}"#
+        );
+    }
+
+    #[test]
+    fn test_switch_to_parent_frame() {
+        let command = SwitchToParentFrame::new(SwitchToParentFrameParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:SwitchToParentFrame");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_dismiss_and_accept_alert() {
+        let command = DismissAlert::new(DismissAlertParameters::default());
+        assert_eq!(command.name(), "WebDriver:DismissAlert");
+
+        let command = AcceptAlert::new(AcceptAlertParameters::default());
+        assert_eq!(command.name(), "WebDriver:AcceptAlert");
+    }
+
+    #[test]
+    fn test_get_alert_text() {
+        let command = GetAlertText::new(GetAlertTextParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:GetAlertText");
+
+        let json_data = r#"{"value":"Are you sure?"}"#;
+        let response: GetAlertTextResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, "Are you sure?");
+    }
+
+    #[test]
+    fn test_send_alert_text() {
+        let command = SendAlertText::new(SendAlertTextParameters {
+            text: "hello".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:SendAlertText");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"text":"hello"}"#
+        );
+    }
+
+    #[test]
+    #[allow(clippy::unit_cmp)]
+    fn test_delete_all_cookies() {
+        let command = DeleteAllCookies::new(DeleteAllCookiesParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:DeleteAllCookies");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            "{}"
+        );
+
+        let json_data = r#"{"value":null}"#;
+        let response: DeleteAllCookiesResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, ());
+    }
+
+    #[test]
+    #[allow(clippy::unit_cmp)]
+    fn test_delete_cookie() {
+        let command = DeleteCookie::new(DeleteCookieParameters {
+            name: "session".into(),
+        });
+
+        assert_eq!(command.name(), "WebDriver:DeleteCookie");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            r#"{"name":"session"}"#
+        );
+
+        let json_data = r#"{"value":null}"#;
+        let response: DeleteCookieResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, ());
+    }
+
+    #[test]
+    fn test_perform_actions_hover() {
+        let command = PerformActions::new(PerformActionsParameters {
+            actions: vec![ActionSequence::Pointer {
+                id: "mouse".into(),
+                parameters: PointerParameters {
+                    pointer_type: PointerType::Mouse,
+                },
+                actions: vec![PointerAction::PointerMove {
+                    duration: Some(0),
+                    origin: PointerOrigin::Element(Element {
+                        id: "element-42".into(),
+                    }),
+                    x: 0,
+                    y: 0,
+                }],
+            }],
+        });
+
+        assert_eq!(command.name(), "WebDriver:PerformActions");
+        assert_eq!(
+            serde_json::to_value(command.parameters()).expect("Serialization should succeed"),
+            serde_json::json!({
+                "actions": [{
+                    "type": "pointer",
+                    "id": "mouse",
+                    "parameters": { "pointerType": "mouse" },
+                    "actions": [{
+                        "type": "pointerMove",
+                        "duration": 0,
+                        "origin": { "element-6066-11e4-a52e-4f735466cecf": "element-42" },
+                        "x": 0,
+                        "y": 0
+                    }]
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_perform_actions_key_and_pause() {
+        let command = PerformActions::new(PerformActionsParameters {
+            actions: vec![
+                ActionSequence::Key {
+                    id: "keyboard".into(),
+                    actions: vec![
+                        KeyAction::KeyDown { value: "a".into() },
+                        KeyAction::KeyUp { value: "a".into() },
+                    ],
+                },
+                ActionSequence::None {
+                    id: "none".into(),
+                    actions: vec![PauseAction::Pause { duration: None }],
+                },
+                ActionSequence::Pointer {
+                    id: "mouse".into(),
+                    parameters: PointerParameters {
+                        pointer_type: PointerType::Mouse,
+                    },
+                    actions: vec![PointerAction::PointerMove {
+                        duration: None,
+                        origin: PointerOrigin::Viewport,
+                        x: 10,
+                        y: -5,
+                    }],
+                },
+            ],
+        });
+
+        assert_eq!(
+            serde_json::to_value(command.parameters()).expect("Serialization should succeed"),
+            serde_json::json!({
+                "actions": [
+                    {
+                        "type": "key",
+                        "id": "keyboard",
+                        "actions": [
+                            { "type": "keyDown", "value": "a" },
+                            { "type": "keyUp", "value": "a" }
+                        ]
+                    },
+                    {
+                        "type": "none",
+                        "id": "none",
+                        "actions": [{ "type": "pause" }]
+                    },
+                    {
+                        "type": "pointer",
+                        "id": "mouse",
+                        "parameters": { "pointerType": "mouse" },
+                        "actions": [{
+                            "type": "pointerMove",
+                            "origin": "viewport",
+                            "x": 10,
+                            "y": -5
+                        }]
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    #[allow(clippy::unit_cmp)]
+    fn test_release_actions() {
+        let command = ReleaseActions::new(ReleaseActionsParameters::default());
+
+        assert_eq!(command.name(), "WebDriver:ReleaseActions");
+        assert_eq!(
+            serde_json::to_string(command.parameters()).expect("Serialization should succeed"),
+            "{}"
+        );
+
+        let json_data = r#"{"value":null}"#;
+        let response: ReleaseActionsResponse =
+            serde_json::from_str(json_data).expect("Deserialization should succeed");
+
+        assert_eq!(response.value, ());
+    }
+
     #[test]
     fn test_take_screenshot_full() {
         let command = TakeScreenshot::new(TakeScreenshotParameters::full());
@@ -349,6 +1851,69 @@ mod tests {
         assert_eq!(command.name(), "WebDriver:TakeScreenshot");
         assert_eq!(command.parameters().full, Some(false));
         assert_eq!(command.parameters().element_id, Some("element-42".into()));
+        assert_eq!(command.parameters().scroll, Some(true));
+    }
+
+    #[test]
+    fn test_take_screenshot_parameters_scroll_serialization() {
+        let to_string = |parameters: &TakeScreenshotParameters| {
+            serde_json::to_string(parameters).expect("Serialization should succeed")
+        };
+
+        assert_eq!(
+            to_string(&TakeScreenshotParameters::viewport()),
+            r#"{"full":false}"#
+        );
+        assert_eq!(
+            to_string(&TakeScreenshotParameters::full()),
+            r#"{"full":true}"#
+        );
+        assert_eq!(
+            to_string(&TakeScreenshotParameters::element("element-42".into())),
+            r#"{"full":false,"id":"element-42","scroll":true}"#
+        );
+    }
+
+    #[test]
+    fn test_take_screenshot_parameters_builder() {
+        let to_string = |parameters: &TakeScreenshotParameters| {
+            serde_json::to_string(parameters).expect("Serialization should succeed")
+        };
+
+        assert_eq!(
+            to_string(&TakeScreenshotParameters::builder().build()),
+            to_string(&TakeScreenshotParameters::viewport())
+        );
+        assert_eq!(
+            to_string(&TakeScreenshotParameters::builder().full().build()),
+            to_string(&TakeScreenshotParameters::full())
+        );
+        assert_eq!(
+            to_string(
+                &TakeScreenshotParameters::builder()
+                    .element("element-42".into())
+                    .build()
+            ),
+            to_string(&TakeScreenshotParameters::element("element-42".into()))
+        );
+        assert_eq!(
+            to_string(
+                &TakeScreenshotParameters::builder()
+                    .element("element-42".into())
+                    .scroll(false)
+                    .build()
+            ),
+            r#"{"full":false,"id":"element-42","scroll":false}"#
+        );
+        assert_eq!(
+            to_string(
+                &TakeScreenshotParameters::builder()
+                    .full()
+                    .scroll(false)
+                    .build()
+            ),
+            r#"{"full":true,"scroll":false}"#
+        );
     }
 
     #[test]
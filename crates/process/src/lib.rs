@@ -11,8 +11,18 @@
 //! The spawned process is configured with the `kill on drop` feature to ensure that the process is terminated when dropped,
 //! and, depending on the operating system, it leverages [`ProcessGroup`](process_wrap::tokio::ProcessGroup) on Unix or [`JobObject`](process_wrap::tokio::JobObject) on Windows
 //! to also kill all its child processes.
-
-use std::{ffi::OsStr, io, process::Stdio, result};
+//!
+//! The last lines of the standard error of the process are kept, and logged at the `WARN` level when it exits abnormally.
+
+use std::{
+    collections::VecDeque,
+    ffi::{OsStr, OsString},
+    io,
+    process::{ExitStatus, Stdio},
+    result,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
 
 use process_wrap::tokio::{KillOnDrop, TokioChildWrapper, TokioCommandWrap};
 use thiserror::Error;
@@ -20,20 +30,24 @@ use tokio::{
     io::{AsyncBufReadExt, BufReader},
     process::Command,
 };
-use tracing::{Instrument, debug, trace};
+use tracing::{Instrument, debug, trace, warn};
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("spawn command failed: {1:?}")]
     SpawnCommand(#[source] io::Error, Command),
+    #[error("program not found: '{0}'")]
+    ProgramNotFound(String),
     #[error("kill child with pid = {1:?} failed")]
     KillChild(#[source] io::Error, Option<u32>),
+    #[error("kill child with pid = {1:?} timeout: still not dead after {0:?}")]
+    KillTimeout(Duration, Option<u32>),
 }
 
 pub type Result<T, E = Error> = result::Result<T, E>;
 
 /// Represents the status of the managed process.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Status {
     Alive,
     Terminated,
@@ -41,6 +55,45 @@ pub enum Status {
     Error(String),
 }
 
+/// Default number of lines of the standard error kept by a [`Process`], see [`Process::set_stderr_lines`].
+pub const DEFAULT_STDERR_LINES: usize = 20;
+
+/// The last lines written by the process on its standard error, shared with the task reading them.
+#[derive(Debug)]
+struct StderrTail {
+    lines: VecDeque<String>,
+    capacity: usize,
+}
+
+impl StderrTail {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Appends the line, dropping the oldest ones beyond the capacity.
+    fn push(&mut self, line: String) {
+        self.lines.push_back(line);
+        self.truncate();
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        while self.lines.len() > self.capacity {
+            self.lines.pop_front();
+        }
+    }
+}
+
+/// The [`StderrTail`] of a process, locked by the reading task and the [`Process`].
+type SharedStderrTail = Arc<Mutex<StderrTail>>;
+
 /// Represents an asynchronously spawned process.
 ///
 /// This structure wraps a child process (provided by the[`process_wrap`](https://docs.rs/process-wrap) crate)
@@ -48,6 +101,19 @@ pub enum Status {
 #[derive(Debug)]
 pub struct Process {
     child: Box<dyn TokioChildWrapper>,
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    stderr_tail: SharedStderrTail,
+    /// Whether the process was killed on purpose, so its termination is not reported as abnormal.
+    killed: bool,
+    /// Whether the abnormal exit of the process has already been logged.
+    exit_reported: bool,
+    /// The final status of the process, once it exited or was killed.
+    final_status: Option<Status>,
+    /// The process group of the process (led by it), kept to kill the remaining members once the process exited.
+    #[cfg(unix)]
+    pgid: Option<u32>,
 }
 
 impl Process {
@@ -60,41 +126,65 @@ impl Process {
     ///
     /// # Errors
     ///
-    /// Returns an [`Error`] if the process cannot be spawned.
+    /// Returns an [`Error::ProgramNotFound`] if the program does not exist,
+    /// or an [`Error`] if the process cannot be spawned.
     pub fn spawn<P, A, I>(program: P, args: A) -> Result<Self>
     where
         P: AsRef<OsStr>,
         A: IntoIterator<Item = I>,
         I: AsRef<OsStr>,
     {
-        debug!("Creating a new Command instance...");
-        let trace_enabled = tracing::enabled!(tracing::Level::TRACE);
-
-        let mut command = TokioCommandWrap::with_new(program.as_ref(), |command| {
-            command
-                .args(args)
-                .stdout(pipe_or_null(trace_enabled))
-                .stderr(pipe_or_null(trace_enabled));
-        });
-
-        #[cfg(windows)]
-        command.wrap(process_wrap::tokio::JobObject);
-
-        #[cfg(unix)]
-        command.wrap(process_wrap::tokio::ProcessGroup::leader());
-
-        command.wrap(KillOnDrop);
-
-        debug!(stdout = ?command.command().as_std(), "Spawning command child...");
-        let mut child = command
-            .spawn()
-            .map_err(|error| Error::SpawnCommand(error, command.into_command()))?;
-
-        if trace_enabled {
-            child = trace_child_output(child);
-        }
+        Self::spawn_with(program, args, Vec::<(OsString, OsString)>::new())
+    }
 
-        Ok(Self { child })
+    /// Creates and spawns a new process with additional environment variables.
+    ///
+    /// The variables are added to the environment inherited from the current process,
+    /// and are set again when the process is restarted.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - The command or path to the program to execute.
+    /// * `args` - An iterable of arguments to pass to the program.
+    /// * `envs` - An iterable of environment variables (key and value) to set for the program.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::ProgramNotFound`] if the program does not exist,
+    /// or an [`Error`] if the process cannot be spawned.
+    pub fn spawn_with<P, A, I, E, K, V>(program: P, args: A, envs: E) -> Result<Self>
+    where
+        P: AsRef<OsStr>,
+        A: IntoIterator<Item = I>,
+        I: AsRef<OsStr>,
+        E: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        let program = program.as_ref().to_os_string();
+        let args = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect::<Vec<_>>();
+        let envs = envs
+            .into_iter()
+            .map(|(key, value)| (key.as_ref().to_os_string(), value.as_ref().to_os_string()))
+            .collect::<Vec<_>>();
+        let stderr_tail = Arc::new(Mutex::new(StderrTail::new(DEFAULT_STDERR_LINES)));
+        let child = spawn_child(&program, &args, &envs, &stderr_tail)?;
+
+        Ok(Self {
+            #[cfg(unix)]
+            pgid: child.id(),
+            child,
+            program,
+            args,
+            envs,
+            stderr_tail,
+            killed: false,
+            exit_reported: false,
+            final_status: None,
+        })
     }
 
     /// Returns the process identifier, if available.
@@ -104,24 +194,352 @@ impl Process {
     }
 
     /// Returns the current status of the process.
+    ///
+    /// The first time the process is seen exited with a non-zero code or terminated by a signal, without having
+    /// been killed with [`Process::kill`], the last lines of its standard error (see [`Process::stderr_tail`])
+    /// are logged at the `WARN` level.
+    /// Once the process exited (or was killed), its final status is kept and returned from then on,
+    /// without querying the already reaped process again.
     pub fn status(&mut self) -> Status {
-        match self.child.try_wait() {
+        if let Some(status) = &self.final_status {
+            return status.clone();
+        }
+
+        let pid = self.child.id();
+        let status = match self.child.try_wait() {
             Ok(None) => Status::Alive,
-            Ok(Some(status)) => status.code().map_or(Status::Terminated, Status::Exited),
+            Ok(Some(status)) => exit_status(status),
             Err(error) => Status::Error(error.to_string()),
+        };
+
+        if matches!(status, Status::Terminated | Status::Exited(_)) {
+            self.final_status = Some(status.clone());
         }
+
+        let abnormal = match status {
+            Status::Terminated => true,
+            Status::Exited(code) => code != 0,
+            _ => false,
+        };
+        if abnormal && !self.killed && !self.exit_reported {
+            self.exit_reported = true;
+            warn!(?pid, ?status, "Child exited abnormally");
+
+            for line in self.stderr_tail() {
+                warn!(?pid, "[stderr] {line}");
+            }
+        }
+
+        status
+    }
+
+    /// Returns the last lines written by the process on its standard error, oldest first.
+    ///
+    /// The lines are read by a background task, so the very last ones may be missing right after the process exited.
+    #[must_use]
+    pub fn stderr_tail(&self) -> Vec<String> {
+        self.stderr_tail
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .lines
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Sets the number of lines of the standard error kept (default: [`DEFAULT_STDERR_LINES`]), `0` keeps none.
+    ///
+    /// The oldest lines beyond the new number are dropped right away.
+    pub fn set_stderr_lines(&mut self, lines: usize) {
+        self.stderr_tail
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .set_capacity(lines);
+    }
+
+    /// Returns `true` if the process is still running.
+    ///
+    /// Unlike [`Process::status`], this doesn't require a mutable reference: it probes the operating system
+    /// with the process identifier (`kill(pid, 0)` on Unix, `OpenProcess` on Windows) instead of waiting on the child.
+    /// A process that already has been waited for (e.g. after [`Process::kill`]) has no identifier and is never alive.
+    #[must_use]
+    pub fn is_alive(&self) -> bool {
+        self.id().is_some_and(is_pid_alive)
+    }
+
+    /// Returns the identifiers of the live processes in the process group of the process, itself included.
+    ///
+    /// The process leads its own group (see [`ProcessGroup`](process_wrap::tokio::ProcessGroup)), so this lists
+    /// the subprocesses it spawned, unless they moved to another group. The members are read from `/proc`, so
+    /// the list is empty on the systems without it (e.g. macOS), and once the process has been waited for.
+    /// This is purely observational, [`Process::kill`] kills the whole group anyway.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn child_pids(&self) -> Vec<u32> {
+        self.id().map_or_else(Vec::new, process_group_members)
     }
 
     /// Attempts to kill the process asynchronously.
     ///
+    /// Killing a process that already exited keeps its final status (see [`Process::status`]), but on Unix
+    /// the kill is still sent to its process group, where the subprocesses it spawned may still run.
+    /// A process (or process group) that is already gone is not an error.
+    ///
     /// # Errors
     ///
     /// Returns an [`Error`] if killing the process fails.
     pub async fn kill(&mut self) -> Result<()> {
+        if !matches!(self.status(), Status::Alive | Status::Error(_)) {
+            #[cfg(unix)]
+            {
+                debug!(pgid = ?self.pgid, "Child already exited, killing its process group");
+                kill_process_group(self.pgid)?;
+            }
+            #[cfg(not(unix))]
+            debug!(
+                "Child already exited with process id: {:?}",
+                self.child.id()
+            );
+
+            return Ok(());
+        }
+
         debug!("Killing child with process id: {:?}", self.child.id());
+        self.killed = true;
         Box::into_pin(self.child.kill())
             .await
-            .map_err(|error| Error::KillChild(error, self.id()))
+            .or_else(|error| {
+                if is_already_gone(&error) {
+                    Ok(())
+                } else {
+                    Err(error)
+                }
+            })
+            .map_err(|error| Error::KillChild(error, self.id()))?;
+
+        // The killed process has been reaped: it is terminated even if its exit status cannot be read anymore.
+        let status = match self.child.try_wait() {
+            Ok(Some(status)) => exit_status(status),
+            _ => Status::Terminated,
+        };
+        self.final_status = Some(status);
+
+        Ok(())
+    }
+
+    /// Attempts to kill the process asynchronously, like [`Process::kill`], giving up after the timeout.
+    ///
+    /// The kill signal is sent right away, the timeout bounds the wait for the process (and, on Unix,
+    /// its process group) to be dead and reaped, which may hang on a wedged process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::KillTimeout`] if the process is not dead within the timeout, so the caller can escalate
+    /// (e.g. retry or give up on the process), or an [`Error`] if killing the process fails.
+    pub async fn kill_with_timeout(&mut self, timeout: Duration) -> Result<()> {
+        let id = self.id();
+
+        kill_within(timeout, id, self.kill()).await
+    }
+
+    /// Kills the process and spawns a fresh one with the original program, arguments and environment variables.
+    ///
+    /// The lines kept from the standard error of the killed process are cleared.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if killing the current process or spawning the new one fails.
+    pub async fn restart(&mut self) -> Result<()> {
+        debug!("Restarting child with process id: {:?}", self.child.id());
+        self.kill().await?;
+        self.stderr_tail
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .lines
+            .clear();
+        self.child = spawn_child(&self.program, &self.args, &self.envs, &self.stderr_tail)?;
+        #[cfg(unix)]
+        {
+            self.pgid = self.child.id();
+        }
+        self.killed = false;
+        self.exit_reported = false;
+        self.final_status = None;
+
+        Ok(())
+    }
+}
+
+/// Converts the exit status of the child into a [`Status`]: [`Status::Terminated`] if it was killed by a signal.
+fn exit_status(status: ExitStatus) -> Status {
+    status.code().map_or(Status::Terminated, Status::Exited)
+}
+
+/// Returns `true` if the kill failed because the process (or its process group) is already gone.
+fn is_already_gone(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    if error.raw_os_error() == Some(nix::errno::Errno::ESRCH as i32) {
+        return true;
+    }
+
+    error.kind() == io::ErrorKind::NotFound
+}
+
+/// Sends the kill signal to the process group, which is already gone if it has no member left (`ESRCH`).
+#[cfg(unix)]
+fn kill_process_group(pgid: Option<u32>) -> Result<()> {
+    use nix::{
+        errno::Errno,
+        sys::signal::{Signal, killpg},
+        unistd::Pid,
+    };
+
+    let Some(raw_pgid) = pgid.and_then(|pgid| i32::try_from(pgid).ok()) else {
+        return Ok(());
+    };
+
+    match killpg(Pid::from_raw(raw_pgid), Signal::SIGKILL) {
+        Ok(()) | Err(Errno::ESRCH) => Ok(()),
+        Err(errno) => Err(Error::KillChild(errno.into(), pgid)),
+    }
+}
+
+/// Awaits the kill future of the process with the given id, failing with [`Error::KillTimeout`] after the timeout.
+async fn kill_within<F>(timeout: Duration, id: Option<u32>, kill: F) -> Result<()>
+where
+    F: Future<Output = Result<()>> + Send,
+{
+    tokio::time::timeout(timeout, kill)
+        .await
+        .map_err(|_| Error::KillTimeout(timeout, id))?
+}
+
+/// Spawns the program as a child process, in its own process group (Unix) or job object (Windows).
+///
+/// The lines of its standard error are kept in the given tail.
+fn spawn_child(
+    program: &OsStr,
+    args: &[OsString],
+    envs: &[(OsString, OsString)],
+    stderr_tail: &SharedStderrTail,
+) -> Result<Box<dyn TokioChildWrapper>> {
+    debug!("Creating a new Command instance...");
+    let trace_enabled = tracing::enabled!(tracing::Level::TRACE);
+
+    let mut command = TokioCommandWrap::with_new(program, |command| {
+        command
+            .args(args)
+            .envs(envs.iter().map(|(key, value)| (key, value)))
+            .stdout(pipe_or_null(trace_enabled))
+            .stderr(Stdio::piped());
+    });
+
+    #[cfg(windows)]
+    command.wrap(process_wrap::tokio::JobObject);
+
+    #[cfg(unix)]
+    command.wrap(process_wrap::tokio::ProcessGroup::leader());
+
+    command.wrap(KillOnDrop);
+
+    debug!(stdout = ?command.command().as_std(), "Spawning command child...");
+    let child = command.spawn().map_err(|error| {
+        if error.kind() == io::ErrorKind::NotFound {
+            Error::ProgramNotFound(program.to_string_lossy().into_owned())
+        } else {
+            Error::SpawnCommand(error, command.into_command())
+        }
+    })?;
+
+    Ok(capture_child_output(child, Arc::clone(stderr_tail)))
+}
+
+/// Sends the null signal to the process, which only checks it exists and can be signaled.
+///
+/// A zombie (exited but not yet reaped) still answers the null signal, so it is ruled out with its `/proc/<pid>/stat`
+/// state, when available.
+#[cfg(unix)]
+fn is_pid_alive(pid: u32) -> bool {
+    use nix::{errno::Errno, sys::signal, unistd::Pid};
+
+    let Ok(raw_pid) = i32::try_from(pid) else {
+        return false;
+    };
+
+    // `EPERM` means the process exists but belongs to another user.
+    let signaled = matches!(
+        signal::kill(Pid::from_raw(raw_pid), None),
+        Ok(()) | Err(Errno::EPERM)
+    );
+
+    signaled && process_stat(pid).is_none_or(|(state, _)| is_live_state(&state))
+}
+
+/// Returns the identifiers of the live processes in the process group, read from `/proc` (empty without it).
+#[cfg(unix)]
+fn process_group_members(pgid: u32) -> Vec<u32> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    let mut pids: Vec<u32> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .filter(|pid| live_process_group(*pid) == Some(pgid))
+        .collect();
+    pids.sort_unstable();
+
+    pids
+}
+
+/// Reads the process group of the process from its `/proc/<pid>/stat` file, `None` if it is not alive (or a zombie).
+#[cfg(unix)]
+fn live_process_group(pid: u32) -> Option<u32> {
+    let (state, pgrp) = process_stat(pid)?;
+
+    is_live_state(&state).then_some(pgrp)
+}
+
+/// Reads the state and the process group of the process from its `/proc/<pid>/stat` file.
+#[cfg(unix)]
+fn process_stat(pid: u32) -> Option<(String, u32)> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name, in parentheses, may hold spaces: it is followed by the `state ppid pgrp ...` fields.
+    let (_, fields) = stat.rsplit_once(')')?;
+    let mut fields = fields.split_whitespace();
+    let state = fields.next()?.to_string();
+    let pgrp = fields.nth(1)?.parse().ok()?;
+
+    Some((state, pgrp))
+}
+
+/// Returns `true` unless the `/proc/<pid>/stat` state is zombie (`Z`) or dead (`X`).
+#[cfg(unix)]
+fn is_live_state(state: &str) -> bool {
+    state != "Z" && state != "X"
+}
+
+/// Opens the process and checks it has not yet exited.
+#[cfg(windows)]
+fn is_pid_alive(pid: u32) -> bool {
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, STILL_ACTIVE},
+        System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
+    };
+
+    // SAFETY: the handle is checked before use and closed before returning.
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+
+        let mut exit_code = 0;
+        let succeeded = GetExitCodeProcess(handle, &raw mut exit_code) != 0;
+        CloseHandle(handle);
+
+        #[allow(clippy::cast_sign_loss)]
+        let still_active = STILL_ACTIVE as u32;
+        succeeded && exit_code == still_active
     }
 }
 
@@ -133,19 +551,24 @@ fn pipe_or_null(condition: bool) -> Stdio {
     }
 }
 
-/// Spawns tasks to trace the child process output.
+/// Spawns tasks to capture the child process output.
 ///
-/// This function creates asynchronous tasks that read and log the standard output and error of the child process,
-/// which is useful for debugging.
+/// This function creates asynchronous tasks that read and log (at the `TRACE` level) the standard output and error
+/// of the child process, which is useful for debugging. The standard output is only piped if the `TRACE` level
+/// is enabled, while the lines of the standard error are always read, to be kept in the tail.
 ///
 /// # Arguments
 ///
-/// * `child` - The child process whose output will be traced.
+/// * `child` - The child process whose output will be captured.
+/// * `stderr_tail` - The tail the last lines of the standard error are kept in.
 ///
 /// # Returns
 ///
-/// The modified child process with output tracing enabled.
-fn trace_child_output(mut child: Box<dyn TokioChildWrapper>) -> Box<dyn TokioChildWrapper> {
+/// The modified child process with output capture enabled.
+fn capture_child_output(
+    mut child: Box<dyn TokioChildWrapper>,
+    stderr_tail: SharedStderrTail,
+) -> Box<dyn TokioChildWrapper> {
     let pid = child.id();
 
     if let Some(stdout) = child.stdout().take() {
@@ -168,6 +591,10 @@ fn trace_child_output(mut child: Box<dyn TokioChildWrapper>) -> Box<dyn TokioChi
             async move {
                 while let Ok(Some(line)) = stderr_reader.next_line().await {
                     trace!(?pid, "[stderr] {line}");
+                    stderr_tail
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push(line);
                 }
             }
             .in_current_span(),
@@ -185,7 +612,7 @@ mod tests {
 
     fn spawn_sleep_process() -> Process {
         #[cfg(unix)]
-        let process = Process::spawn("sleep", &["1"]);
+        let process = Process::spawn("sleep", ["1"]);
 
         #[cfg(windows)]
         let process = Process::spawn("timeout", ["1"]);
@@ -193,6 +620,17 @@ mod tests {
         process.expect("Failed to spawn process")
     }
 
+    #[tokio::test]
+    async fn test_process_spawn_program_not_found() {
+        let error = Process::spawn("pantin-nonexistent-program", ["1"])
+            .expect_err("Should not spawn a nonexistent program");
+
+        match error {
+            Error::ProgramNotFound(program) => assert_eq!(program, "pantin-nonexistent-program"),
+            error => panic!("Unexpected error: {error:?}"),
+        }
+    }
+
     #[tokio::test]
     async fn test_process_exit() {
         let mut process = spawn_sleep_process();
@@ -236,6 +674,208 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_process_kill_exited() {
+        let mut process = spawn_sleep_process();
+
+        let mut status = process.status();
+        for _ in 0..100 {
+            if status != Status::Alive {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            status = process.status();
+        }
+        assert_eq!(status, Status::Exited(0));
+
+        process
+            .kill_with_timeout(Duration::from_secs(5))
+            .await
+            .expect("Killing an exited process should not fail");
+        assert_eq!(process.status(), Status::Exited(0));
+
+        process.kill().await.expect("Should kill again");
+        assert_eq!(process.status(), Status::Exited(0));
+    }
+
+    #[tokio::test]
+    async fn test_process_kill_with_timeout() {
+        let mut process = spawn_sleep_process();
+
+        process
+            .kill_with_timeout(Duration::from_secs(5))
+            .await
+            .expect("Should kill within the timeout");
+
+        assert!(
+            !matches!(process.status(), Status::Alive),
+            "Should not be alive after kill"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_process_kill_with_timeout_elapsed() {
+        let mut process = spawn_sleep_process();
+        let pid = process.id();
+
+        // A signaled process is reaped almost immediately, so a slow-to-die one (like a wedged process group)
+        // is simulated by a kill that only completes long after the timeout.
+        let slow_kill = async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(())
+        };
+        let start = std::time::Instant::now();
+        let error = kill_within(Duration::from_millis(100), pid, slow_kill)
+            .await
+            .expect_err("Should time out");
+
+        assert!(
+            matches!(error, Error::KillTimeout(timeout, id) if timeout == Duration::from_millis(100) && id == pid),
+            "Expected KillTimeout error, got: {error:?}"
+        );
+        assert!(
+            start.elapsed() < Duration::from_secs(1),
+            "Should not wait for the slow kill"
+        );
+        assert!(process.is_alive(), "Should still be alive");
+
+        // The caller can then escalate, here by waiting for the kill without any bound.
+        process.kill().await.expect("Should kill");
+        assert!(!process.is_alive(), "Should not be alive after kill");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_process_is_alive() {
+        let mut process = spawn_sleep_process();
+
+        assert!(process.is_alive(), "Should be alive after spawn");
+
+        process.kill().await.expect("Should kill");
+
+        assert!(!process.is_alive(), "Should not be alive after kill");
+    }
+
+    // The zombies are detected with `/proc`.
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_process_is_alive_zombie() {
+        let process = Process::spawn("sh", ["-c", "exit 0"]).expect("Failed to spawn process");
+        let pid = process.id().expect("Should have an id");
+
+        // The exited process is not reaped until its status is read, so it stays a zombie.
+        for _ in 0..50 {
+            if process_stat(pid).is_some_and(|(state, _)| state == "Z") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        assert_eq!(process.id(), Some(pid), "Should not be reaped");
+        assert!(!process.is_alive(), "A zombie should not be alive");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_process_restart() {
+        let mut process = Process::spawn("sleep", ["10"]).expect("Failed to spawn process");
+        let pid = process.id();
+
+        process.restart().await.expect("Should restart");
+
+        assert!(process.id().is_some(), "Should have an id");
+        assert_ne!(process.id(), pid, "Should have a new pid");
+        assert!(process.is_alive(), "Should be alive after restart");
+
+        process.kill().await.expect("Should kill");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_process_spawn_with_envs() {
+        let mut process = Process::spawn_with(
+            "sh",
+            ["-c", "test \"$PANTIN_TEST_ENV\" = pantin"],
+            [("PANTIN_TEST_ENV", "pantin")],
+        )
+        .expect("Failed to spawn process");
+
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+        match process.status() {
+            Status::Exited(actual_code) => assert_eq!(actual_code, 0, "Should see the variable"),
+            status => panic!("Unexpected status: {status:?}"),
+        }
+    }
+
+    // The process group members are read from `/proc`.
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_process_child_pids() {
+        let mut process =
+            Process::spawn("sh", ["-c", "sleep 10 & wait"]).expect("Failed to spawn process");
+        let pid = process.id().expect("Should have an id");
+
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let pids = process.child_pids();
+
+        assert!(pids.contains(&pid), "Should list the leader, got: {pids:?}");
+        assert_eq!(pids.len(), 2, "Should list the forked child, got: {pids:?}");
+
+        process.kill().await.expect("Should kill");
+
+        assert!(
+            process.child_pids().is_empty(),
+            "Should list nothing once killed"
+        );
+    }
+
+    // The process group members are read from `/proc`.
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_process_kill_exited_process_group() {
+        let mut process =
+            Process::spawn("sh", ["-c", "sleep 10 & exit 0"]).expect("Failed to spawn process");
+        let pgid = process.id().expect("Should have an id");
+
+        let mut status = process.status();
+        for _ in 0..100 {
+            if status != Status::Alive {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            status = process.status();
+        }
+        assert_eq!(status, Status::Exited(0));
+        assert_eq!(
+            process_group_members(pgid).len(),
+            1,
+            "The forked child should outlive the leader"
+        );
+
+        process.kill().await.expect("Should kill the process group");
+
+        let mut members = process_group_members(pgid);
+        for _ in 0..50 {
+            if members.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            members = process_group_members(pgid);
+        }
+        assert!(
+            members.is_empty(),
+            "Should kill the forked child, got: {members:?}"
+        );
+        assert_eq!(process.status(), Status::Exited(0));
+
+        process
+            .kill()
+            .await
+            .expect("Should kill a gone process group again");
+    }
+
     #[tokio::test]
     async fn test_process_id() {
         let process = spawn_sleep_process();
@@ -243,6 +883,65 @@ mod tests {
         assert!(process.id().is_some(), "Should have an id");
     }
 
+    /// Waits until the process kept the expected lines of its standard error.
+    #[cfg(unix)]
+    async fn wait_stderr_tail(process: &Process, expected: &[&str]) {
+        for _ in 0..50 {
+            if process.stderr_tail() == expected {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        panic!(
+            "Expected the stderr lines {expected:?}, got: {:?}",
+            process.stderr_tail()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_process_stderr_abnormal_exit() {
+        let mut process = Process::spawn(
+            "sh",
+            [
+                "-c",
+                "echo 'pantin: starting' >&2; echo 'pantin: crashed' >&2; exit 3",
+            ],
+        )
+        .expect("Failed to spawn process");
+
+        wait_stderr_tail(&process, &["pantin: starting", "pantin: crashed"]).await;
+
+        let mut status = process.status();
+        for _ in 0..50 {
+            if status != Status::Alive {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            status = process.status();
+        }
+
+        assert_eq!(status, Status::Exited(3));
+        assert!(logs_contain("Child exited abnormally"));
+        assert!(logs_contain("[stderr] pantin: starting"));
+        assert!(logs_contain("[stderr] pantin: crashed"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_process_stderr_lines() {
+        let mut process = Process::spawn("sh", ["-c", "echo 1 >&2; echo 2 >&2; echo 3 >&2"])
+            .expect("Failed to spawn process");
+
+        process.set_stderr_lines(1);
+        wait_stderr_tail(&process, &["3"]).await;
+
+        process.set_stderr_lines(0);
+        assert!(process.stderr_tail().is_empty());
+    }
+
     #[tokio::test]
     #[tracing_test::traced_test]
     async fn test_process_tracing() {
@@ -0,0 +1,36 @@
+#![deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+#![warn(clippy::all, clippy::pedantic, clippy::nursery, clippy::cargo)]
+
+//! Build script of the Pantin Server.
+//!
+//! It exposes the short git hash of the build to the crate as the `PANTIN_GIT_HASH` environment variable,
+//! read with `option_env!`. A `PANTIN_GIT_HASH` set in the build environment takes precedence (e.g. when building
+//! without the git repository), and the variable is left unset if the hash cannot be read.
+
+use std::{path::Path, process::Command};
+
+fn main() {
+    println!("cargo::rerun-if-env-changed=PANTIN_GIT_HASH");
+
+    // Rebuilds when the checked out commit changes, without rerunning on every build if there is no repository.
+    for path in ["../../.git/HEAD", "../../.git/refs"] {
+        if Path::new(path).exists() {
+            println!("cargo::rerun-if-changed={path}");
+        }
+    }
+
+    if std::env::var_os("PANTIN_GIT_HASH").is_some() {
+        return;
+    }
+
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+
+    if let Some(hash) = hash {
+        println!("cargo::rustc-env=PANTIN_GIT_HASH={}", hash.trim());
+    }
+}
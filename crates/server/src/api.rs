@@ -6,11 +6,13 @@
 //! The key types defined here are:
 //!
 //! - [`Success<T>`]: A generic wrapper for successful responses. It holds data of type `T`.
-//! - [`Failure`]: A wrapper for error responses. It contains an error message describing the cause.
+//! - [`Failure`]: A wrapper for error responses. It contains a machine-readable error code and an error message
+//!   describing the cause.
 //! - [`Error`]: An enumeration of errors that can occur within the server, including errors from state handling,
 //!   browser operations, and query extraction. It implements [`IntoResponse`] so that errors are automatically
 //!   converted into HTTP responses with appropriate status codes and JSON bodies.
 //! - [`Query<T>`]: A wrapper for extracting query parameters from HTTP request parts using Axum.
+//! - [`JsonBody<T>`]: A wrapper for extracting a JSON request body using Axum.
 //!
 //! # Usage
 //!
@@ -21,7 +23,8 @@
 //!
 //! The [`Error`] enum converts errors from state management, browser operations, and query extraction into
 //! HTTP responses. Depending on the error variant, it returns appropriate HTTP status codes such as 400 (Bad Request),
-//! 422 (Unprocessable Entity), or 500 (Internal Server Error) along with a JSON error message.
+//! 403 (Forbidden), 404 (Not Found), 422 (Unprocessable Entity), 429 (Too Many Requests), 503 (Service Unavailable), 504 (Gateway Timeout)
+//! or 500 (Internal Server Error) along with a JSON error message.
 //!
 //! Internally, the error is logged using the `tracing` crate before being transformed into a response.
 
@@ -29,10 +32,14 @@ use std::result;
 
 use axum::{
     Json,
-    extract::{FromRequestParts, rejection::QueryRejection},
-    http::StatusCode,
+    extract::{
+        FromRequest, FromRequestParts,
+        rejection::{JsonRejection, QueryRejection},
+    },
+    http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
+use deadpool::managed::PoolError;
 use serde::Serialize;
 use tracing::error;
 
@@ -56,18 +63,21 @@ impl<T> Success<T> {
 
 /// A wrapper for error responses.
 ///
-/// This type encapsulates an error message that will be sent back as JSON.
-/// The message field is named `cause` in the JSON output.
+/// This type encapsulates an error message that will be sent back as JSON, along with a stable error code.
+/// The message field is named `cause` in the JSON output, and is meant for humans: the clients should rely
+/// on the `code` field (e.g. `"missing_field"` or `"pool_timeout"`) to distinguish the kinds of errors.
 #[derive(Debug, Serialize)]
 pub struct Failure {
     cause: String,
+    code: &'static str,
 }
 
 impl Failure {
-    /// Creates a new [`Failure`] instance with the specified error cause.
-    pub fn new<C: Into<String>>(cause: C) -> Self {
+    /// Creates a new [`Failure`] instance with the specified error code and cause.
+    pub fn new<C: Into<String>>(code: &'static str, cause: C) -> Self {
         Self {
             cause: cause.into(),
+            code,
         }
     }
 }
@@ -84,37 +94,192 @@ pub enum Error {
     Browser(#[from] pantin_browser::Error),
     #[error(transparent)]
     QueryRejection(#[from] QueryRejection),
+    #[error(transparent)]
+    JsonRejection(#[from] JsonRejection),
     #[error("missing field: {0}")]
     MissingField(String),
+    #[error("{0} exceeds the maximum of {1}")]
+    ExceedsMaximum(String, u32),
+    #[error("unsupported parameter: {0}")]
+    Unsupported(String),
+    #[error("{0} is out of range")]
+    OutOfRange(String),
+    #[error("forbidden host: '{0}' is not allowed")]
+    ForbiddenHost(String),
+    #[error("too many requests, retry after {0} seconds")]
+    TooManyRequests(u64),
+    #[error("unauthorized: missing or invalid API key")]
+    Unauthorized,
+    #[error("draining: the server does not accept new screenshot requests")]
+    Draining,
+    #[error("request timeout")]
+    RequestTimeout,
+    #[error(transparent)]
+    Serialize(#[from] serde_json::Error),
+    #[error("transcode screenshot failed: {0}")]
+    Transcode(#[from] image::ImageError),
+    #[error("decode screenshot failed: {0}")]
+    Decode(#[from] base64::DecodeError),
+    #[error("blocking task failed: {0}")]
+    Blocking(#[from] tokio::task::JoinError),
 }
 
-impl IntoResponse for Error {
-    fn into_response(self) -> Response {
-        error!("{:?}", self);
+impl Error {
+    /// Returns `true` if the error comes from a likely transient Marionette failure (see
+    /// [`pantin_marionette::Error::is_transient`]), so the operation may be retried with another browser.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Browser(pantin_browser::Error::Marionette(error)) if error.is_transient())
+    }
+
+    /// Returns the HTTP status code of the response the error is converted into.
+    pub fn status(&self) -> StatusCode {
+        self.status_code_and_cause().0
+    }
+
+    /// Returns the machine-readable code of the response the error is converted into, in `snake_case`.
+    pub fn code(&self) -> &'static str {
+        self.status_code_and_cause().1
+    }
+
+    /// Returns the cause of the response the error is converted into.
+    pub fn cause(&self) -> String {
+        self.status_code_and_cause().2
+    }
 
-        let (status, message) = match self {
-            // Return `BAD_REQUEST` for query extraction errors, missing fields or URL parsing errors.
-            Self::QueryRejection(rejection) => (StatusCode::BAD_REQUEST, rejection.body_text()),
-            Self::MissingField(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+    /// Returns the HTTP status code, the machine-readable code and the cause of the response the error is
+    /// converted into.
+    fn status_code_and_cause(&self) -> (StatusCode, &'static str, String) {
+        match self {
+            // Return `BAD_REQUEST` for query extraction errors, missing or out of range fields, and the invalid
+            // or disallowed URLs.
+            Self::QueryRejection(rejection) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_query",
+                rejection.body_text(),
+            ),
+            // The JSON body rejections carry their own status (e.g. `UNSUPPORTED_MEDIA_TYPE` without a JSON
+            // `Content-Type`).
+            Self::JsonRejection(rejection) => {
+                (rejection.status(), "invalid_body", rejection.body_text())
+            },
+            Self::MissingField(_) => (StatusCode::BAD_REQUEST, "missing_field", self.to_string()),
+            Self::ExceedsMaximum(..) => {
+                (StatusCode::BAD_REQUEST, "exceeds_maximum", self.to_string())
+            },
+            Self::Unsupported(_) => (
+                StatusCode::BAD_REQUEST,
+                "unsupported_parameter",
+                self.to_string(),
+            ),
+            Self::OutOfRange(_) => (StatusCode::BAD_REQUEST, "out_of_range", self.to_string()),
+            // The cause of an invalid URL is the parse error alone (e.g. "empty host"), without the wrapper text.
             Self::Browser(pantin_browser::Error::ParseUrl(error)) => {
-                (StatusCode::BAD_REQUEST, error.to_string())
+                (StatusCode::BAD_REQUEST, "invalid_url", error.to_string())
             },
-            // Return `UNPROCESSABLE_ENTITY` for command failures.
-            Self::Browser(pantin_browser::Error::Marionette(
-                pantin_marionette::Error::Request(pantin_marionette::request::Error::Response(
-                    pantin_marionette::response::Error::CommandFailure(_id, failure),
-                )),
-            )) => (
-                StatusCode::UNPROCESSABLE_ENTITY,
-                format!("{}: {}", failure.error, failure.message),
+            Self::Browser(error) if error.is_user_error() => {
+                (StatusCode::BAD_REQUEST, "invalid_url", self.to_string())
+            },
+            // Return `FORBIDDEN` when the page landed on a host rejected by the host policy.
+            Self::ForbiddenHost(_) => (StatusCode::FORBIDDEN, "forbidden_host", self.to_string()),
+            // Return `TOO_MANY_REQUESTS` when the rate limit is exceeded (see `into_response` for `Retry-After`).
+            Self::TooManyRequests(_) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "too_many_requests",
+                self.to_string(),
             ),
-            // All other errors result in `INTERNAL_SERVER_ERROR`.
-            Self::Browser(_) | Self::State(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
+            // Return `UNAUTHORIZED` when an admin endpoint is called without the admin API key.
+            Self::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized", self.to_string()),
+            // Return `REQUEST_TIMEOUT` when the request was not served in time, like the timeout layer does.
+            Self::RequestTimeout => (
+                StatusCode::REQUEST_TIMEOUT,
+                "request_timeout",
+                self.to_string(),
+            ),
+            // Return `SERVICE_UNAVAILABLE` for the new requests once the server is draining.
+            Self::Draining => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "draining",
+                self.to_string(),
+            ),
+            // Return `INTERNAL_SERVER_ERROR` with a clear message when the browser program does not exist.
+            // The program path is only logged (see `into_response`), not disclosed to the client.
+            Self::Browser(pantin_browser::Error::Process(
+                pantin_process::Error::ProgramNotFound(_),
+            ))
+            | Self::State(state::Error::PoolError(PoolError::Backend(
+                pantin_browser::Error::Process(pantin_process::Error::ProgramNotFound(_)),
+            ))) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "browser_program_not_found",
+                "browser program not found, check the `browser_program` setting".to_string(),
+            ),
+            // Return `NOT_FOUND` when the selector (or `XPath` expression) matches no element.
+            Self::Browser(pantin_browser::Error::ElementNotFound { .. }) => {
+                (StatusCode::NOT_FOUND, "element_not_found", self.to_string())
             },
-        };
+            // Return `SERVICE_UNAVAILABLE` when no browser could be acquired in time (see `into_response` for
+            // `Retry-After`).
+            Self::State(state::Error::AcquireTimeout { .. }) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "pool_timeout",
+                self.to_string(),
+            ),
+            // Return `GATEWAY_TIMEOUT` when the page did not load within the navigation timeout.
+            Self::Browser(pantin_browser::Error::PageLoadTimeout(_)) => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "navigation_timeout",
+                self.to_string(),
+            ),
+            // Return `UNPROCESSABLE_ENTITY` for the command failures (including the timeouts of the other commands,
+            // like a script), and `INTERNAL_SERVER_ERROR` otherwise.
+            Self::Browser(error) => error.command_failure().map_or_else(
+                || {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "internal_error",
+                        self.to_string(),
+                    )
+                },
+                |failure| {
+                    (
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "command_failure",
+                        format!("{}: {}", failure.error, failure.message),
+                    )
+                },
+            ),
+            // All other errors result in `INTERNAL_SERVER_ERROR`.
+            Self::State(_)
+            | Self::Serialize(_)
+            | Self::Transcode(_)
+            | Self::Decode(_)
+            | Self::Blocking(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+                self.to_string(),
+            ),
+        }
+    }
+}
 
-        (status, Json(Failure::new(message))).into_response()
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        error!("{:?}", self);
+
+        let (status, code, cause) = self.status_code_and_cause();
+        let failure = Json(Failure::new(code, cause));
+
+        // The seconds to wait are sent in the `Retry-After` header when the rate limit is exceeded,
+        // or the browser pool is exhausted.
+        if let Self::TooManyRequests(retry_after)
+        | Self::State(state::Error::AcquireTimeout { retry_after, .. }) = self
+        {
+            let retry_after = [(header::RETRY_AFTER, retry_after.to_string())];
+
+            return (status, retry_after, failure).into_response();
+        }
+
+        (status, failure).into_response()
     }
 }
 
@@ -129,10 +294,20 @@ pub type Result<T = Response, E = Error> = result::Result<T, E>;
 #[from_request(via(axum::extract::Query), rejection(Error))]
 pub struct Query<T>(pub T);
 
+/// A wrapper type for extracting a JSON request body.
+///
+/// This type uses Axum's [`FromRequest`] to extract the body via [`axum::Json`]
+/// and converts any rejection into an [`Error`] with appropriate HTTP status code.
+#[derive(Debug, FromRequest)]
+#[from_request(via(axum::Json), rejection(Error))]
+pub struct JsonBody<T>(pub T);
+
 #[cfg(test)]
 #[cfg_attr(coverage, coverage(off))]
 #[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 mod tests {
+    use std::time::Duration;
+
     use super::*;
 
     #[test]
@@ -144,9 +319,10 @@ mod tests {
 
     #[test]
     fn test_failure_serialization() {
-        let failure = Failure::new("error");
+        let failure = Failure::new("internal_error", "error");
         let json = serde_json::to_string(&failure).expect("Serialization should succeed");
         assert!(json.contains(r#""cause":"error""#));
+        assert!(json.contains(r#""code":"internal_error""#));
     }
 
     #[tokio::test]
@@ -155,7 +331,60 @@ mod tests {
         let response = error.into_response();
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
+        let error = Error::ExceedsMaximum("width".to_string(), 4096);
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let error = Error::Unsupported("mode=all-matching".to_string());
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let error = Error::OutOfRange("geo_lat".to_string());
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let error = Error::ForbiddenHost("localhost".to_string());
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let error = Error::Unauthorized;
+        assert_eq!(error.code(), "unauthorized");
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let error = Error::Draining;
+        assert_eq!(error.code(), "draining");
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let error = Error::TooManyRequests(3);
+        assert_eq!(error.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "3");
+
+        let error = Error::State(state::Error::PoolError(PoolError::Backend(
+            pantin_browser::Error::Process(pantin_process::Error::ProgramNotFound(
+                "firefox".into(),
+            )),
+        )));
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        assert_eq!(
+            body.as_ref(),
+            br#"{"cause":"browser program not found, check the `browser_program` setting","code":"browser_program_not_found"}"#
+        );
+
         let error = Error::Browser(pantin_browser::Error::ParseUrl(url::ParseError::EmptyHost));
+        assert_eq!(error.cause(), "empty host");
+        assert_eq!(error.code(), "invalid_url");
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let error = Error::Browser(pantin_browser::Error::UnsupportedUrlProtocol("file".into()));
         let response = error.into_response();
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
@@ -174,6 +403,85 @@ mod tests {
         let response = error.into_response();
         assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
+        let error = Error::Browser(pantin_browser::Error::PageLoadTimeout(
+            "Timeout loading page after 500ms".into(),
+        ));
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let error = Error::Browser(pantin_browser::Error::ElementNotFound {
+            selector: "#missing".into(),
+        });
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let error = Error::State(state::Error::AcquireTimeout {
+            timeout: Duration::from_secs(1),
+            retry_after: 2,
+        });
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read response body");
+        assert_eq!(
+            body,
+            r#"{"cause":"acquire browser timeout: no browser available after 1s","code":"pool_timeout"}"#
+        );
+
         // TODO: add more testes
     }
+
+    #[tokio::test]
+    async fn test_error_code_missing_field() {
+        let error = Error::MissingField("selector".to_string());
+        assert_eq!(error.code(), "missing_field");
+
+        let body = axum::body::to_bytes(error.into_response().into_body(), usize::MAX)
+            .await
+            .expect("Failed to read response body");
+        assert_eq!(
+            body,
+            r#"{"cause":"missing field: selector","code":"missing_field"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_code_command_failure() {
+        let command_failure = |error: &str| {
+            Error::Browser(pantin_browser::Error::Marionette(
+                pantin_marionette::Error::Request(pantin_marionette::request::Error::Response(
+                    pantin_marionette::response::Error::CommandFailure(
+                        42,
+                        pantin_marionette::response::Failure {
+                            error: error.into(),
+                            message: "test-message".into(),
+                            stacktrace: "test-trace".into(),
+                        },
+                    ),
+                )),
+            ))
+        };
+
+        // Only the navigation command reports a page load timeout, the other timeouts (e.g. a script) are
+        // command failures.
+        let error = command_failure("timeout");
+        assert_eq!(error.code(), "command_failure");
+        assert_eq!(error.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let error = command_failure("stale element reference");
+        assert_eq!(error.code(), "command_failure");
+
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read response body");
+        let json: serde_json::Value = serde_json::from_slice(&body).expect("Invalid JSON body");
+        assert_eq!(json["code"], "command_failure");
+        assert_eq!(json["cause"], "stale element reference: test-message");
+    }
 }
@@ -41,16 +41,35 @@
 //! }
 //! ```
 
+use std::{
+    collections::HashSet,
+    sync::{Mutex, PoisonError},
+};
+
 use deadpool::managed;
-use pantin_browser::{Browser, browser};
+use pantin_browser::{Browser, BrowserOptions, ProxyConfig, browser, profile::UserJs};
 use tracing::debug;
+use uuid::Uuid;
+
+/// Number of times the browsers retry a transient navigation failure (like an aborted load),
+/// see [`BrowserOptions::navigate_retries`].
+pub const NAVIGATE_RETRIES: u8 = 2;
 
 /// The browser manager responsible for creating and recycling [`Browser`] instances.
 ///
-/// It holds the program path used to launch the browser.
+/// It holds the program path used to launch the browser, the optional proxy configuration of the browsers,
+/// the extra arguments they are launched with, whether they are headless, the template of the `user.js` file
+/// of their profiles and whether their HTTP cache is cleared when they are recycled.
+/// It also tracks the discarded browsers, which are never recycled (see [`BrowserManager::discard`]).
 #[derive(Debug)]
 pub struct BrowserManager {
     program: String,
+    proxy: Option<ProxyConfig>,
+    args: Vec<String>,
+    headless: bool,
+    user_js: UserJs,
+    clear_cache: bool,
+    discarded: Mutex<HashSet<Uuid>>,
 }
 
 impl BrowserManager {
@@ -62,8 +81,92 @@ impl BrowserManager {
     pub fn new<P: Into<String>>(program: P) -> Self {
         Self {
             program: program.into(),
+            proxy: None,
+            args: Vec::new(),
+            headless: true,
+            user_js: UserJs::default(),
+            clear_cache: false,
+            discarded: Mutex::default(),
         }
     }
+
+    /// Sets the proxy the created browsers route their traffic through.
+    #[must_use]
+    pub fn with_proxy(mut self, proxy: Option<ProxyConfig>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Sets the arguments passed to the created browsers after the default flags.
+    #[must_use]
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Sets whether the created browsers are headless (the default), or visible to debug locally.
+    #[must_use]
+    pub const fn with_headless(mut self, headless: bool) -> Self {
+        self.headless = headless;
+        self
+    }
+
+    /// Sets the template of the `user.js` file of the created browsers profiles (default: the embedded one).
+    #[must_use]
+    pub fn with_user_js(mut self, user_js: UserJs) -> Self {
+        self.user_js = user_js;
+        self
+    }
+
+    /// Sets whether the HTTP cache of the browsers is cleared when they are recycled (see [`Browser::clear_cache`]),
+    /// so they never render stale assets.
+    #[must_use]
+    pub const fn with_clear_cache(mut self, clear_cache: bool) -> Self {
+        self.clear_cache = clear_cache;
+        self
+    }
+
+    /// Discards the browser with the given identifier: it is removed from the pool the next time it is recycled,
+    /// instead of being handed out again (e.g. a browser that did not answer a ping, while a request may hold it).
+    pub fn discard(&self, uuid: Uuid) {
+        self.discarded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(uuid);
+    }
+
+    /// Returns `true` if the browser with the given identifier was discarded, and forgets it.
+    fn take_discarded(&self, uuid: Uuid) -> bool {
+        self.discarded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&uuid)
+    }
+
+    /// Opens a browser configured like the pooled ones (program, proxy, arguments and `user.js`), with the given timezone
+    /// and locale.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`browser::Error`] if the browser cannot be opened.
+    pub async fn open(
+        &self,
+        timezone: Option<String>,
+        locale: Option<String>,
+    ) -> Result<Browser, browser::Error> {
+        let options = BrowserOptions {
+            proxy: self.proxy.clone(),
+            timezone,
+            locale,
+            headless: self.headless,
+            extra_args: self.args.clone(),
+            user_js: self.user_js.clone(),
+            navigate_retries: NAVIGATE_RETRIES,
+            ..BrowserOptions::default()
+        };
+
+        Browser::open_with_options(self.program.clone(), options).await
+    }
 }
 
 impl managed::Manager for BrowserManager {
@@ -72,7 +175,7 @@ impl managed::Manager for BrowserManager {
 
     /// Creates a new [`Browser`] instance.
     async fn create(&self) -> Result<Self::Type, Self::Error> {
-        let browser = Browser::open(self.program.clone()).await?;
+        let browser = self.open(None, None).await?;
         debug!(uuid=?browser.uuid(), pid=?browser.pid(), sid=?browser.sid(), "Create Browser instance in pool");
 
         Ok(browser)
@@ -81,13 +184,25 @@ impl managed::Manager for BrowserManager {
     /// Recycles an existing browser instance.
     ///
     /// This method is called by the pool when a browser instance is returned.
-    /// If needed it can perform any necessary recycling steps.
+    /// It resets the browser state (cookies and storages), and clears the HTTP cache if enabled, so the next request
+    /// starts clean. The browser is removed from the pool if the reset fails, or if it was discarded
+    /// (see [`BrowserManager::discard`]).
     async fn recycle(
         &self,
         browser: &mut Self::Type,
         _: &managed::Metrics,
     ) -> managed::RecycleResult<Self::Error> {
+        if self.take_discarded(browser.uuid()) {
+            debug!(uuid=?browser.uuid(), pid=?browser.pid(), sid=?browser.sid(), "Discard Browser instance from pool");
+            return Err(managed::RecycleError::message("browser discarded"));
+        }
+
         debug!(uuid=?browser.uuid(), pid=?browser.pid(), sid=?browser.sid(), "Recycle Browser instance from pool");
+        browser.reset_state().await?;
+
+        if self.clear_cache {
+            browser.clear_cache().await?;
+        }
 
         Ok(())
     }
@@ -96,6 +211,7 @@ impl managed::Manager for BrowserManager {
     ///
     /// This method is called when a browser instance is permanently removed from the pool.
     fn detach(&self, browser: &mut Self::Type) {
+        self.take_discarded(browser.uuid());
         debug!(uuid=?browser.uuid(), pid=?browser.pid(), sid=?browser.sid(), "Detach Browser instance from pool");
     }
 }
@@ -116,6 +232,9 @@ mod tests {
     async fn test_browser_manager() {
         let manager = BrowserManager::new("firefox");
         assert_eq!(manager.program, "firefox");
+        assert_eq!(manager.proxy, None);
+        assert!(manager.args.is_empty());
+        assert!(manager.headless);
 
         let pool: BrowserPool = Pool::builder(manager)
             .max_size(1)
@@ -161,4 +280,89 @@ mod tests {
             "pantin_server::browser_pool: Detach Browser instance from pool"
         ));
     }
+
+    #[test]
+    fn test_browser_manager_discard() {
+        let manager = BrowserManager::new("firefox");
+        let uuid = Uuid::new_v4();
+
+        assert!(!manager.take_discarded(uuid));
+
+        manager.discard(uuid);
+        assert!(manager.take_discarded(uuid));
+        assert!(
+            !manager.take_discarded(uuid),
+            "A discarded browser should be forgotten once taken"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_browser_manager_recycle_reset_state() {
+        let pool: BrowserPool = Pool::builder(BrowserManager::new("firefox"))
+            .max_size(1)
+            .build()
+            .expect("Failed to build pool");
+
+        {
+            let mut browser = Box::pin(pool.get()).await.expect("Firefox browser");
+
+            browser
+                .navigate("https://www.infomaniak.com")
+                .await
+                .expect("Navigation failed");
+            browser
+                .execute_script(
+                    "document.cookie = 'pantin=1'; localStorage.setItem('pantin', '1');",
+                    None,
+                )
+                .await
+                .expect("Script execution failed");
+        }
+
+        {
+            let mut browser = Box::pin(pool.get()).await.expect("Firefox browser");
+
+            let value = browser
+                .execute_script(
+                    "return [document.cookie.includes('pantin=1'), localStorage.getItem('pantin')]",
+                    None,
+                )
+                .await
+                .expect("Script execution failed");
+
+            assert_eq!(
+                value,
+                serde_json::json!([false, null]),
+                "Cookie and storage should be cleared on recycle"
+            );
+        }
+
+        for browser in pool.retain(|_, _| false).removed {
+            browser.close().await.expect("Browser close");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_browser_manager_recycle_clear_cache() {
+        let manager = BrowserManager::new("firefox").with_clear_cache(true);
+        let pool: BrowserPool = Pool::builder(manager)
+            .max_size(1)
+            .build()
+            .expect("Failed to build pool");
+
+        for _ in 0..2 {
+            let mut browser = Box::pin(pool.get()).await.expect("Firefox browser");
+
+            browser
+                .navigate("https://www.infomaniak.com")
+                .await
+                .expect("Recycled browser should stay usable");
+        }
+
+        assert_eq!(pool.status().size, 1, "Browser should be recycled");
+
+        for browser in pool.retain(|_, _| false).removed {
+            browser.close().await.expect("Browser close");
+        }
+    }
 }
@@ -0,0 +1,180 @@
+//! Module for caching screenshot responses in memory.
+//!
+//! This module provides a [`ScreenshotCache`], a bounded LRU cache (backed by [lru](https://crates.io/crates/lru))
+//! whose entries expire after a configurable time to live. It stores the body and content type of the screenshot
+//! responses, keyed by a hash of the normalized request parameters, so identical requests can be answered
+//! without acquiring a browser.
+
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use axum::body::Bytes;
+use lru::LruCache;
+use tracing::debug;
+
+/// A screenshot response body and its content type, as stored in the [`ScreenshotCache`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedScreenshot {
+    pub content_type: &'static str,
+    pub bytes: Bytes,
+}
+
+impl CachedScreenshot {
+    /// Creates a new [`CachedScreenshot`] from a content type and a body.
+    pub fn new<B: Into<Bytes>>(content_type: &'static str, bytes: B) -> Self {
+        Self {
+            content_type,
+            bytes: bytes.into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    screenshot: CachedScreenshot,
+    expires_at: Instant,
+}
+
+/// A thread-safe LRU cache of screenshot responses with a time to live.
+///
+/// Cloning a [`ScreenshotCache`] is cheap, all clones share the same entries.
+/// A cache created with a capacity of zero is disabled: it never stores nor returns anything.
+#[derive(Debug, Clone, Default)]
+pub struct ScreenshotCache {
+    entries: Option<Arc<Mutex<LruCache<u64, Entry>>>>,
+    ttl: Duration,
+}
+
+impl ScreenshotCache {
+    /// Creates a new cache holding at most `capacity` screenshots, each for `ttl`.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of cached screenshots (zero disables the cache).
+    /// * `ttl` - How long a cached screenshot is served before being captured again.
+    ///
+    /// # Returns
+    ///
+    /// A new [`ScreenshotCache`] instance.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        let entries = NonZeroUsize::new(capacity)
+            .filter(|_| !ttl.is_zero())
+            .map(|capacity| Arc::new(Mutex::new(LruCache::new(capacity))));
+
+        Self { entries, ttl }
+    }
+
+    /// Returns `true` if the cache can store screenshots.
+    pub const fn is_enabled(&self) -> bool {
+        self.entries.is_some()
+    }
+
+    /// Returns the screenshot cached for the given key, if any and not expired.
+    pub fn get(&self, key: u64) -> Option<CachedScreenshot> {
+        let mut entries = self
+            .entries
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match entries.get(&key) {
+            Some(entry) if entry.expires_at > Instant::now() => {
+                debug!(key, "Screenshot cache hit");
+                Some(entry.screenshot.clone())
+            },
+            Some(_) => {
+                debug!(key, "Screenshot cache entry expired");
+                entries.pop(&key);
+                None
+            },
+            None => None,
+        }
+    }
+
+    /// Stores a screenshot for the given key, evicting the least recently used one if the cache is full.
+    pub fn insert(&self, key: u64, screenshot: CachedScreenshot) {
+        if let Some(entries) = &self.entries {
+            let entry = Entry {
+                screenshot,
+                expires_at: Instant::now() + self.ttl,
+            };
+
+            entries
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .put(key, entry);
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn screenshot(bytes: &'static [u8]) -> CachedScreenshot {
+        CachedScreenshot::new("image/png", bytes)
+    }
+
+    #[test]
+    fn test_cache_hit() {
+        let cache = ScreenshotCache::new(2, Duration::from_secs(60));
+
+        assert!(cache.is_enabled());
+        assert_eq!(cache.get(1), None);
+
+        cache.insert(1, screenshot(b"one"));
+
+        assert_eq!(cache.get(1), Some(screenshot(b"one")));
+
+        let shared = cache.clone();
+        shared.insert(2, screenshot(b"two"));
+
+        assert_eq!(cache.get(2), Some(screenshot(b"two")));
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let cache = ScreenshotCache::new(2, Duration::from_secs(60));
+
+        cache.insert(1, screenshot(b"one"));
+        cache.insert(2, screenshot(b"two"));
+        cache.get(1);
+        cache.insert(3, screenshot(b"three"));
+
+        assert_eq!(cache.get(1), Some(screenshot(b"one")));
+        assert_eq!(cache.get(2), None, "Least recently used should be evicted");
+        assert_eq!(cache.get(3), Some(screenshot(b"three")));
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl_expiry() {
+        let cache = ScreenshotCache::new(2, Duration::from_millis(50));
+
+        cache.insert(1, screenshot(b"one"));
+        assert!(cache.get(1).is_some());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(cache.get(1), None, "Entry should be expired");
+    }
+
+    #[test]
+    fn test_cache_disabled() {
+        for cache in [
+            ScreenshotCache::default(),
+            ScreenshotCache::new(0, Duration::from_secs(60)),
+            ScreenshotCache::new(2, Duration::ZERO),
+        ] {
+            assert!(!cache.is_enabled());
+
+            cache.insert(1, screenshot(b"one"));
+
+            assert_eq!(cache.get(1), None);
+        }
+    }
+}
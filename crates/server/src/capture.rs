@@ -0,0 +1,126 @@
+//! This module implements the `capture` command of the Pantin Server binary.
+//!
+//! It opens a browser, navigates to a page, takes a single screenshot and writes the PNG bytes to a file,
+//! without starting the HTTP server.
+
+use color_eyre::Result;
+use pantin_browser::{Browser, BrowserOptions, ScreenshotParameters, profile::UserJs};
+use tracing::info;
+
+use crate::{
+    browser_pool::NAVIGATE_RETRIES,
+    cli::{CaptureArgs, CaptureMode, PantinSettings},
+};
+
+/// The URL schemes the `capture` command allows in addition to the default ones, to capture local pages.
+const LOCAL_SCHEMES: [&str; 2] = ["file", "data"];
+
+/// Captures a screenshot of the page described by the arguments and writes it to the output file.
+///
+/// The browser is launched with the program, proxy, arguments, headless mode and `user.js` template
+/// of the settings, and closed before returning.
+///
+/// # Errors
+///
+/// Returns an error if the `user.js` template cannot be read, the browser cannot be opened, the capture fails,
+/// or the file cannot be written.
+pub async fn run(settings: &PantinSettings, args: &CaptureArgs) -> Result<()> {
+    let user_js = match &settings.user_js_path {
+        Some(path) => UserJs::read(path).await?,
+        None => UserJs::default(),
+    };
+    let options = BrowserOptions {
+        proxy: settings.browser_proxy.clone(),
+        extra_args: settings.browser_args.clone(),
+        headless: !settings.no_headless,
+        user_js,
+        navigate_retries: NAVIGATE_RETRIES,
+        ..BrowserOptions::default()
+    };
+    let mut browser = Browser::open_with_options(&settings.browser_program, options).await?;
+    let result = capture(&mut browser, args).await;
+    if result.is_ok() {
+        browser.close().await?;
+    } else {
+        browser.close_after_error().await?;
+    }
+
+    let bytes = result?;
+    tokio::fs::write(&args.out, &bytes).await?;
+    info!(out = ?args.out, size = bytes.len(), "Screenshot saved");
+
+    Ok(())
+}
+
+/// Navigates to the URL, resizes the window and returns the PNG bytes of the screenshot.
+async fn capture(browser: &mut Browser, args: &CaptureArgs) -> Result<Vec<u8>> {
+    browser.allow_schemes(LOCAL_SCHEMES);
+    browser.navigate(args.url.as_str()).await?;
+    browser.hide_body_scrollbar().await?;
+    browser.set_window_size(args.width, args.height).await?;
+
+    let parameters = match args.mode {
+        CaptureMode::Full => ScreenshotParameters::full(),
+        CaptureMode::Viewport => ScreenshotParameters::viewport(),
+    };
+
+    Ok(browser.screenshot_bytes(parameters).await?)
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use clap::Parser;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::cli::Command;
+
+    const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+    fn capture_settings(out: &str) -> (PantinSettings, CaptureArgs) {
+        let settings = PantinSettings::parse_from([
+            "pantin",
+            "capture",
+            "--url",
+            "data:text/html,<h1>Pantin</h1>",
+            "--out",
+            out,
+            "--width",
+            "320",
+            "--height",
+            "240",
+        ]);
+
+        match settings.command.clone() {
+            Some(Command::Capture(args)) => (settings, args),
+            command => panic!("Should have a capture command, got: {command:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_capture_run() {
+        let out = std::env::temp_dir().join(format!("pantin-capture-{}.png", Uuid::new_v4()));
+        let (settings, args) = capture_settings(out.to_str().expect("UTF-8 path"));
+
+        run(&settings, &args).await.expect("Capture failed");
+
+        let bytes = tokio::fs::read(&out).await.expect("Screenshot file");
+        tokio::fs::remove_file(&out)
+            .await
+            .expect("Remove screenshot");
+
+        assert!(bytes.starts_with(&PNG_SIGNATURE), "Should be a PNG file");
+    }
+
+    #[tokio::test]
+    async fn test_capture_run_invalid_program() {
+        let out = std::env::temp_dir().join(format!("pantin-capture-{}.png", Uuid::new_v4()));
+        let (mut settings, args) = capture_settings(out.to_str().expect("UTF-8 path"));
+        settings.browser_program = "invalid-browser-command".into();
+
+        assert!(run(&settings, &args).await.is_err());
+        assert!(!out.exists(), "Should not write the file");
+    }
+}
@@ -1,13 +1,22 @@
 //! This module defines the configuration settings for the pantin server.
+//!
+//! The settings are read, from the highest to the lowest precedence, from the command-line arguments,
+//! the environment variables, the TOML file given with `--config`, and the defaults.
 
-use clap::{Parser, ValueEnum};
-use serde::Serialize;
+use std::{ffi::OsString, fs, path::PathBuf};
+
+use clap::{
+    Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum, error::ErrorKind,
+    parser::ValueSource,
+};
+use pantin_browser::{ProxyConfig, parse_proxy};
+use serde::{Deserialize, Serialize};
 
 /// Represents the log verbosity level.
 ///
-/// The variants are serialized as lowercase strings.
-#[derive(Debug, Copy, Clone, ValueEnum, Serialize)]
-#[serde(rename_all(serialize = "lowercase"))]
+/// The variants are (de)serialized as lowercase strings.
+#[derive(Debug, Copy, Clone, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LogLevel {
     Info,
     Debug,
@@ -24,6 +33,56 @@ impl AsRef<str> for LogLevel {
     }
 }
 
+/// Represents the log output format.
+///
+/// The variants are (de)serialized as lowercase strings.
+#[derive(Debug, Copy, Clone, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+/// Represents the area captured by the `capture` command.
+#[derive(Debug, Copy, Clone, ValueEnum)]
+pub enum CaptureMode {
+    /// Capture the full page.
+    Full,
+    /// Capture only the visible (viewport) area.
+    Viewport,
+}
+
+/// Represents the commands run instead of the server.
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Capture a screenshot of a page to a PNG file and exit
+    Capture(CaptureArgs),
+}
+
+/// Holds the arguments of the `capture` command.
+#[derive(Debug, Clone, Args)]
+pub struct CaptureArgs {
+    /// URL of the page to capture (http, https, file or data)
+    #[arg(long)]
+    pub url: String,
+
+    /// Path of the PNG file to write
+    #[arg(long)]
+    pub out: PathBuf,
+
+    /// Window width in pixels
+    #[arg(long, default_value_t = 800)]
+    pub width: u16,
+
+    /// Window height in pixels
+    #[arg(long, default_value_t = 600)]
+    pub height: u16,
+
+    /// Captured area
+    #[arg(value_enum, long, default_value = "viewport")]
+    pub mode: CaptureMode,
+}
+
 /// Holds all configuration settings to start the pantin server.
 ///
 /// Values can be provided via command-line arguments or through environment variables.
@@ -31,6 +90,11 @@ impl AsRef<str> for LogLevel {
 #[derive(Debug, Clone, Parser)]
 #[command(version, about, long_about = None)]
 pub struct PantinSettings {
+    /// Path of a TOML file holding the settings (named like the arguments in `snake_case`, e.g. `server_port = 8080`),
+    /// overridden by the command-line arguments and environment variables
+    #[arg(long, env = "PANTIN_CONFIG")]
+    pub config: Option<PathBuf>,
+
     /// Host of the API server
     #[arg(long, default_value = "localhost", env = "PANTIN_SERVER_HOST")]
     pub server_host: String,
@@ -39,6 +103,14 @@ pub struct PantinSettings {
     #[arg(short, long, default_value_t = 4242, env = "PANTIN_SERVER_PORT")]
     pub server_port: u16,
 
+    /// Path of a Unix domain socket to listen on instead of the host and port (Unix only)
+    #[arg(long, env = "PANTIN_SERVER_SOCKET", conflicts_with_all = ["server_host", "server_port"])]
+    pub server_socket: Option<PathBuf>,
+
+    /// Comma-separated list of origins allowed to call the API from a browser (CORS disabled if empty)
+    #[arg(long, env = "PANTIN_CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Vec<String>,
+
     /// Request timeout in seconds
     #[arg(short, long, default_value_t = 30, env = "PANTIN_REQUEST_TIMEOUT")]
     pub request_timeout: u16,
@@ -47,6 +119,10 @@ pub struct PantinSettings {
     #[arg(long, default_value_t = 5, env = "PANTIN_BROWSER_POOL_MAX_SIZE")]
     pub browser_pool_max_size: u8,
 
+    /// Minimum number of idle browsers, created at startup and replenished in the background so the requests don't pay the cold start
+    #[arg(long, default_value_t = 0, env = "PANTIN_BROWSER_POOL_MIN_IDLE")]
+    pub browser_pool_min_idle: u8,
+
     /// Maximum age in seconds of an unused browser session
     #[arg(long, default_value_t = 60, env = "PANTIN_BROWSER_MAX_AGE")]
     pub browser_max_age: u16,
@@ -55,18 +131,291 @@ pub struct PantinSettings {
     #[arg(long, default_value_t = 10, env = "PANTIN_BROWSER_MAX_RECYCLE_COUNT")]
     pub browser_max_recycle_count: u16,
 
+    /// Maximum time in seconds to wait for an available browser in the pool
+    #[arg(long, default_value_t = 10, env = "PANTIN_BROWSER_ACQUIRE_TIMEOUT")]
+    pub browser_acquire_timeout: u16,
+
+    /// Seconds sent in the `Retry-After` header when no browser is available in time
+    /// (derived from the average capture time if 0)
+    #[arg(long, default_value_t = 0, env = "PANTIN_BROWSER_RETRY_AFTER")]
+    pub browser_retry_after: u16,
+
+    /// Maximum screenshot width in pixels
+    #[arg(long, default_value_t = 4096, env = "PANTIN_MAX_WIDTH")]
+    pub max_width: u16,
+
+    /// Maximum screenshot height in pixels
+    #[arg(long, default_value_t = 4096, env = "PANTIN_MAX_HEIGHT")]
+    pub max_height: u16,
+
+    /// Maximum page height in pixels of a full page screenshot (`mode=full`), taller pages are rejected (unlimited if 0)
+    #[arg(long, default_value_t = 0, env = "PANTIN_MAX_PAGE_HEIGHT")]
+    pub max_page_height: u32,
+
+    /// Maximum number of elements a screenshot request can capture (`mode=all-matching`)
+    #[arg(long, default_value_t = 100, env = "PANTIN_MAX_ELEMENTS")]
+    pub max_elements: u16,
+
+    /// Maximum delay in milliseconds a screenshot request can wait before the capture
+    #[arg(long, default_value_t = 10000, env = "PANTIN_MAX_DELAY")]
+    pub max_delay: u16,
+
+    /// Maximum number of screenshots kept in the in-memory cache (cache disabled if 0)
+    #[arg(long, default_value_t = 0, env = "PANTIN_CACHE_CAPACITY")]
+    pub cache_capacity: u16,
+
+    /// Time in seconds a cached screenshot is served before being captured again
+    #[arg(long, default_value_t = 60, env = "PANTIN_CACHE_TTL")]
+    pub cache_ttl: u16,
+
+    /// Maximum number of screenshot requests per minute and client, identified by its `X-Api-Key` header
+    /// if it is one of the `--rate-limit-api-keys`, otherwise its IP address (rate limit disabled if 0)
+    #[arg(long, default_value_t = 0, env = "PANTIN_RATE_LIMIT")]
+    pub rate_limit: u16,
+
+    /// Comma-separated list of the API keys identifying the clients of the rate limit in the `X-Api-Key` header
+    #[arg(long, env = "PANTIN_RATE_LIMIT_API_KEYS", value_delimiter = ',')]
+    pub rate_limit_api_keys: Vec<String>,
+
+    /// Comma-separated list of the only hosts (and their subdomains), IP addresses or CIDR ranges the captured pages
+    /// can land on, checked after the redirects (all hosts allowed if empty)
+    #[arg(long, env = "PANTIN_ALLOWED_HOSTS", value_delimiter = ',')]
+    pub allowed_hosts: Vec<String>,
+
+    /// Comma-separated list of the hosts (and their subdomains), IP addresses or CIDR ranges the captured pages
+    /// cannot land on, checked after the redirects (e.g. "localhost,127.0.0.0/8,169.254.0.0/16")
+    #[arg(long, env = "PANTIN_BLOCKED_HOSTS", value_delimiter = ',')]
+    pub blocked_hosts: Vec<String>,
+
+    /// API key expected in the `X-Api-Key` header of the admin endpoints (e.g. `POST /admin/drain`),
+    /// which reject all requests if unset
+    #[arg(long, env = "PANTIN_ADMIN_API_KEY")]
+    pub admin_api_key: Option<String>,
+
+    /// Maximum time in seconds to close the browsers on shutdown
+    #[arg(long, default_value_t = 10, env = "PANTIN_SHUTDOWN_TIMEOUT")]
+    pub shutdown_timeout: u16,
+
     /// Command or binary path to launch a gecko like browser
     #[arg(long, default_value = "firefox", env = "PANTIN_BROWSER_PROGRAM")]
     pub browser_program: String,
 
+    /// Proxy URL the browsers route their traffic through (http://, https://, socks4:// or socks5://, without credentials)
+    #[arg(long, env = "PANTIN_BROWSER_PROXY", value_parser = parse_proxy_arg)]
+    pub browser_proxy: Option<ProxyConfig>,
+
+    /// Space separated arguments passed to the browsers after the default flags (e.g. "--window-size=1280,720")
+    #[arg(
+        long,
+        env = "PANTIN_BROWSER_ARGS",
+        value_delimiter = ' ',
+        allow_hyphen_values = true
+    )]
+    pub browser_args: Vec<String>,
+
+    /// Clear the HTTP cache of the browsers when they are recycled, so they never render stale assets
+    #[arg(long, env = "PANTIN_BROWSER_CLEAR_CACHE")]
+    pub browser_clear_cache: bool,
+
+    /// Path to a custom template of the `user.js` file of the browser profiles (default: the embedded template),
+    /// read at startup
+    #[arg(long, env = "PANTIN_USER_JS_PATH")]
+    pub user_js_path: Option<PathBuf>,
+
+    /// Launch visible browsers instead of headless ones, to debug locally (requires a display, e.g. `DISPLAY` on Unix)
+    #[arg(long, env = "PANTIN_NO_HEADLESS")]
+    pub no_headless: bool,
+
+    /// Validate the configuration (listener bind and browser launch) and exit without serving
+    #[arg(long, env = "PANTIN_DRY_RUN")]
+    pub dry_run: bool,
+
     /// Log level
     #[arg(value_enum, long, default_value = "info", env = "PANTIN_LOG_LEVEL")]
     pub log_level: LogLevel,
+
+    /// Log format
+    #[arg(value_enum, long, default_value = "pretty", env = "PANTIN_LOG_FORMAT")]
+    pub log_format: LogFormat,
+
+    /// Command to run instead of starting the server
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
-/// Parses the command-line arguments and environment variables to produce a [`PantinSettings`] instance.
+/// Parses the `--browser-proxy` argument into a [`ProxyConfig`].
+fn parse_proxy_arg(value: &str) -> Result<ProxyConfig, String> {
+    parse_proxy(value).map_err(|error| error.to_string())
+}
+
+/// Parses the command-line arguments, environment variables and config file to produce a [`PantinSettings`] instance.
+///
+/// Exits the process with the usage error if they are invalid.
 pub fn parse() -> PantinSettings {
-    PantinSettings::parse()
+    parse_from(std::env::args_os()).unwrap_or_else(|error| error.exit())
+}
+
+/// Parses the given command-line arguments, the environment variables and the config file given with `--config`.
+///
+/// The values of the config file are only used for the settings that are not set by an argument
+/// or an environment variable.
+///
+/// # Errors
+///
+/// Returns a [`clap::Error`] if the arguments are invalid, or the config file cannot be read,
+/// is not valid TOML, or holds an unknown setting or an invalid value.
+pub fn parse_from<I, T>(args: I) -> Result<PantinSettings, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut command = PantinSettings::command();
+    let matches = command.try_get_matches_from_mut(args)?;
+    let mut settings = PantinSettings::from_arg_matches(&matches)?;
+
+    let Some(path) = &settings.config else {
+        return Ok(settings);
+    };
+
+    let content = fs::read_to_string(path).map_err(|error| {
+        command.error(
+            ErrorKind::Io,
+            format!("read config file '{}' failed: {error}", path.display()),
+        )
+    })?;
+    let file: FileSettings = toml::from_str(&content).map_err(|error| {
+        command.error(
+            ErrorKind::InvalidValue,
+            format!("parse config file '{}' failed: {error}", path.display()),
+        )
+    })?;
+
+    let is_explicit = |id: &str| {
+        matches!(
+            matches.value_source(id),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        )
+    };
+    let has_socket = is_explicit("server_socket") || file.server_socket.is_some();
+    let has_address = is_explicit("server_host")
+        || is_explicit("server_port")
+        || file.server_host.is_some()
+        || file.server_port.is_some();
+    if has_socket && has_address {
+        return Err(command.error(
+            ErrorKind::ArgumentConflict,
+            "the argument '--server-socket' cannot be used with '--server-host' or '--server-port'",
+        ));
+    }
+
+    let browser_proxy = file
+        .browser_proxy
+        .as_deref()
+        .map(parse_proxy)
+        .transpose()
+        .map_err(|error| {
+            command.error(
+                ErrorKind::ValueValidation,
+                format!("invalid 'browser_proxy' in config file: {error}"),
+            )
+        })?;
+
+    // The file values only replace the defaults, the arguments and environment variables take precedence.
+    macro_rules! merge {
+        ($($field:ident),* $(,)?) => {$(
+            if let Some(value) = file.$field {
+                if !is_explicit(stringify!($field)) {
+                    settings.$field = value;
+                }
+            }
+        )*};
+    }
+    macro_rules! merge_optional {
+        ($($field:ident),* $(,)?) => {$(
+            if file.$field.is_some() && !is_explicit(stringify!($field)) {
+                settings.$field = file.$field;
+            }
+        )*};
+    }
+
+    merge!(
+        server_host,
+        server_port,
+        cors_origins,
+        request_timeout,
+        browser_pool_max_size,
+        browser_pool_min_idle,
+        browser_max_age,
+        browser_max_recycle_count,
+        browser_acquire_timeout,
+        browser_retry_after,
+        max_width,
+        max_height,
+        max_page_height,
+        max_elements,
+        max_delay,
+        cache_capacity,
+        cache_ttl,
+        rate_limit,
+        rate_limit_api_keys,
+        allowed_hosts,
+        blocked_hosts,
+        shutdown_timeout,
+        browser_program,
+        browser_args,
+        browser_clear_cache,
+        no_headless,
+        dry_run,
+        log_level,
+        log_format,
+    );
+    merge_optional!(server_socket, admin_api_key, user_js_path);
+    if browser_proxy.is_some() && !is_explicit("browser_proxy") {
+        settings.browser_proxy = browser_proxy;
+    }
+
+    Ok(settings)
+}
+
+/// Holds the settings read from the config file given with `--config`, all optional.
+///
+/// The fields are named like the [`PantinSettings`] ones, and unknown fields are rejected.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileSettings {
+    server_host: Option<String>,
+    server_port: Option<u16>,
+    server_socket: Option<PathBuf>,
+    cors_origins: Option<Vec<String>>,
+    request_timeout: Option<u16>,
+    browser_pool_max_size: Option<u8>,
+    browser_pool_min_idle: Option<u8>,
+    browser_max_age: Option<u16>,
+    browser_max_recycle_count: Option<u16>,
+    browser_acquire_timeout: Option<u16>,
+    browser_retry_after: Option<u16>,
+    max_width: Option<u16>,
+    max_height: Option<u16>,
+    max_page_height: Option<u32>,
+    max_elements: Option<u16>,
+    max_delay: Option<u16>,
+    cache_capacity: Option<u16>,
+    cache_ttl: Option<u16>,
+    rate_limit: Option<u16>,
+    rate_limit_api_keys: Option<Vec<String>>,
+    allowed_hosts: Option<Vec<String>>,
+    blocked_hosts: Option<Vec<String>>,
+    admin_api_key: Option<String>,
+    shutdown_timeout: Option<u16>,
+    browser_program: Option<String>,
+    browser_proxy: Option<String>,
+    browser_args: Option<Vec<String>>,
+    browser_clear_cache: Option<bool>,
+    user_js_path: Option<PathBuf>,
+    no_headless: Option<bool>,
+    dry_run: Option<bool>,
+    log_level: Option<LogLevel>,
+    log_format: Option<LogFormat>,
 }
 
 #[cfg(test)]
@@ -90,16 +439,47 @@ mod tests {
 
         assert_eq!(settings.server_host, "localhost");
         assert_eq!(settings.server_port, 4242);
+        assert_eq!(settings.server_socket, None);
+        assert!(settings.cors_origins.is_empty());
         assert_eq!(settings.request_timeout, 30);
         assert_eq!(settings.browser_pool_max_size, 5);
+        assert_eq!(settings.browser_pool_min_idle, 0);
         assert_eq!(settings.browser_max_age, 60);
         assert_eq!(settings.browser_max_recycle_count, 10);
+        assert_eq!(settings.browser_acquire_timeout, 10);
+        assert_eq!(settings.browser_retry_after, 0);
+        assert_eq!(settings.max_width, 4096);
+        assert_eq!(settings.max_height, 4096);
+        assert_eq!(settings.max_elements, 100);
+        assert_eq!(settings.max_delay, 10000);
+        assert_eq!(settings.max_page_height, 0);
+        assert_eq!(settings.cache_capacity, 0);
+        assert_eq!(settings.cache_ttl, 60);
+        assert_eq!(settings.rate_limit, 0);
+        assert!(settings.rate_limit_api_keys.is_empty());
+        assert!(settings.allowed_hosts.is_empty());
+        assert!(settings.blocked_hosts.is_empty());
+        assert_eq!(settings.admin_api_key, None);
+        assert_eq!(settings.shutdown_timeout, 10);
         assert_eq!(settings.browser_program, "firefox");
+        assert_eq!(settings.browser_proxy, None);
+        assert!(settings.browser_args.is_empty());
+        assert!(!settings.browser_clear_cache);
+        assert_eq!(settings.user_js_path, None);
+        assert!(!settings.no_headless);
+        assert!(!settings.dry_run);
         assert!(
             matches!(settings.log_level, LogLevel::Info),
             "Should have Info log level, got: {:?}",
             settings.log_level
         );
+        assert!(
+            matches!(settings.log_format, LogFormat::Pretty),
+            "Should have Pretty log format, got: {:?}",
+            settings.log_format
+        );
+        assert!(settings.command.is_none());
+        assert_eq!(settings.config, None);
     }
 
     #[test]
@@ -111,28 +491,314 @@ mod tests {
             "example.com",
             "--server-port",
             "8080",
+            "--cors-origins",
+            "http://a.example,http://b.example",
             "--request-timeout",
             "60",
             "--browser-pool-max-size",
             "10",
+            "--browser-pool-min-idle",
+            "2",
             "--browser-max-age",
             "120",
             "--browser-max-recycle-count",
             "20",
+            "--browser-acquire-timeout",
+            "5",
+            "--browser-retry-after",
+            "3",
+            "--max-width",
+            "1920",
+            "--max-height",
+            "1080",
+            "--max-elements",
+            "20",
+            "--max-page-height",
+            "20000",
+            "--cache-capacity",
+            "100",
+            "--cache-ttl",
+            "30",
+            "--rate-limit",
+            "120",
+            "--allowed-hosts",
+            "example.com,example.org",
+            "--blocked-hosts",
+            "localhost",
+            "--shutdown-timeout",
+            "5",
             "--browser-program",
             "custom_browser",
+            "--browser-proxy",
+            "socks5://127.0.0.1:1080",
+            "--browser-args",
+            "--disable-gpu --window-size=1280,720",
+            "--browser-clear-cache",
+            "--user-js-path",
+            "/etc/pantin/user.js",
+            "--no-headless",
+            "--dry-run",
             "--log-level",
             "debug",
+            "--log-format",
+            "json",
         ];
         let settings = PantinSettings::parse_from(args);
 
         assert_eq!(settings.server_host, "example.com");
         assert_eq!(settings.server_port, 8080);
+        assert_eq!(
+            settings.cors_origins,
+            ["http://a.example", "http://b.example"]
+        );
         assert_eq!(settings.request_timeout, 60);
         assert_eq!(settings.browser_pool_max_size, 10);
+        assert_eq!(settings.browser_pool_min_idle, 2);
         assert_eq!(settings.browser_max_age, 120);
         assert_eq!(settings.browser_max_recycle_count, 20);
+        assert_eq!(settings.browser_acquire_timeout, 5);
+        assert_eq!(settings.browser_retry_after, 3);
+        assert_eq!(settings.max_width, 1920);
+        assert_eq!(settings.max_height, 1080);
+        assert_eq!(settings.max_elements, 20);
+        assert_eq!(settings.max_page_height, 20000);
+        assert_eq!(settings.cache_capacity, 100);
+        assert_eq!(settings.cache_ttl, 30);
+        assert_eq!(settings.rate_limit, 120);
+        assert_eq!(settings.allowed_hosts, ["example.com", "example.org"]);
+        assert_eq!(settings.blocked_hosts, ["localhost"]);
+        assert_eq!(settings.shutdown_timeout, 5);
+        assert_eq!(settings.browser_program, "custom_browser");
+        assert_eq!(
+            settings.browser_proxy.and_then(|proxy| proxy.socks_proxy),
+            Some("127.0.0.1:1080".to_string())
+        );
+        assert_eq!(
+            settings.browser_args,
+            ["--disable-gpu", "--window-size=1280,720"]
+        );
+        assert!(settings.browser_clear_cache);
+        assert_eq!(
+            settings.user_js_path,
+            Some(PathBuf::from("/etc/pantin/user.js"))
+        );
+        assert!(settings.no_headless);
+        assert!(settings.dry_run);
+        assert!(matches!(settings.log_level, LogLevel::Debug));
+        assert!(matches!(settings.log_format, LogFormat::Json));
+    }
+
+    #[test]
+    fn test_capture_command() {
+        let args = vec![
+            "pantin",
+            "--browser-program",
+            "custom_browser",
+            "capture",
+            "--url",
+            "https://example.com",
+            "--out",
+            "example.png",
+            "--width",
+            "1024",
+            "--mode",
+            "full",
+        ];
+        let settings = PantinSettings::parse_from(args);
+
+        assert_eq!(settings.browser_program, "custom_browser");
+
+        let Some(Command::Capture(capture)) = settings.command else {
+            panic!("Should have a capture command, got: {:?}", settings.command);
+        };
+
+        assert_eq!(capture.url, "https://example.com");
+        assert_eq!(capture.out, PathBuf::from("example.png"));
+        assert_eq!(capture.width, 1024);
+        assert_eq!(capture.height, 600);
+        assert!(matches!(capture.mode, CaptureMode::Full));
+    }
+
+    /// Writes the config file in a temporary directory, returning its path.
+    fn config_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("pantin-{}-{name}.toml", std::process::id()));
+        fs::write(&path, content).expect("Failed to write config file");
+        path
+    }
+
+    #[test]
+    fn test_config_file() {
+        let path = config_file(
+            "settings",
+            r#"
+                server_port = 8080
+                browser_program = "custom_browser"
+                browser_args = ["--disable-gpu", "--window-size=1280,720"]
+                allowed_hosts = ["example.com", "example.org"]
+                browser_clear_cache = true
+                log_level = "debug"
+            "#,
+        );
+        let config = path.to_str().expect("Config path");
+
+        let settings = parse_from(["pantin", "--config", config]).expect("Valid config file");
+
+        assert_eq!(settings.config.as_deref(), Some(path.as_path()));
+        assert_eq!(settings.server_port, 8080);
         assert_eq!(settings.browser_program, "custom_browser");
+        assert_eq!(
+            settings.browser_args,
+            ["--disable-gpu", "--window-size=1280,720"]
+        );
+        assert_eq!(settings.allowed_hosts, ["example.com", "example.org"]);
+        assert!(settings.browser_clear_cache);
         assert!(matches!(settings.log_level, LogLevel::Debug));
+        // The settings missing from the file keep their default.
+        assert_eq!(settings.server_host, "localhost");
+        assert_eq!(settings.request_timeout, 30);
+
+        // The arguments override the file, even before a subcommand.
+        let settings = parse_from([
+            "pantin",
+            "--config",
+            config,
+            "--server-port",
+            "9090",
+            "capture",
+            "--url",
+            "https://example.com",
+            "--out",
+            "example.png",
+        ])
+        .expect("Valid config file");
+
+        assert_eq!(settings.server_port, 9090);
+        assert_eq!(settings.browser_program, "custom_browser");
+        assert!(matches!(settings.command, Some(Command::Capture(_))));
+
+        fs::remove_file(path).expect("Failed to remove config file");
+    }
+
+    #[test]
+    fn test_config_file_flags() {
+        let path = config_file(
+            "flags",
+            r#"
+                browser_clear_cache = false
+                no_headless = true
+                browser_proxy = "socks5://proxy.local:1080"
+                admin_api_key = "secret"
+            "#,
+        );
+        let config = path.to_str().expect("Config path");
+
+        let settings = parse_from(["pantin", "--config", config]).expect("Valid config file");
+
+        assert!(!settings.browser_clear_cache);
+        assert!(settings.no_headless);
+        assert!(settings.browser_proxy.is_some());
+        assert_eq!(settings.admin_api_key.as_deref(), Some("secret"));
+
+        // A flag set to `false` in the file is still enabled by the command line.
+        let settings = parse_from(["pantin", "--config", config, "--browser-clear-cache"])
+            .expect("Valid config file");
+
+        assert!(settings.browser_clear_cache);
+
+        fs::remove_file(path).expect("Failed to remove config file");
+    }
+
+    #[test]
+    fn test_config_file_errors() {
+        let missing = std::env::temp_dir().join("pantin-missing-config.toml");
+        let missing = missing.to_str().expect("Config path");
+        let error = parse_from(["pantin", "--config", missing]).expect_err("Missing file");
+        assert_eq!(error.kind(), ErrorKind::Io);
+
+        for (name, content, kind) in [
+            ("invalid", "server_port = ", ErrorKind::InvalidValue),
+            ("unknown", "server_prot = 8080", ErrorKind::InvalidValue),
+            ("nested", "config = \"other.toml\"", ErrorKind::InvalidValue),
+            ("type", "server_port = 80.5", ErrorKind::InvalidValue),
+            ("value", "server_port = 100000", ErrorKind::InvalidValue),
+            ("flag", "dry_run = \"yes\"", ErrorKind::InvalidValue),
+            (
+                "proxy",
+                "browser_proxy = \"ftp://proxy.local\"",
+                ErrorKind::ValueValidation,
+            ),
+            (
+                "conflict",
+                "server_socket = \"pantin.sock\"\nserver_port = 8080",
+                ErrorKind::ArgumentConflict,
+            ),
+        ] {
+            let path = config_file(name, content);
+            let config = path.to_str().expect("Config path");
+
+            let error = parse_from(["pantin", "--config", config]).expect_err(name);
+            assert_eq!(error.kind(), kind, "Unexpected error for {name}: {error}");
+            if matches!(name, "unknown" | "nested") {
+                assert!(error.to_string().contains("unknown field"), "{error}");
+            }
+
+            fs::remove_file(path).expect("Failed to remove config file");
+        }
+    }
+
+    #[test]
+    fn test_invalid_browser_proxy() {
+        let args = vec!["pantin", "--browser-proxy", "ftp://proxy.local"];
+
+        assert!(PantinSettings::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_max_delay_settings() {
+        let args = vec!["pantin", "--max-delay", "2500"];
+        let settings = PantinSettings::parse_from(args);
+
+        assert_eq!(settings.max_delay, 2500);
+    }
+
+    #[test]
+    fn test_rate_limit_api_keys_settings() {
+        let args = vec!["pantin", "--rate-limit-api-keys", "key-a,key-b"];
+        let settings = PantinSettings::parse_from(args);
+
+        assert_eq!(settings.rate_limit_api_keys, ["key-a", "key-b"]);
+    }
+
+    #[test]
+    fn test_admin_api_key_settings() {
+        let args = vec!["pantin", "--admin-api-key", "secret"];
+        let settings = PantinSettings::parse_from(args);
+
+        assert_eq!(settings.admin_api_key.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn test_server_socket_settings() {
+        let args = vec!["pantin", "--server-socket", "/tmp/pantin.sock"];
+        let settings = PantinSettings::parse_from(args);
+
+        assert_eq!(
+            settings.server_socket,
+            Some(PathBuf::from("/tmp/pantin.sock"))
+        );
+
+        let args = vec![
+            "pantin",
+            "--server-socket",
+            "/tmp/pantin.sock",
+            "--server-port",
+            "8080",
+        ];
+        let settings = PantinSettings::try_parse_from(args);
+
+        assert!(
+            settings.is_err(),
+            "Server socket should conflict with server port"
+        );
     }
 }
@@ -0,0 +1,25 @@
+//! Module for draining the server before a rolling deploy.
+//!
+//! Once the `POST /admin/drain` endpoint is called, the [`reject`] middleware answers the new screenshot requests
+//! with a `503 Service Unavailable` error, while the requests already in flight complete. The `/health` endpoint
+//! reports the draining state, so the load balancers stop routing requests to the server.
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+};
+
+use crate::{api, state};
+
+/// Middleware rejecting the request with [`api::Error::Draining`] if the server is draining.
+pub async fn reject(
+    State(state): State<state::State>,
+    request: Request,
+    next: Next,
+) -> api::Result {
+    if state.is_draining() {
+        return Err(api::Error::Draining);
+    }
+
+    Ok(next.run(request).await)
+}
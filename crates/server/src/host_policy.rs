@@ -0,0 +1,335 @@
+//! Module for restricting the hosts the screenshots can be taken of.
+//!
+//! This module provides a [`HostPolicy`], built from the allowed and blocked hosts of the settings. It is checked
+//! against the final URL of the page, once the navigation (and its redirects) completed, so a URL accepted by the
+//! browser cannot redirect the capture to an internal address.
+
+use std::{net::IpAddr, sync::Arc};
+
+use url::{Host, Url};
+
+use crate::api;
+
+/// An entry of the allowed or blocked hosts that is neither a domain, an IP address nor a CIDR range.
+#[derive(thiserror::Error, Debug)]
+#[error("invalid host entry '{0}': expected a domain, an IP address or a CIDR range")]
+pub struct InvalidHostEntry(String);
+
+/// An entry of the allowed or blocked hosts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostEntry {
+    /// A domain, matching itself and its subdomains (lowercase, without the trailing dot).
+    Domain(String),
+    /// A range of IP addresses, an IP address being a range of a single address.
+    Network { address: IpAddr, prefix: u8 },
+}
+
+impl HostEntry {
+    /// Parses a trimmed, non-empty entry: a CIDR range (e.g. `127.0.0.0/8`), an IP address (e.g. `[::1]`),
+    /// or a domain.
+    ///
+    /// The IPv4-mapped IPv6 addresses (e.g. `::ffff:127.0.0.1`) are converted to IPv4.
+    fn parse(entry: &str) -> Result<Self, InvalidHostEntry> {
+        let invalid = || InvalidHostEntry(entry.into());
+        let (address, prefix) = match entry.split_once('/') {
+            Some((address, prefix)) => {
+                (address, Some(prefix.parse::<u8>().map_err(|_| invalid())?))
+            },
+            None => (entry, None),
+        };
+        let unbracketed = address
+            .strip_prefix('[')
+            .and_then(|address| address.strip_suffix(']'))
+            .unwrap_or(address);
+
+        let Ok(address) = unbracketed.parse::<IpAddr>() else {
+            return match prefix {
+                Some(_) => Err(invalid()),
+                None => Ok(Self::Domain(
+                    entry.trim_end_matches('.').to_ascii_lowercase(),
+                )),
+            };
+        };
+
+        let bits = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(bits);
+        if prefix > bits {
+            return Err(invalid());
+        }
+
+        Ok(match address {
+            IpAddr::V6(v6) if prefix >= 96 => {
+                v6.to_ipv4_mapped()
+                    .map_or(Self::Network { address, prefix }, |v4| Self::Network {
+                        address: IpAddr::V4(v4),
+                        prefix: prefix - 96,
+                    })
+            },
+            _ => Self::Network { address, prefix },
+        })
+    }
+}
+
+/// A policy allowing or blocking the hosts of the captured pages.
+///
+/// A domain matches an entry if it is equal to it (case-insensitive) or is one of its subdomains:
+/// `example.com` matches `example.com` and `www.example.com`. An IP address matches an entry if it is the same
+/// address (e.g. `127.0.0.1` or `[::1]`) or within the same CIDR range (e.g. `127.0.0.0/8` or `fc00::/7`),
+/// the IPv4-mapped IPv6 addresses (e.g. `[::ffff:127.0.0.1]`) being matched as IPv4 addresses.
+/// The blocked hosts are always rejected, and if there is any allowed host, only those are accepted.
+/// Cloning a [`HostPolicy`] is cheap, all clones share the same hosts.
+#[derive(Debug, Clone, Default)]
+pub struct HostPolicy {
+    allowed_hosts: Arc<[HostEntry]>,
+    blocked_hosts: Arc<[HostEntry]>,
+}
+
+impl HostPolicy {
+    /// Creates a new host policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `allowed_hosts` - The only hosts accepted (all hosts accepted if empty).
+    /// * `blocked_hosts` - The hosts always rejected.
+    ///
+    /// # Returns
+    ///
+    /// A new [`HostPolicy`] instance.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidHostEntry`] if an entry is an invalid CIDR range (e.g. `10.0.0.0/33`).
+    pub fn new(
+        allowed_hosts: &[String],
+        blocked_hosts: &[String],
+    ) -> Result<Self, InvalidHostEntry> {
+        Ok(Self {
+            allowed_hosts: parse_entries(allowed_hosts)?,
+            blocked_hosts: parse_entries(blocked_hosts)?,
+        })
+    }
+
+    /// Returns `true` if the policy can reject hosts.
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed_hosts.is_empty() || !self.blocked_hosts.is_empty()
+    }
+
+    /// Checks the host of the given URL against the policy.
+    ///
+    /// The URLs without a host (like `about:blank`) are only accepted if there is no allowed host.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`api::Error::ForbiddenHost`] if the host is blocked, or not allowed.
+    pub fn check(&self, url: &str) -> api::Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let parsed = Url::parse(url).ok();
+        let Some(host) = parsed.as_ref().and_then(Url::host) else {
+            return if self.allowed_hosts.is_empty() {
+                Ok(())
+            } else {
+                Err(api::Error::ForbiddenHost(url.into()))
+            };
+        };
+
+        let blocked = matches_any(&host, &self.blocked_hosts);
+        let allowed = self.allowed_hosts.is_empty() || matches_any(&host, &self.allowed_hosts);
+
+        if blocked || !allowed {
+            return Err(api::Error::ForbiddenHost(host.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the trimmed and non-empty entries.
+fn parse_entries(hosts: &[String]) -> Result<Arc<[HostEntry]>, InvalidHostEntry> {
+    hosts
+        .iter()
+        .map(|host| host.trim())
+        .filter(|host| !host.is_empty())
+        .map(HostEntry::parse)
+        .collect()
+}
+
+/// Returns `true` if the host matches one of the entries: a domain equal to, or a subdomain of, a domain entry,
+/// or an IP address within a network entry.
+fn matches_any(host: &Host<&str>, entries: &[HostEntry]) -> bool {
+    let address = match *host {
+        Host::Domain(domain) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+
+            return entries.iter().any(|entry| match entry {
+                HostEntry::Domain(entry) => {
+                    domain == *entry
+                        || domain
+                            .strip_suffix(entry.as_str())
+                            .is_some_and(|prefix| prefix.ends_with('.'))
+                },
+                HostEntry::Network { .. } => false,
+            });
+        },
+        Host::Ipv4(v4) => IpAddr::V4(v4),
+        Host::Ipv6(v6) => v6.to_ipv4_mapped().map_or(IpAddr::V6(v6), IpAddr::V4),
+    };
+
+    entries.iter().any(|entry| match *entry {
+        HostEntry::Network {
+            address: network,
+            prefix,
+        } => in_network(address, network, prefix),
+        HostEntry::Domain(_) => false,
+    })
+}
+
+/// Returns `true` if the address shares the first `prefix` bits of the network address (of the same family).
+fn in_network(address: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    let (address, network, bits) = match (address, network) {
+        (IpAddr::V4(address), IpAddr::V4(network)) => (
+            u128::from(u32::from(address)),
+            u128::from(u32::from(network)),
+            32,
+        ),
+        (IpAddr::V6(address), IpAddr::V6(network)) => {
+            (u128::from(address), u128::from(network), 128)
+        },
+        _ => return false,
+    };
+
+    // A shift by the whole width (a `/0` range) overflows, and every address matches.
+    (address ^ network)
+        .checked_shr(bits - u32::from(prefix))
+        .is_none_or(|difference| difference == 0)
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn hosts(hosts: &[&str]) -> Vec<String> {
+        hosts.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_host_policy_disabled() {
+        let policy = HostPolicy::new(&[], &hosts(&[" ", ""])).expect("Valid host policy");
+
+        assert!(!policy.is_enabled());
+        assert!(policy.check("http://localhost/").is_ok());
+        assert!(policy.check("about:blank").is_ok());
+    }
+
+    #[test]
+    fn test_host_policy_blocked_hosts() {
+        let policy = HostPolicy::new(&[], &hosts(&["localhost", "Internal.Example", "127.0.0.1"]))
+            .expect("Valid host policy");
+
+        assert!(policy.is_enabled());
+        assert!(policy.check("https://example.com/page").is_ok());
+        assert!(policy.check("https://notinternal.example/").is_ok());
+        assert!(policy.check("about:blank").is_ok());
+
+        for url in [
+            "http://localhost:8080/",
+            "http://LOCALHOST/",
+            "http://internal.example/",
+            "http://api.internal.example./",
+            "http://127.0.0.1/admin",
+        ] {
+            assert!(
+                matches!(policy.check(url), Err(api::Error::ForbiddenHost(_))),
+                "Should block {url}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_host_policy_allowed_hosts() {
+        let policy = HostPolicy::new(&hosts(&["example.com"]), &hosts(&["admin.example.com"]))
+            .expect("Valid host policy");
+
+        assert!(policy.check("https://example.com/").is_ok());
+        assert!(policy.check("https://www.example.com/").is_ok());
+
+        for url in [
+            "https://admin.example.com/",
+            "https://example.org/",
+            "https://badexample.com/",
+            "about:blank",
+        ] {
+            assert!(
+                matches!(policy.check(url), Err(api::Error::ForbiddenHost(_))),
+                "Should reject {url}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_host_policy_ip_addresses() {
+        let policy = HostPolicy::new(
+            &[],
+            &hosts(&["127.0.0.1", "[::1]", "169.254.0.0/16", "fc00::/7"]),
+        )
+        .expect("Valid host policy");
+
+        assert!(policy.check("http://127.0.0.2/").is_ok());
+        assert!(policy.check("http://169.255.0.1/").is_ok());
+        assert!(policy.check("http://[2001:db8::1]/").is_ok());
+
+        for url in [
+            "http://127.0.0.1/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[::ffff:7f00:1]/",
+            "http://2130706433/",
+            "http://0x7f.0.0.1/",
+            "http://[::1]/",
+            "http://[0:0:0:0:0:0:0:1]/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::ffff:169.254.169.254]/",
+            "http://[fd00::1]/",
+        ] {
+            assert!(
+                matches!(policy.check(url), Err(api::Error::ForbiddenHost(_))),
+                "Should block {url}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_host_policy_cidr_ranges() {
+        let policy = HostPolicy::new(&[], &hosts(&["127.0.0.0/8", "::ffff:10.0.0.0/104"]))
+            .expect("Valid host policy");
+
+        for url in [
+            "http://127.0.0.2/",
+            "http://127.255.255.255/",
+            "http://[::ffff:127.0.0.2]/",
+            "http://10.1.2.3/",
+        ] {
+            assert!(
+                matches!(policy.check(url), Err(api::Error::ForbiddenHost(_))),
+                "Should block {url}"
+            );
+        }
+        assert!(policy.check("http://128.0.0.1/").is_ok());
+        assert!(policy.check("http://11.0.0.1/").is_ok());
+
+        let allow_all = HostPolicy::new(&hosts(&["0.0.0.0/0"]), &[]).expect("Valid host policy");
+        assert!(allow_all.check("http://192.168.1.1/").is_ok());
+        assert!(allow_all.check("http://[::1]/").is_err());
+        assert!(allow_all.check("http://example.com/").is_err());
+
+        for entry in ["10.0.0.0/33", "::/129", "example.com/8", "10.0.0.0/"] {
+            assert!(
+                HostPolicy::new(&[], &hosts(&[entry])).is_err(),
+                "Should reject {entry}"
+            );
+        }
+    }
+}
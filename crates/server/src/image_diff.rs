@@ -0,0 +1,131 @@
+//! Module for comparing two screenshots pixel by pixel.
+//!
+//! This module provides [`diff`], used by the screenshot diff endpoint to compute the ratio of mismatching pixels
+//! between a baseline and a candidate screenshot, along with an image highlighting them.
+
+use image::{Rgba, RgbaImage};
+
+/// Color of the mismatching pixels in the diff image.
+const MISMATCH_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+/// How much the matching pixels are faded to white in the diff image, from 0 (unchanged) to 255 (white).
+const FADE: u16 = 230;
+
+/// The result of the comparison of two images.
+#[derive(Debug)]
+pub struct ImageDiff {
+    /// Ratio of mismatching pixels, from 0 (identical) to 1.
+    pub mismatch: f64,
+    /// Whether the images have different dimensions.
+    pub size_mismatch: bool,
+    /// The baseline faded to light gray, with the mismatching pixels in red.
+    pub image: RgbaImage,
+}
+
+/// Compares the candidate image to the baseline one.
+///
+/// Two pixels match if none of their channels differ by more than the threshold. Images of different sizes are
+/// compared on a canvas as large as both, where the pixels covered by only one of them always mismatch.
+pub fn diff(baseline: &RgbaImage, candidate: &RgbaImage, threshold: u8) -> ImageDiff {
+    let width = baseline.width().max(candidate.width());
+    let height = baseline.height().max(candidate.height());
+    let mut mismatching = 0_u64;
+
+    let image = RgbaImage::from_fn(width, height, |x, y| {
+        match (
+            baseline.get_pixel_checked(x, y),
+            candidate.get_pixel_checked(x, y),
+        ) {
+            (Some(expected), Some(actual)) if matches(*expected, *actual, threshold) => {
+                faded(*expected)
+            },
+            _ => {
+                mismatching += 1;
+                MISMATCH_COLOR
+            },
+        }
+    });
+
+    let pixels = u64::from(width) * u64::from(height);
+    // The ratio does not need more precision than a `f64` holds.
+    #[allow(clippy::cast_precision_loss)]
+    let mismatch = if pixels == 0 {
+        0.0
+    } else {
+        mismatching as f64 / pixels as f64
+    };
+
+    ImageDiff {
+        mismatch,
+        size_mismatch: baseline.dimensions() != candidate.dimensions(),
+        image,
+    }
+}
+
+/// Returns `true` if none of the channels of the pixels differ by more than the threshold.
+fn matches(expected: Rgba<u8>, actual: Rgba<u8>, threshold: u8) -> bool {
+    expected
+        .0
+        .iter()
+        .zip(actual.0)
+        .all(|(expected, actual)| expected.abs_diff(actual) <= threshold)
+}
+
+/// Returns the luma of the pixel, faded to white.
+fn faded(pixel: Rgba<u8>) -> Rgba<u8> {
+    let [red, green, blue, _] = pixel.0.map(u32::from);
+    let luma = (red * 299 + green * 587 + blue * 114) / 1000;
+    let faded = (u32::from(FADE) * 255 + (255 - u32::from(FADE)) * luma) / 255;
+    let faded = u8::try_from(faded).unwrap_or(u8::MAX);
+
+    Rgba([faded, faded, faded, 255])
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32, pixel: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba(pixel))
+    }
+
+    #[test]
+    fn test_diff_identical() {
+        let baseline = image(4, 3, [10, 20, 30, 255]);
+        let diff = diff(&baseline, &baseline.clone(), 0);
+
+        assert!(diff.mismatch.abs() < f64::EPSILON);
+        assert!(!diff.size_mismatch);
+        assert_eq!(diff.image.dimensions(), (4, 3));
+        assert!(diff.image.pixels().all(|pixel| *pixel != MISMATCH_COLOR));
+    }
+
+    #[test]
+    fn test_diff_threshold() {
+        let baseline = image(2, 2, [100, 100, 100, 255]);
+        let mut candidate = baseline.clone();
+        candidate.put_pixel(1, 1, Rgba([105, 100, 100, 255]));
+
+        let diff_strict = diff(&baseline, &candidate, 0);
+        assert!((diff_strict.mismatch - 0.25).abs() < f64::EPSILON);
+        assert_eq!(*diff_strict.image.get_pixel(1, 1), MISMATCH_COLOR);
+        assert_ne!(*diff_strict.image.get_pixel(0, 0), MISMATCH_COLOR);
+
+        let diff_tolerant = diff(&baseline, &candidate, 5);
+        assert!(diff_tolerant.mismatch.abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_diff_size_mismatch() {
+        let baseline = image(2, 2, [0, 0, 0, 255]);
+        let candidate = image(2, 4, [0, 0, 0, 255]);
+
+        let diff = diff(&baseline, &candidate, 0);
+        assert!(diff.size_mismatch);
+        assert_eq!(diff.image.dimensions(), (2, 4));
+        assert!((diff.mismatch - 0.5).abs() < f64::EPSILON);
+        assert_eq!(*diff.image.get_pixel(1, 3), MISMATCH_COLOR);
+    }
+}
@@ -2,13 +2,13 @@
 //!
 //! This module provides a simple function to install a global tracing subscriber configured
 //! with an environment filter and a formatting layer. The subscriber logs messages at the specified
-//! log level and includes file names and line numbers in its output.
+//! log level, either human-readable or as JSON lines, and includes file names and line numbers in its output.
 //!
 //! # Example
 //!
 //! ```no_run
 //! fn main() -> Result<(), Box<dyn std::error::Error>> {
-//!     pantin_tracing::install("debug")?;
+//!     pantin_tracing::install("debug", LogFormat::Json)?;
 //!     // Your application logic here...
 //!     Ok(())
 //! }
@@ -17,9 +17,14 @@
 use std::result;
 
 use thiserror::Error;
-use tracing::subscriber::{SetGlobalDefaultError, set_global_default};
+use tracing::{
+    Subscriber,
+    subscriber::{SetGlobalDefaultError, set_global_default},
+};
 use tracing_subscriber::{EnvFilter, Registry, fmt::layer, layer::SubscriberExt};
 
+use crate::cli::LogFormat;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("setup tracing failed")]
@@ -28,22 +33,62 @@ pub enum Error {
 
 pub type Result<T, E = Error> = result::Result<T, E>;
 
-/// Installs a global tracing subscriber with the given log level.
+/// Installs a global tracing subscriber with the given log level and format.
 ///
-/// This function configures an environment filter and a formatting layer that includes file names and
-/// line numbers, then sets the global default subscriber. The log level is applied to the `"pantin"` target.
+/// This function builds the subscriber with [`subscriber`], then sets it as the global default.
 ///
 /// # Arguments
 ///
 /// * `log_level` - A value convertible to a string representing the desired log level (e.g. "info", "debug", "trace").
+/// * `log_format` - The output format of the logs.
 ///
 /// # Errors
 ///
 /// Returns an [`Error::SetGlobalDefault`] if the installation fails.
-pub fn install<L: AsRef<str>>(log_level: L) -> Result<()> {
+pub fn install<L: AsRef<str>>(log_level: L, log_format: LogFormat) -> Result<()> {
+    set_global_default(subscriber(log_level, log_format)).map_err(Error::SetGlobalDefault)
+}
+
+/// Builds a tracing subscriber with the given log level and format.
+///
+/// This function configures an environment filter and a formatting layer that includes file names and
+/// line numbers, either human-readable or as JSON lines. The log level is applied to the `"pantin"` target.
+///
+/// # Arguments
+///
+/// * `log_level` - A value convertible to a string representing the desired log level (e.g. "info", "debug", "trace").
+/// * `log_format` - The output format of the logs.
+pub fn subscriber<L: AsRef<str>>(
+    log_level: L,
+    log_format: LogFormat,
+) -> impl Subscriber + Send + Sync {
     let env_filter = EnvFilter::new(format!("pantin={}", log_level.as_ref()));
-    let format_layer = layer().with_file(true).with_line_number(true);
+    let (pretty_layer, json_layer) = match log_format {
+        LogFormat::Pretty => (Some(layer().with_file(true).with_line_number(true)), None),
+        LogFormat::Json => (
+            None,
+            Some(layer().json().with_file(true).with_line_number(true)),
+        ),
+    };
+
+    Registry::default()
+        .with(env_filter)
+        .with(pretty_layer)
+        .with(json_layer)
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
 
-    set_global_default(Registry::default().with(env_filter).with(format_layer))
-        .map_err(Error::SetGlobalDefault)
+    #[test]
+    fn test_subscriber() {
+        for log_format in [LogFormat::Pretty, LogFormat::Json] {
+            tracing::subscriber::with_default(subscriber("trace", log_format), || {
+                tracing::info!(?log_format, "Subscriber built");
+            });
+        }
+    }
 }
@@ -8,11 +8,19 @@
 //! This crate implements the Pantin Server, an HTTP server that handles browser-based tasks
 //! such as taking screenshots using headless browsers. The server is built with Axum, Tower, and Tokio,
 //! and supports features such as graceful shutdown, logging, and browser pool management.
+//! The `capture` command takes a single screenshot to a file instead of starting the server.
 
 mod api;
 mod browser_pool;
+mod cache;
+mod capture;
 mod cli;
+mod draining;
+mod host_policy;
+mod image_diff;
 mod logger;
+mod metrics;
+mod rate_limit;
 mod routes;
 mod server;
 mod signal;
@@ -25,9 +33,12 @@ async fn main() -> Result<()> {
     color_eyre::install()?;
 
     let settings = cli::parse();
-    logger::install(settings.log_level)?;
+    logger::install(settings.log_level, settings.log_format)?;
 
-    server::start(settings).await?;
+    match &settings.command {
+        Some(cli::Command::Capture(args)) => capture::run(&settings, args).await?,
+        None => server::start(settings).await?,
+    }
 
     Ok(())
 }
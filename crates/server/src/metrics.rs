@@ -0,0 +1,264 @@
+//! Module for collecting the server metrics and exporting them in the Prometheus text format.
+//!
+//! This module provides [`Metrics`], a set of atomic counters shared by all requests: the number of screenshot
+//! requests and errors, the number of browsers recycled out of the pool, and a histogram of the capture durations.
+//! They are rendered along with the browser pool gauges in the
+//! [Prometheus exposition format](https://prometheus.io/docs/instrumenting/exposition_formats/).
+
+use std::{
+    fmt,
+    fmt::{Display, Formatter},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use deadpool::Status;
+
+/// The upper bounds of the capture duration histogram buckets.
+const CAPTURE_DURATION_BUCKETS: [Duration; 8] = [
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_millis(2500),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+    Duration::from_secs(30),
+];
+
+#[derive(Debug, Default)]
+struct Counters {
+    screenshots: AtomicU64,
+    screenshot_errors: AtomicU64,
+    browsers_recycled: AtomicU64,
+    /// Cumulative counts: each bucket counts the captures lasting at most its upper bound.
+    capture_duration_buckets: [AtomicU64; CAPTURE_DURATION_BUCKETS.len()],
+    capture_duration_count: AtomicU64,
+    capture_duration_sum_micros: AtomicU64,
+}
+
+/// The server metrics, maintained with atomic counters.
+///
+/// Cloning [`Metrics`] is cheap, all clones share the same counters.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    counters: Arc<Counters>,
+}
+
+impl Metrics {
+    /// Counts a screenshot request.
+    pub fn screenshot(&self) {
+        self.counters.screenshots.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a failed screenshot request.
+    pub fn screenshot_error(&self) {
+        self.counters
+            .screenshot_errors
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts the browsers removed from the pool because they were too old, recycled too many times or failed a ping.
+    pub fn browsers_recycled(&self, count: u64) {
+        self.counters
+            .browsers_recycled
+            .fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Records the duration of a screenshot capture in the histogram.
+    pub fn capture_duration(&self, duration: Duration) {
+        let counters = &self.counters;
+
+        for (bound, bucket) in CAPTURE_DURATION_BUCKETS
+            .iter()
+            .zip(&counters.capture_duration_buckets)
+        {
+            if duration <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let micros = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        counters
+            .capture_duration_sum_micros
+            .fetch_add(micros, Ordering::Relaxed);
+        counters
+            .capture_duration_count
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the average duration of the screenshot captures, or `None` if there was none.
+    pub fn average_capture_duration(&self) -> Option<Duration> {
+        let count = self.counters.capture_duration_count.load(Ordering::Relaxed);
+        let sum = self
+            .counters
+            .capture_duration_sum_micros
+            .load(Ordering::Relaxed);
+
+        (count > 0).then(|| Duration::from_micros(sum / count))
+    }
+
+    /// Renders the metrics, and the gauges of the given browser pool status, in the Prometheus text format.
+    pub fn prometheus(&self, pool_status: Status) -> String {
+        Prometheus {
+            counters: &self.counters,
+            pool_status,
+        }
+        .to_string()
+    }
+}
+
+/// The Prometheus text format of the metrics.
+struct Prometheus<'a> {
+    counters: &'a Counters,
+    pool_status: Status,
+}
+
+impl Display for Prometheus<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let counters = self.counters;
+        let pool_status = self.pool_status;
+
+        let name = "pantin_screenshots_total";
+        header(f, name, "counter", "Total number of screenshot requests.")?;
+        writeln!(f, "{name} {}", counters.screenshots.load(Ordering::Relaxed))?;
+
+        let name = "pantin_screenshot_errors_total";
+        header(
+            f,
+            name,
+            "counter",
+            "Total number of failed screenshot requests.",
+        )?;
+        writeln!(
+            f,
+            "{name} {}",
+            counters.screenshot_errors.load(Ordering::Relaxed)
+        )?;
+
+        let name = "pantin_browsers_recycled_total";
+        header(
+            f,
+            name,
+            "counter",
+            "Total number of browsers removed from the pool by age, recycle count or failed ping.",
+        )?;
+        writeln!(
+            f,
+            "{name} {}",
+            counters.browsers_recycled.load(Ordering::Relaxed)
+        )?;
+
+        let name = "pantin_browser_pool_max_size";
+        header(f, name, "gauge", "Maximum number of browsers in the pool.")?;
+        writeln!(f, "{name} {}", pool_status.max_size)?;
+
+        let name = "pantin_browser_pool_size";
+        header(f, name, "gauge", "Number of browsers in the pool.")?;
+        writeln!(f, "{name} {}", pool_status.size)?;
+
+        let name = "pantin_browser_pool_available";
+        header(f, name, "gauge", "Number of idle browsers in the pool.")?;
+        writeln!(f, "{name} {}", pool_status.available)?;
+
+        let name = "pantin_screenshot_capture_duration_seconds";
+        let count = counters.capture_duration_count.load(Ordering::Relaxed);
+        let sum = counters.capture_duration_sum_micros.load(Ordering::Relaxed);
+        header(
+            f,
+            name,
+            "histogram",
+            "Time spent capturing the screenshots not served from the cache.",
+        )?;
+        for (bound, bucket) in CAPTURE_DURATION_BUCKETS
+            .iter()
+            .zip(&counters.capture_duration_buckets)
+        {
+            writeln!(
+                f,
+                "{name}_bucket{{le=\"{}\"}} {}",
+                bound.as_secs_f64(),
+                bucket.load(Ordering::Relaxed)
+            )?;
+        }
+        writeln!(f, "{name}_bucket{{le=\"+Inf\"}} {count}")?;
+        writeln!(f, "{name}_sum {}", Duration::from_micros(sum).as_secs_f64())?;
+        writeln!(f, "{name}_count {count}")
+    }
+}
+
+/// Writes the `HELP` and `TYPE` lines of a metric.
+fn header(f: &mut Formatter<'_>, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(f, "# HELP {name} {help}")?;
+    writeln!(f, "# TYPE {name} {kind}")
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn pool_status() -> Status {
+        Status {
+            max_size: 5,
+            size: 2,
+            available: 1,
+            waiting: 0,
+        }
+    }
+
+    #[test]
+    fn test_metrics_average_capture_duration_empty() {
+        assert_eq!(Metrics::default().average_capture_duration(), None);
+    }
+
+    #[test]
+    fn test_metrics_prometheus() {
+        let metrics = Metrics::default();
+        let shared = metrics.clone();
+
+        metrics.screenshot();
+        metrics.screenshot();
+        shared.screenshot_error();
+        metrics.browsers_recycled(3);
+        metrics.capture_duration(Duration::from_millis(200));
+        shared.capture_duration(Duration::from_secs(3));
+
+        assert_eq!(
+            metrics.average_capture_duration(),
+            Some(Duration::from_millis(1600))
+        );
+
+        let prometheus = metrics.prometheus(pool_status());
+
+        for line in [
+            "# TYPE pantin_screenshots_total counter",
+            "pantin_screenshots_total 2",
+            "# TYPE pantin_screenshot_errors_total counter",
+            "pantin_screenshot_errors_total 1",
+            "# TYPE pantin_browsers_recycled_total counter",
+            "pantin_browsers_recycled_total 3",
+            "# TYPE pantin_browser_pool_max_size gauge",
+            "pantin_browser_pool_max_size 5",
+            "pantin_browser_pool_size 2",
+            "pantin_browser_pool_available 1",
+            "# TYPE pantin_screenshot_capture_duration_seconds histogram",
+            "pantin_screenshot_capture_duration_seconds_bucket{le=\"0.1\"} 0",
+            "pantin_screenshot_capture_duration_seconds_bucket{le=\"0.25\"} 1",
+            "pantin_screenshot_capture_duration_seconds_bucket{le=\"2.5\"} 1",
+            "pantin_screenshot_capture_duration_seconds_bucket{le=\"5\"} 2",
+            "pantin_screenshot_capture_duration_seconds_bucket{le=\"+Inf\"} 2",
+            "pantin_screenshot_capture_duration_seconds_sum 3.2",
+            "pantin_screenshot_capture_duration_seconds_count 2",
+        ] {
+            assert!(
+                prometheus.lines().any(|actual| actual == line),
+                "Missing line {line:?} in:\n{prometheus}"
+            );
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Module for limiting the rate of requests per client.
+//!
+//! This module provides a [`RateLimiter`], holding one token bucket per client, and the [`limit`] middleware
+//! rejecting the requests of the clients that emptied their bucket, and handing the [`RateLimitedClient`] to the
+//! handlers taking more tokens. A client is identified by the value of its `X-Api-Key` header when it is one of
+//! the configured API keys, otherwise by its peer IP address.
+
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    net::SocketAddr,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, OptionalFromRequestParts, Request, State},
+    http::request::Parts,
+    middleware::Next,
+};
+use lru::LruCache;
+use tracing::debug;
+
+use crate::api;
+
+/// The header identifying the client by its API key.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Maximum number of tracked clients, the bucket of the least recently seen client being forgotten above it.
+const MAX_CLIENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: u16,
+    refilled_at: Instant,
+}
+
+/// A thread-safe token bucket rate limiter, with one bucket per client.
+///
+/// Each bucket holds up to `per_minute` tokens and is refilled with one token every `1 / per_minute` minute,
+/// a request takes one token and is rejected if the bucket is empty.
+/// At most [`MAX_CLIENTS`] buckets are kept in a LRU cache, so the memory stays bounded whatever the number
+/// of clients (e.g. many IPv6 source addresses).
+/// Cloning a [`RateLimiter`] is cheap, all clones share the same buckets.
+/// A rate limiter created with a rate of zero is disabled: it never rejects anything.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    buckets: Option<Arc<Mutex<LruCache<String, Bucket>>>>,
+    capacity: u16,
+    refill_interval: Duration,
+    api_keys: Arc<HashSet<String>>,
+}
+
+impl RateLimiter {
+    /// Creates a new rate limiter allowing `per_minute` requests per minute and client.
+    ///
+    /// # Arguments
+    ///
+    /// * `per_minute` - The maximum number of requests per minute (zero disables the rate limiter).
+    ///
+    /// # Returns
+    ///
+    /// A new [`RateLimiter`] instance.
+    pub fn new(per_minute: u16) -> Self {
+        if per_minute == 0 {
+            return Self::default();
+        }
+
+        Self {
+            buckets: Some(Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_CLIENTS).unwrap_or(NonZeroUsize::MIN),
+            )))),
+            capacity: per_minute,
+            refill_interval: Duration::from_secs(60) / u32::from(per_minute),
+            api_keys: Arc::default(),
+        }
+    }
+
+    /// Sets the API keys identifying the clients, the requests with another (or without) API key are identified
+    /// by their peer IP address, so a client cannot get a new bucket by changing its API key.
+    #[must_use]
+    pub fn with_api_keys(mut self, api_keys: &[String]) -> Self {
+        self.api_keys = Arc::new(api_keys.iter().cloned().collect());
+        self
+    }
+
+    /// Returns `true` if the rate limiter can reject requests.
+    pub const fn is_enabled(&self) -> bool {
+        self.buckets.is_some()
+    }
+
+    /// Takes a token from the bucket of the given client.
+    ///
+    /// # Errors
+    ///
+    /// Returns the time to wait for the next token if the bucket of the client is empty.
+    pub fn acquire(&self, client: &str) -> Result<(), Duration> {
+        self.acquire_many(client, 1)
+    }
+
+    /// Takes `count` tokens from the bucket of the given client, or none if it holds fewer.
+    ///
+    /// # Errors
+    ///
+    /// Returns the time to wait for the missing tokens if the bucket of the client holds fewer than `count`.
+    pub fn acquire_many(&self, client: &str, count: u16) -> Result<(), Duration> {
+        let Some(buckets) = &self.buckets else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let mut buckets = buckets.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let bucket = buckets.get_or_insert_mut(client.to_string(), || Bucket {
+            tokens: self.capacity,
+            refilled_at: now,
+        });
+        *bucket = self.refill(*bucket, now);
+
+        let acquired = if bucket.tokens < count {
+            let elapsed = now.duration_since(bucket.refilled_at);
+            let missing = u32::from(count - bucket.tokens);
+
+            Err((self.refill_interval * missing).saturating_sub(elapsed))
+        } else {
+            bucket.tokens -= count;
+
+            Ok(())
+        };
+        drop(buckets);
+
+        acquired
+    }
+
+    /// Returns the key identifying the client of the request: its API key when it is one of the configured ones,
+    /// otherwise its peer IP address.
+    fn client_key(&self, request: &Request) -> String {
+        if let Some(api_key) = request
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|api_key| self.api_keys.contains(*api_key))
+        {
+            return format!("key:{api_key}");
+        }
+
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map_or_else(
+                || "ip:unknown".to_string(),
+                |ConnectInfo(address)| format!("ip:{}", address.ip()),
+            )
+    }
+
+    /// Returns the bucket with the tokens earned since its last refill, up to the capacity.
+    fn refill(&self, bucket: Bucket, now: Instant) -> Bucket {
+        let elapsed = now.duration_since(bucket.refilled_at);
+        let earned = elapsed.as_nanos() / self.refill_interval.as_nanos();
+        let earned = u16::try_from(earned).unwrap_or(u16::MAX);
+        let tokens = bucket.tokens.saturating_add(earned).min(self.capacity);
+
+        let refilled_at = if tokens == self.capacity {
+            now
+        } else {
+            bucket.refilled_at + self.refill_interval * u32::from(earned)
+        };
+
+        Bucket {
+            tokens,
+            refilled_at,
+        }
+    }
+}
+
+/// The client of a rate limited request, inserted in the request extensions by the [`limit`] middleware
+/// for the handlers taking more than one token.
+#[derive(Debug, Clone)]
+pub struct RateLimitedClient {
+    rate_limiter: RateLimiter,
+    key: String,
+}
+
+impl RateLimitedClient {
+    /// Takes the tokens of a batch of `size` requests: one per request, minus the one the batch request took
+    /// in the [`limit`] middleware.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`api::Error::ExceedsMaximum`] if the batch holds more requests than allowed per minute,
+    /// or an [`api::Error::TooManyRequests`] if the bucket of the client holds too few tokens.
+    pub fn acquire_batch(&self, size: usize) -> api::Result<()> {
+        let capacity = self.rate_limiter.capacity;
+        let size = u16::try_from(size)
+            .ok()
+            .filter(|size| *size <= capacity)
+            .ok_or_else(|| api::Error::ExceedsMaximum("batch size".into(), u32::from(capacity)))?;
+
+        self.rate_limiter
+            .acquire_many(&self.key, size.saturating_sub(1))
+            .map_err(|retry_after| too_many_requests(&self.key, retry_after))
+    }
+}
+
+/// Extracts the [`RateLimitedClient`] of the request, if its rate was limited.
+impl<S: Send + Sync> OptionalFromRequestParts<S> for RateLimitedClient {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned())
+    }
+}
+
+/// Middleware rejecting the request with [`api::Error::TooManyRequests`] if its client exceeded the rate limit.
+///
+/// Requests without an API key, received over a Unix domain socket (so without a peer address), share the same bucket.
+/// The accepted requests carry their [`RateLimitedClient`] in their extensions.
+pub async fn limit(
+    State(rate_limiter): State<RateLimiter>,
+    mut request: Request,
+    next: Next,
+) -> api::Result {
+    if rate_limiter.is_enabled() {
+        let key = rate_limiter.client_key(&request);
+
+        if let Err(retry_after) = rate_limiter.acquire(&key) {
+            return Err(too_many_requests(&key, retry_after));
+        }
+
+        request
+            .extensions_mut()
+            .insert(RateLimitedClient { rate_limiter, key });
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Returns the [`api::Error::TooManyRequests`] error asking the client to retry after the given time.
+fn too_many_requests(client: &str, retry_after: Duration) -> api::Error {
+    debug!(client, ?retry_after, "Rate limit exceeded");
+    // Rounded up, so the client never retries before the next token.
+    let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+    api::Error::TooManyRequests(retry_after)
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_acquire() {
+        let rate_limiter = RateLimiter::new(2);
+
+        assert!(rate_limiter.is_enabled());
+        assert_eq!(rate_limiter.acquire("a"), Ok(()));
+        assert_eq!(rate_limiter.acquire("a"), Ok(()));
+
+        let retry_after = rate_limiter
+            .acquire("a")
+            .expect_err("Bucket should be empty");
+        assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(30));
+
+        assert_eq!(
+            rate_limiter.acquire("b"),
+            Ok(()),
+            "Clients should have their own bucket"
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_max_clients() {
+        let rate_limiter = RateLimiter::new(2);
+
+        // The buckets of the clients are not full, so they cannot be forgotten for being refilled.
+        for client in 0..=MAX_CLIENTS {
+            rate_limiter
+                .acquire(&client.to_string())
+                .expect("Should have a token");
+        }
+
+        let buckets = rate_limiter.buckets.as_ref().expect("Enabled rate limiter");
+        let (len, first, last) = {
+            let buckets = buckets.lock().expect("Buckets lock");
+            (
+                buckets.len(),
+                buckets.contains("0"),
+                buckets.contains(&MAX_CLIENTS.to_string()),
+            )
+        };
+        assert_eq!(len, MAX_CLIENTS);
+        assert!(!first, "The least recently seen client should be forgotten");
+        assert!(last, "The last client should be tracked");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_refill() {
+        let rate_limiter = RateLimiter::new(600);
+
+        for _ in 0..600 {
+            rate_limiter.acquire("a").expect("Should have a token");
+        }
+        assert!(rate_limiter.acquire("a").is_err());
+
+        // One token every 100 milliseconds.
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        assert_eq!(rate_limiter.acquire("a"), Ok(()));
+        assert!(rate_limiter.acquire("a").is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter_acquire_many() {
+        let rate_limiter = RateLimiter::new(3);
+
+        assert_eq!(rate_limiter.acquire_many("a", 2), Ok(()));
+
+        let retry_after = rate_limiter
+            .acquire_many("a", 2)
+            .expect_err("Bucket should hold a single token");
+        assert!(retry_after > Duration::from_secs(19) && retry_after <= Duration::from_secs(20));
+
+        assert_eq!(
+            rate_limiter.acquire("a"),
+            Ok(()),
+            "A rejected request should take no token"
+        );
+    }
+
+    #[test]
+    fn test_rate_limited_client_acquire_batch() {
+        let client = RateLimitedClient {
+            rate_limiter: RateLimiter::new(3),
+            key: "a".into(),
+        };
+        client.rate_limiter.acquire("a").unwrap();
+
+        assert!(matches!(
+            client.acquire_batch(4),
+            Err(api::Error::ExceedsMaximum(name, 3)) if name == "batch size"
+        ));
+        assert!(client.acquire_batch(3).is_ok());
+        assert!(matches!(
+            client.acquire_batch(2),
+            Err(api::Error::TooManyRequests(20))
+        ));
+        assert!(
+            client.acquire_batch(1).is_ok(),
+            "The batch request took its token"
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_disabled() {
+        let rate_limiter = RateLimiter::new(0);
+
+        assert!(!rate_limiter.is_enabled());
+
+        for _ in 0..100 {
+            assert_eq!(rate_limiter.acquire("a"), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_client_key() {
+        let rate_limiter = RateLimiter::new(1).with_api_keys(&["secret".into()]);
+        let request = |api_key: Option<&str>| {
+            let mut builder =
+                Request::builder().extension(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 1234))));
+            if let Some(api_key) = api_key {
+                builder = builder.header(API_KEY_HEADER, api_key);
+            }
+
+            builder.body(Body::empty()).unwrap()
+        };
+
+        assert_eq!(
+            rate_limiter.client_key(&request(Some("secret"))),
+            "key:secret"
+        );
+        assert_eq!(
+            rate_limiter.client_key(&request(Some("unknown"))),
+            "ip:10.0.0.1",
+            "Unknown API keys should not get their own bucket"
+        );
+        assert_eq!(rate_limiter.client_key(&request(None)), "ip:10.0.0.1");
+
+        let request = Request::builder().body(Body::empty()).unwrap();
+        assert_eq!(rate_limiter.client_key(&request), "ip:unknown");
+    }
+}
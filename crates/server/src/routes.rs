@@ -1,24 +1,47 @@
 //! This module provides HTTP handlers for screenshot functionality in the Pantin Server API.
 //!
 //! It allows clients to request screenshots of web pages using a headless browser.
-//! The API supports various screenshot modes and response formats.
+//! The API supports various screenshot modes and response formats, a server-sent events variant
+//! that reports the capture progress, a batch variant streaming the results of several requests as NDJSON,
+//! and a diff variant comparing the screenshots of two URLs.
 
-use std::time::Duration;
+use std::{
+    convert::Infallible,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Cursor,
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
 
 use axum::{
     Json,
+    body::{Body, Bytes, HttpBody},
     extract::State,
-    http::{StatusCode, header},
-    response::{IntoResponse, Response},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, Sse},
+    },
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use deadpool::managed::Object;
+use futures_util::{Stream, StreamExt, stream};
+use language_tags::LanguageTag;
+use pantin_browser::{
+    Browser, ColorScheme, ScreenshotFindElementUsing, ScreenshotParameters, Timeouts,
 };
-use pantin_browser::{Browser, ScreenshotFindElementUsing, ScreenshotParameters};
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tokio::sync::{OwnedSemaphorePermit, mpsc};
+use tracing::{Instrument, Span, debug, error, field, info, instrument, warn};
 
 use crate::{
     api,
-    api::{Failure, Query, Success},
-    state,
+    api::{Failure, JsonBody, Query, Success},
+    browser_pool::BrowserManager,
+    cache::CachedScreenshot,
+    host_policy::HostPolicy,
+    image_diff, rate_limit, state,
+    state::ScreenshotLimits,
 };
 
 /// Health-check endpoint that returns a JSON response with "pong".
@@ -26,27 +49,160 @@ pub async fn ping() -> Response {
     Json(Success::<String>::new("pong".into())).into_response()
 }
 
+/// Maximum time the [`health`] endpoint waits for a browser.
+const HEALTH_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Health-check endpoint that verifies a browser can actually be acquired from the pool.
+///
+/// Returns a JSON response with "healthy", or a 503 Service Unavailable error describing
+/// why no browser could be obtained (e.g. the browser program is missing or the pool is exhausted),
+/// or that the server is draining (see [`admin_drain`]).
+pub async fn health(State(state): State<state::State>) -> Response {
+    if state.is_draining() {
+        return api::Error::Draining.into_response();
+    }
+
+    match state.get_browser_within(HEALTH_ACQUIRE_TIMEOUT).await {
+        Ok(_browser) => Json(Success::<String>::new("healthy".into())).into_response(),
+        Err(error) => {
+            error!("Health check failed: {:?}", error);
+
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(Failure::new("unhealthy", format!("unhealthy: {error}"))),
+            )
+                .into_response()
+        },
+    }
+}
+
+/// Metrics endpoint that returns the server metrics in the Prometheus text format.
+///
+/// This exposes the screenshot requests and errors counters, the browser pool gauges (maximum size, size
+/// and available browsers) and the histogram of the capture durations.
+pub async fn metrics_prometheus(State(state): State<state::State>) -> Response {
+    let body = state.metrics().prometheus(state.browser_pool_status());
+
+    (
+        StatusCode::OK,
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    )
+        .into_response()
+}
+
+/// Maximum time the [`version`] endpoint waits for an idle browser.
+const VERSION_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The build information returned by the [`version`] endpoint.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildInfo {
+    /// Version of the Pantin Server crate.
+    version: &'static str,
+    /// Short git hash of the build, if known when it was built.
+    git_hash: Option<&'static str>,
+    /// Version of the browser, from the capabilities of its Marionette session.
+    firefox_version: Option<String>,
+}
+
+/// Build information endpoint that returns the version of the server, its git hash and the version of the browser.
+///
+/// The browser version is only reported (otherwise `null`) if an idle browser of the pool can be acquired
+/// within [`VERSION_ACQUIRE_TIMEOUT`], so this endpoint never waits for a browser to be opened.
+pub async fn version(State(state): State<state::State>) -> Response {
+    let firefox_version = if state.browser_pool_status().available > 0 {
+        match state.get_browser_within(VERSION_ACQUIRE_TIMEOUT).await {
+            Ok(browser) => browser
+                .capabilities()
+                .get("browserVersion")
+                .and_then(|version| version.as_str())
+                .map(ToString::to_string),
+            Err(error) => {
+                debug!(?error, "No browser to report the version of");
+                None
+            },
+        }
+    } else {
+        None
+    };
+
+    Json(Success::new(BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: option_env!("PANTIN_GIT_HASH"),
+        firefox_version,
+    }))
+    .into_response()
+}
+
+/// Admin endpoint that starts draining the server before a rolling deploy.
+///
+/// The new screenshot requests are then answered with a 503 Service Unavailable error, and `/health` reports
+/// the draining state, while the requests in flight complete. Draining cannot be stopped, the server is meant
+/// to be shut down once drained.
+///
+/// # Errors
+///
+/// Returns an [`api::Error::Unauthorized`] if the `X-Api-Key` header is not the admin API key.
+pub async fn admin_drain(State(state): State<state::State>, headers: HeaderMap) -> api::Result {
+    let api_key = headers
+        .get(rate_limit::API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    if !state.is_admin(api_key) {
+        return Err(api::Error::Unauthorized);
+    }
+
+    if !state.drain() {
+        info!("Draining, the new screenshot requests are rejected");
+    }
+
+    Ok(Json(Success::<String>::new("draining".into())).into_response())
+}
+
 /// Fallback endpoint that returns a 404 Not Found error as a JSON response.
 pub async fn not_found() -> Response {
-    (StatusCode::NOT_FOUND, Json(Failure::new("not found"))).into_response()
+    (
+        StatusCode::NOT_FOUND,
+        Json(Failure::new("not_found", "not found")),
+    )
+        .into_response()
 }
 
+/// Page load timeout in milliseconds used when the query has no `nav_timeout` (the Firefox default).
+const DEFAULT_NAV_TIMEOUT: u64 = 300_000;
+
+/// Number of elements captured in `all-matching` mode when the query has no `max_elements`.
+const DEFAULT_MAX_ELEMENTS: u16 = 10;
+
+/// Accuracy in meters of the position reported to the pages when the query overrides the geolocation.
+const GEOLOCATION_ACCURACY: f64 = 10.0;
+
 /// Specifies the mode used to capture a screenshot.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash, Deserialize)]
 #[serde(rename_all(deserialize = "lowercase"))]
 pub enum ScreenshotMode {
     /// Capture the full page.
     Full,
+    /// Capture the full page, clamped to a maximum height.
+    #[serde(rename(deserialize = "full-clamped"))]
+    FullClamped,
     /// Capture only the visible (viewport) area.
     Viewport,
     /// Capture a specific element identified by a CSS selector.
     Selector,
     /// Capture a specific element identified by an `XPath` expression.
     XPath,
+    /// Capture each element matching a CSS selector, returned as a JSON array of Base64-encoded PNG.
+    #[serde(rename(deserialize = "all-matching"))]
+    AllMatching,
 }
 
 /// Specifies the response type for the screenshot.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 #[serde(rename_all(deserialize = "kebab-case"))]
 pub enum ScreenshotResponseType {
     /// Returns the screenshot as an attachment (with a filename).
@@ -55,56 +211,1212 @@ pub enum ScreenshotResponseType {
     ImagePngBase64,
     /// Returns the screenshot as raw PNG bytes.
     ImagePngBytes,
+    /// Returns the screenshot as raw JPEG bytes.
+    ImageJpeg,
+    /// Returns the screenshot as raw WebP bytes.
+    ImageWebp,
     /// Returns a JSON containing a Base64-encoded PNG string.
     JsonPngBase64,
     /// Returns a JSON containing raw PNG bytes.
     JsonPngBytes,
+    /// Returns a JSON containing a ready-to-use `data:image/png;base64,...` URL.
+    JsonDataUrl,
+    /// Returns the HTML source of the rendered page instead of a screenshot.
+    Html,
+    /// Returns a `multipart/mixed` body with a JSON metadata part followed by the raw PNG bytes part.
+    Multipart,
+}
+
+/// Specifies the image format of the raw bytes responses, the screenshot is transcoded from PNG if needed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all(deserialize = "lowercase"))]
+pub enum ImageFormat {
+    /// PNG, as captured by the browser.
+    #[default]
+    Png,
+    /// JPEG, without transparency.
+    Jpeg,
+    /// Lossless WebP.
+    Webp,
+}
+
+impl ImageFormat {
+    /// Returns the MIME type of the format.
+    const fn content_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    /// Returns the file extension of the format.
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
 }
 
 /// Represents the query parameters for a screenshot request.
 ///
-/// This structure is deserialized from the URL query string.
-#[derive(Debug, Deserialize)]
+/// This structure is deserialized from the URL query string, or from the JSON body of the `POST` requests.
+#[derive(Debug, Default, Deserialize)]
 pub struct ScreenshotQuery {
     /// URL of the page to take a screenshot.
     url: String,
     /// Delay in milliseconds after `DOMContentLoaded` before taking the screenshot (default: 0).
     delay: Option<u16>,
+    /// Page load timeout in milliseconds (default: 300000, as Firefox).
+    nav_timeout: Option<u16>,
     /// Screenshot width (default: 800).
     width: Option<u16>,
     /// Screenshot height (default: 600).
     height: Option<u16>,
     /// Whether to show the scrollbar on `html` and `body` elements (default: false).
     scrollbar: Option<bool>,
-    /// Response type: one of 'attachment', 'image-png-base64', 'image-png-bytes', 'json-png-base64' or 'json-png-bytes' (default: 'image-png-bytes').
+    /// Response type: one of 'attachment', 'image-png-base64', 'image-png-bytes', 'json-png-base64', 'json-png-bytes',
+    /// 'json-data-url', 'html' or 'multipart' (default: 'image-png-bytes'), ignored if `mode` is 'all-matching'.
     response_type: Option<ScreenshotResponseType>,
-    /// Screenshot mode: one of 'full', 'viewport', 'selector' or 'xpath' (default: 'viewport').
+    /// Screenshot mode: one of 'full', 'full-clamped', 'viewport', 'selector', 'xpath' or 'all-matching'
+    /// (default: 'viewport').
     mode: Option<ScreenshotMode>,
-    /// CSS selector (required if `mode` is 'selector').
+    /// CSS selector (required if `mode` is 'selector' or 'all-matching').
     selector: Option<String>,
+    /// Maximum number of elements captured if `mode` is 'all-matching' (default: 10).
+    max_elements: Option<u16>,
     /// `XPath` expression (required if `mode` is 'xpath').
     xpath: Option<String>,
+    /// Maximum height in CSS pixels of the capture, from the top of the page (required if `mode` is 'full-clamped').
+    max_full_height: Option<u32>,
+    /// Horizontal scroll offset in pixels the viewport is captured at (only if `mode` is 'viewport', default: 0).
+    scroll_x: Option<u32>,
+    /// Vertical scroll offset in pixels the viewport is captured at (only if `mode` is 'viewport', default: 0).
+    scroll_y: Option<u32>,
+    /// Latitude reported by the geolocation API, in decimal degrees (requires `geo_lon`).
+    geo_lat: Option<f64>,
+    /// Longitude reported by the geolocation API, in decimal degrees (requires `geo_lat`).
+    geo_lon: Option<f64>,
+    /// Timezone of the browser, as an IANA name like 'Europe/Paris' (default: the server timezone).
+    tz: Option<String>,
+    /// Locale of the browser, as a BCP 47 language tag like 'fr-CH' (default: the browser locale).
+    locale: Option<String>,
+    /// Color scheme of the `prefers-color-scheme` media feature: one of 'dark' or 'light' (default: the browser one).
+    color_scheme: Option<ColorScheme>,
+    /// Absolute URL sent in the `Referer` header of the navigation (and its redirects).
+    referer: Option<String>,
+    /// Name of a cookie deleted once the page is loaded.
+    delete_cookie: Option<String>,
+    /// JavaScript evaluated in every document before the page scripts (e.g. a polyfill), only accepted in a JSON body.
+    init_script: Option<String>,
+    /// Image format of the raw bytes responses: one of 'png', 'jpeg' or 'webp' (default: the format of the
+    /// `response_type`, or 'png').
+    format: Option<ImageFormat>,
+}
+
+impl ScreenshotQuery {
+    /// Ensures the requested dimensions, elements count and delay do not exceed the given [`ScreenshotLimits`],
+    /// the image format agrees with the response type, the scroll offset is only set in viewport mode,
+    /// the geolocation coordinates are complete and valid, and the timezone and locale are well-formed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`api::Error::ExceedsMaximum`] naming the first field out of bounds,
+    /// an [`api::Error::Unsupported`] if the format conflicts with the response type or a scroll offset is set
+    /// in another mode than viewport or the timezone (IANA name) or locale (BCP 47 tag) is invalid,
+    /// or an [`api::Error::MissingField`] / [`api::Error::OutOfRange`] for invalid coordinates.
+    fn validate(&self, limits: ScreenshotLimits) -> api::Result<()> {
+        if self.width.unwrap_or(800) > limits.max_width {
+            return Err(api::Error::ExceedsMaximum(
+                "width".into(),
+                u32::from(limits.max_width),
+            ));
+        }
+
+        if self.height.unwrap_or(600) > limits.max_height {
+            return Err(api::Error::ExceedsMaximum(
+                "height".into(),
+                u32::from(limits.max_height),
+            ));
+        }
+
+        // The maximum number of elements only applies to the `all-matching` mode, the others capture one element.
+        if matches!(self.mode, Some(ScreenshotMode::AllMatching))
+            && self.max_elements() > limits.max_elements
+        {
+            return Err(api::Error::ExceedsMaximum(
+                "max_elements".into(),
+                u32::from(limits.max_elements),
+            ));
+        }
+
+        if self.delay.unwrap_or(0) > limits.max_delay {
+            return Err(api::Error::ExceedsMaximum(
+                "delay".into(),
+                u32::from(limits.max_delay),
+            ));
+        }
+
+        if self
+            .format
+            .is_some_and(|format| format != self.image_format())
+        {
+            return Err(api::Error::Unsupported(
+                "format conflicting with response_type".into(),
+            ));
+        }
+
+        if self.max_full_height.is_some() && !matches!(self.mode, Some(ScreenshotMode::FullClamped))
+        {
+            return Err(api::Error::Unsupported(
+                "max_full_height requires mode=full-clamped".into(),
+            ));
+        }
+
+        // Like the page height, the maximum is in CSS pixels.
+        if let Some(max_full_height) = self.max_full_height {
+            if max_full_height == 0 {
+                return Err(api::Error::OutOfRange("max_full_height".into()));
+            }
+
+            if limits.max_page_height > 0 && max_full_height > limits.max_page_height {
+                return Err(api::Error::ExceedsMaximum(
+                    "max_full_height".into(),
+                    limits.max_page_height,
+                ));
+            }
+        }
+
+        if self.scroll_offset().is_some()
+            && !matches!(self.mode, None | Some(ScreenshotMode::Viewport))
+        {
+            return Err(api::Error::Unsupported(
+                "scroll_x and scroll_y require mode=viewport".into(),
+            ));
+        }
+
+        if let Some(tz) = self.tz.as_deref().filter(|tz| !is_timezone(tz)) {
+            return Err(api::Error::Unsupported(format!(
+                "tz '{tz}', expected an IANA timezone name like 'Europe/Paris'"
+            )));
+        }
+
+        if let Some(locale) = self.locale.as_deref().filter(|locale| !is_locale(locale)) {
+            return Err(api::Error::Unsupported(format!(
+                "locale '{locale}', expected a BCP 47 language tag like 'fr-CH'"
+            )));
+        }
+
+        match (self.geo_lat, self.geo_lon) {
+            (Some(_), None) => Err(api::Error::MissingField("geo_lon".into())),
+            (None, Some(_)) => Err(api::Error::MissingField("geo_lat".into())),
+            (Some(lat), _) if !(-90.0..=90.0).contains(&lat) => {
+                Err(api::Error::OutOfRange("geo_lat".into()))
+            },
+            (_, Some(lon)) if !(-180.0..=180.0).contains(&lon) => {
+                Err(api::Error::OutOfRange("geo_lon".into()))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the key identifying this request in the screenshot cache.
+    ///
+    /// The key is a hash of the normalized query: defaults are applied to the missing parameters
+    /// and the selector (or `XPath` expression) only counts when the mode uses it, so equivalent
+    /// requests share the same key.
+    fn cache_key(&self) -> u64 {
+        let mode = self.mode.unwrap_or(ScreenshotMode::Viewport);
+        let mut hasher = DefaultHasher::new();
+        self.url.hash(&mut hasher);
+        self.delay.unwrap_or(0).hash(&mut hasher);
+        self.width.unwrap_or(800).hash(&mut hasher);
+        self.height.unwrap_or(600).hash(&mut hasher);
+        self.scrollbar.unwrap_or(false).hash(&mut hasher);
+        self.geo_lat.map(f64::to_bits).hash(&mut hasher);
+        self.geo_lon.map(f64::to_bits).hash(&mut hasher);
+        self.tz.hash(&mut hasher);
+        self.locale.hash(&mut hasher);
+        self.color_scheme.hash(&mut hasher);
+        self.referer.hash(&mut hasher);
+        self.delete_cookie.hash(&mut hasher);
+        self.init_script.hash(&mut hasher);
+        self.response_type().hash(&mut hasher);
+        self.image_format().hash(&mut hasher);
+        mode.hash(&mut hasher);
+
+        match mode {
+            ScreenshotMode::Selector => self.selector.hash(&mut hasher),
+            ScreenshotMode::XPath => self.xpath.hash(&mut hasher),
+            ScreenshotMode::AllMatching => {
+                self.selector.hash(&mut hasher);
+                self.max_elements().hash(&mut hasher);
+            },
+            ScreenshotMode::Viewport => self.scroll_offset().unwrap_or_default().hash(&mut hasher),
+            ScreenshotMode::FullClamped => self.max_full_height.hash(&mut hasher),
+            ScreenshotMode::Full => {},
+        }
+
+        hasher.finish()
+    }
+
+    /// Returns the requested response type, or the raw bytes response type of the format (default: PNG bytes).
+    fn response_type(&self) -> ScreenshotResponseType {
+        self.response_type
+            .unwrap_or_else(|| match self.format.unwrap_or_default() {
+                ImageFormat::Png => ScreenshotResponseType::ImagePngBytes,
+                ImageFormat::Jpeg => ScreenshotResponseType::ImageJpeg,
+                ImageFormat::Webp => ScreenshotResponseType::ImageWebp,
+            })
+    }
+
+    /// Returns the image format of the response: the one of the response type, or the requested format
+    /// for an attachment. The other response types are always PNG.
+    fn image_format(&self) -> ImageFormat {
+        match self.response_type() {
+            ScreenshotResponseType::ImageJpeg => ImageFormat::Jpeg,
+            ScreenshotResponseType::ImageWebp => ImageFormat::Webp,
+            ScreenshotResponseType::Attachment => self.format.unwrap_or_default(),
+            _ => ImageFormat::Png,
+        }
+    }
+
+    /// Returns the scroll offset the viewport is captured at, if any of `scroll_x` and `scroll_y` is set.
+    fn scroll_offset(&self) -> Option<(u32, u32)> {
+        if self.scroll_x.is_none() && self.scroll_y.is_none() {
+            return None;
+        }
+
+        Some((self.scroll_x.unwrap_or(0), self.scroll_y.unwrap_or(0)))
+    }
+
+    /// Returns the maximum number of elements captured in `all-matching` mode.
+    fn max_elements(&self) -> u16 {
+        self.max_elements.unwrap_or(DEFAULT_MAX_ELEMENTS)
+    }
+}
+
+/// Returns `true` if the given timezone is a name of the IANA time zone database (e.g. `Europe/Paris`),
+/// with its exact case since the browser reads it from the `TZ` environment variable.
+fn is_timezone(timezone: &str) -> bool {
+    jiff_tzdb::get(timezone).is_some_and(|(name, _)| name == timezone)
+}
+
+/// Returns `true` if the given locale is a valid BCP 47 language tag (e.g. `fr-CH`), its subtags being
+/// registered in the IANA Language Subtag Registry.
+fn is_locale(locale: &str) -> bool {
+    LanguageTag::parse(locale).is_ok_and(|tag| tag.is_valid())
+}
+
+/// A browser serving a single screenshot request.
+enum RequestBrowser {
+    /// A browser of the pool, recycled once the request is done.
+    Pooled(Object<BrowserManager>),
+    /// A browser opened for this request only, because it overrides the timezone or the locale.
+    ///
+    /// It holds one of the dedicated browser permits (see [`state::State::dedicated_browser_permit`])
+    /// until it is closed.
+    Dedicated {
+        browser: Browser,
+        _permit: OwnedSemaphorePermit,
+    },
+}
+
+impl RequestBrowser {
+    /// Gets a browser from the pool, or opens a dedicated one if the query sets a timezone or a locale,
+    /// since both are set when launching the browser.
+    ///
+    /// Like the pooled ones, the dedicated browsers are bounded by the pool size and wait at most the acquire
+    /// timeout for a permit.
+    async fn acquire(state: &state::State, query: &ScreenshotQuery) -> api::Result<Self> {
+        if query.tz.is_none() && query.locale.is_none() {
+            return Ok(Self::Pooled(state.get_browser().await?));
+        }
+
+        let permit = state.dedicated_browser_permit().await?;
+        let browser = state
+            .open_browser(query.tz.clone(), query.locale.clone())
+            .await?;
+
+        Ok(Self::Dedicated {
+            browser,
+            _permit: permit,
+        })
+    }
+
+    /// Drops the browser instead of returning it to the pool, for example after a transient failure.
+    fn discard(self) {
+        if let Self::Pooled(browser) = self {
+            // Takes the broken browser out of the pool, so it is dropped (and killed) instead of recycled.
+            drop(Object::take(browser));
+        }
+    }
+}
+
+impl Deref for RequestBrowser {
+    type Target = Browser;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Pooled(browser) => browser,
+            Self::Dedicated { browser, .. } => browser,
+        }
+    }
+}
+
+impl DerefMut for RequestBrowser {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Pooled(browser) => browser,
+            Self::Dedicated { browser, .. } => browser,
+        }
+    }
+}
+
+/// Represents a step of the capture pipeline, reported to the client by [`screenshot_stream`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScreenshotEvent {
+    /// The browser is navigating to the requested URL.
+    Navigating,
+    /// The page is being prepared (scrollbar, window size, delay).
+    Waiting,
+    /// The screenshot is being captured.
+    Capturing,
+    /// The captured screenshot is being encoded.
+    Encoding,
+    /// The capture is done, holds the Base64-encoded PNG.
+    Done(String),
+    /// The capture failed, holds the cause.
+    Error(String),
+}
+
+impl ScreenshotEvent {
+    /// Returns the name of the event as sent in the SSE `event` field.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Navigating => "navigating",
+            Self::Waiting => "waiting",
+            Self::Capturing => "capturing",
+            Self::Encoding => "encoding",
+            Self::Done(_) => "done",
+            Self::Error(_) => "error",
+        }
+    }
+}
+
+impl From<ScreenshotEvent> for Event {
+    fn from(event: ScreenshotEvent) -> Self {
+        let name = event.name();
+        let data = match event {
+            ScreenshotEvent::Done(data) | ScreenshotEvent::Error(data) => data,
+            _ => name.to_string(),
+        };
+
+        Self::default().event(name).data(data)
+    }
+}
+
+/// Reports [`ScreenshotEvent`]s emitted by the capture pipeline, if someone is listening.
+#[derive(Debug, Clone, Default)]
+struct Progress {
+    sender: Option<mpsc::UnboundedSender<ScreenshotEvent>>,
+}
+
+impl Progress {
+    const fn new(sender: mpsc::UnboundedSender<ScreenshotEvent>) -> Self {
+        Self {
+            sender: Some(sender),
+        }
+    }
+
+    fn emit(&self, event: ScreenshotEvent) {
+        match &self.sender {
+            Some(sender) if sender.send(event).is_err() => {
+                debug!("Screenshot progress receiver dropped");
+            },
+            _ => {},
+        }
+    }
 }
 
 /// Handles screenshot requests by processing query parameters, interacting with a browser,
 /// and returning the screenshot in the requested format.
 ///
 /// This endpoint performs the following steps:
-/// 1. Retrieves a browser instance from the shared state.
-/// 2. Navigates the browser to the specified URL.
-/// 3. Optionally hides scrollbars, sets the window size, and waits for a delay.
-/// 4. Determines the screenshot mode and captures the screenshot.
-/// 5. Returns the screenshot as raw bytes, Base64-encoded data, an attachment, or JSON-wrapped data.
+/// 1. Negotiates the response type from the `Accept` header if the query sets neither `response_type`
+///    nor `format` (see [`negotiate_response_type`]), and validates the query against the screenshot limits.
+/// 2. Answers from the screenshot cache if an identical request was captured recently.
+/// 3. Retrieves a browser instance from the shared state, or opens a dedicated one if the timezone
+///    or the locale is overridden.
+/// 4. Navigates the browser to the specified URL.
+/// 5. Optionally hides scrollbars, sets the window size, and waits for a delay.
+/// 6. Determines the screenshot mode and captures the screenshot, once more with another browser
+///    if a transient Marionette failure occurred (like a stale session).
+/// 7. Returns the screenshot as raw bytes, Base64-encoded data, an attachment, or JSON-wrapped data,
+///    or the HTML source of the page.
+///
+/// The time spent navigating, resizing and capturing is recorded on the request span (`navigate_ms`,
+/// `resize_ms` and `capture_ms`) and logged once the screenshot is captured.
+/// The URL, the final status code and the body size (`url`, `status_code` and `bytes_len`) are recorded too,
+/// and logged in a single summary event once the request completed, successfully or not.
+/// The successful responses carry a `Vary: Accept` header, as the default response type is negotiated from it.
+/// The requests, the failed ones and the capture durations are counted in the [`Metrics`](crate::metrics::Metrics).
+///
+/// The `init_script` is rejected in the query string, see [`screenshot_post`].
 pub async fn screenshot(
-    state: State<state::State>,
+    State(state): State<state::State>,
+    headers: HeaderMap,
     Query(query): Query<ScreenshotQuery>,
 ) -> api::Result {
+    reject_query_init_script(&query)?;
+
+    serve_screenshot(&state, &headers, query).await
+}
+
+/// Handles screenshot requests like [`screenshot`], but with the parameters in a JSON body, the only place
+/// the `init_script` (often too large for a URL) is accepted.
+pub async fn screenshot_post(
+    State(state): State<state::State>,
+    headers: HeaderMap,
+    JsonBody(query): JsonBody<ScreenshotQuery>,
+) -> api::Result {
+    serve_screenshot(&state, &headers, query).await
+}
+
+/// Rejects the `init_script` of a query read from the query string.
+fn reject_query_init_script(query: &ScreenshotQuery) -> api::Result<()> {
+    if query.init_script.is_some() {
+        return Err(api::Error::Unsupported(
+            "init_script in the query string, send it in a POST body".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Serves a screenshot request, read from the query string or from a JSON body (see [`screenshot`]).
+#[instrument(
+    name = "screenshot",
+    skip_all,
+    fields(
+        url = %query.url,
+        status_code = field::Empty,
+        bytes_len = field::Empty,
+        navigate_ms = field::Empty,
+        resize_ms = field::Empty,
+        capture_ms = field::Empty
+    )
+)]
+async fn serve_screenshot(
+    state: &state::State,
+    headers: &HeaderMap,
+    mut query: ScreenshotQuery,
+) -> api::Result {
+    if query.response_type.is_none() && query.format.is_none() {
+        query.response_type = negotiate_response_type(headers);
+    }
+
     info!(?query, "Screenshot");
 
-    let mut browser = state.get_browser().await?;
+    let metrics = state.metrics();
+    metrics.screenshot();
+
+    let start = Instant::now();
+    let response = take_screenshot(state, &query).await;
+    if response.is_err() {
+        metrics.screenshot_error();
+    }
+    record_summary(&response, start);
+
+    // The default response type depends on the `Accept` header, which the HTTP caches must key on too.
+    response.map(|mut response| {
+        response
+            .headers_mut()
+            .append(header::VARY, HeaderValue::from_static("accept"));
+        response
+    })
+}
+
+/// Returns the response type negotiated from the `Accept` header: the one of the supported media type with
+/// the highest quality (the first one on a tie), or `None` if the header accepts none of them (e.g. only `*/*`).
+///
+/// `application/json` selects `json-data-url`, and `image/png`, `image/jpeg` and `image/webp` the raw bytes
+/// of the image. The other media types are ignored, notably `text/html`, sent by the browsers navigating
+/// to the endpoint.
+fn negotiate_response_type(headers: &HeaderMap) -> Option<ScreenshotResponseType> {
+    let accept = headers.get(header::ACCEPT)?.to_str().ok()?;
+    let mut negotiated = None;
+    let mut best_quality = 0.0;
+
+    for media_range in accept.split(',') {
+        let mut parts = media_range.split(';');
+        let media_type = parts.next().unwrap_or_default().trim();
+        let response_type = match media_type.to_ascii_lowercase().as_str() {
+            "application/json" => ScreenshotResponseType::JsonDataUrl,
+            "image/png" => ScreenshotResponseType::ImagePngBytes,
+            "image/jpeg" => ScreenshotResponseType::ImageJpeg,
+            "image/webp" => ScreenshotResponseType::ImageWebp,
+            _ => continue,
+        };
+        let quality = parts
+            .find_map(|parameter| parameter.trim().strip_prefix("q="))
+            .and_then(|quality| quality.parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        if quality > best_quality {
+            negotiated = Some(response_type);
+            best_quality = quality;
+        }
+    }
+
+    negotiated
+}
+
+/// Records the status code and the body size of the response on the request span, and logs the summary event.
+///
+/// The body size is unknown (and not recorded) for the errors, and for the responses streamed without
+/// a `Content-Length` header.
+fn record_summary(response: &api::Result, start: Instant) {
+    let (status_code, bytes_len) = match response {
+        Ok(response) => (response.status(), body_len(response)),
+        Err(error) => (error.status(), None),
+    };
+    let elapsed_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+    let span = Span::current();
+    span.record("status_code", status_code.as_u16());
+    if let Some(bytes_len) = bytes_len {
+        span.record("bytes_len", bytes_len);
+    }
+
+    info!(
+        status_code = status_code.as_u16(),
+        bytes_len, elapsed_ms, "Screenshot completed"
+    );
+}
+
+/// Returns the size of the response body, from its `Content-Length` header or its exact size hint.
+fn body_len(response: &Response) -> Option<u64> {
+    response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse().ok())
+        .or_else(|| response.body().size_hint().exact())
+}
+
+/// Answers the screenshot request from the cache, or captures the screenshot (see [`screenshot`]).
+async fn take_screenshot(state: &state::State, query: &ScreenshotQuery) -> api::Result {
+    query.validate(state.screenshot_limits())?;
+
+    let response_type = query.response_type();
+    let format = query.image_format();
+
+    let cache = state.screenshot_cache();
+    let cache_key = query.cache_key();
+    // The `multipart` metadata describes its own capture (e.g. its duration), and is never answered from the cache.
+    let cacheable = !matches!(response_type, ScreenshotResponseType::Multipart);
+
+    if let Some(screenshot) = cacheable.then(|| cache.get(cache_key)).flatten() {
+        return Ok(screenshot_response(response_type, format, screenshot));
+    }
+
+    let start = Instant::now();
+    let screenshot = retry_transient(move || async move {
+        let mut browser = RequestBrowser::acquire(state, query).await?;
+        let result = capture_screenshot(
+            &mut browser,
+            query,
+            state.screenshot_limits(),
+            state.host_policy(),
+            response_type,
+        )
+        .await;
+
+        if matches!(&result, Err(error) if error.is_transient()) {
+            browser.discard();
+        }
 
-    browser.navigate(query.url).await?;
+        result
+    })
+    .await?;
 
+    state.metrics().capture_duration(start.elapsed());
+    info!("Screenshot captured");
+    if cacheable {
+        cache.insert(cache_key, screenshot.clone());
+    }
+
+    Ok(screenshot_response(response_type, format, screenshot))
+}
+
+/// Runs the operation and, if it fails with a transient error (see [`api::Error::is_transient`]),
+/// runs it once more. Other errors are returned as is.
+async fn retry_transient<T, F, Fut>(mut operation: F) -> api::Result<T>
+where
+    F: FnMut() -> Fut + Send,
+    Fut: Future<Output = api::Result<T>> + Send,
+{
+    match operation().await {
+        Err(error) if error.is_transient() => {
+            warn!(
+                ?error,
+                "Transient failure, retrying once with another browser"
+            );
+            operation().await
+        },
+        result => result,
+    }
+}
+
+/// Prepares the page described by the query with the given browser and captures the screenshot
+/// in the requested response type.
+async fn capture_screenshot(
+    browser: &mut Browser,
+    query: &ScreenshotQuery,
+    limits: ScreenshotLimits,
+    host_policy: &HostPolicy,
+    response_type: ScreenshotResponseType,
+) -> api::Result<CachedScreenshot> {
+    let started = Instant::now();
+    let target = capture(browser, query, limits, host_policy, &Progress::default()).await?;
+
+    let start = Instant::now();
+    let screenshot = match target {
+        CaptureTarget::One(shot) => {
+            let format = query.image_format();
+            screenshot_as(browser, shot, response_type, format, started).await
+        },
+        CaptureTarget::Each(parameters) => screenshot_json_images(browser, parameters).await,
+    };
+    record_elapsed_ms("capture_ms", start);
+
+    screenshot
+}
+
+/// Captures a single screenshot in the requested response type.
+///
+/// The `started` instant is the one the page preparation began at, reported in the `multipart` metadata.
+async fn screenshot_as(
+    browser: &mut Browser,
+    shot: Shot,
+    response_type: ScreenshotResponseType,
+    format: ImageFormat,
+    started: Instant,
+) -> api::Result<CachedScreenshot> {
+    match response_type {
+        ScreenshotResponseType::ImagePngBytes
+        | ScreenshotResponseType::ImageJpeg
+        | ScreenshotResponseType::ImageWebp
+        | ScreenshotResponseType::Attachment => screenshot_image_bytes(browser, shot, format).await,
+        ScreenshotResponseType::ImagePngBase64 => screenshot_image_base64(browser, shot).await,
+        ScreenshotResponseType::JsonPngBase64 => screenshot_json_base64(browser, shot).await,
+        ScreenshotResponseType::JsonPngBytes => screenshot_json_bytes(browser, shot).await,
+        ScreenshotResponseType::JsonDataUrl => screenshot_json_data_url(browser, shot).await,
+        ScreenshotResponseType::Html => page_source_html(browser).await,
+        ScreenshotResponseType::Multipart => screenshot_multipart(browser, shot, started).await,
+    }
+}
+
+/// Records the milliseconds elapsed since `start` as the `field` of the current span.
+fn record_elapsed_ms(field: &'static str, start: Instant) {
+    let elapsed_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+    Span::current().record(field, elapsed_ms);
+}
+
+/// Maximum number of screenshot requests in a batch.
+const MAX_BATCH_SIZE: u16 = 100;
+
+/// A line of the [`screenshot_batch`] response, reporting the result of one screenshot request.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchResult {
+    /// Position of the request in the batch, the results are streamed in completion order.
+    index: usize,
+    url: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cause: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+}
+
+impl BatchResult {
+    fn new(index: usize, url: String, result: api::Result<String>) -> Self {
+        let (data_url, status, cause, code) = match result {
+            Ok(data_url) => (Some(data_url), None, None, None),
+            Err(error) => {
+                // The error is only logged here, like `api::Error::into_response` does for the other routes.
+                error!(index, ?error, "Batch screenshot failed");
+
+                (
+                    None,
+                    Some(error.status().as_u16()),
+                    Some(error.cause()),
+                    Some(error.code()),
+                )
+            },
+        };
+
+        Self {
+            index,
+            url,
+            ok: data_url.is_some(),
+            data_url,
+            status,
+            cause,
+            code,
+        }
+    }
+
+    /// Returns the result serialized as a JSON line, ending with a newline.
+    fn to_line(&self) -> serde_json::Result<Bytes> {
+        let mut line = serde_json::to_vec(self)?;
+        line.push(b'\n');
+
+        Ok(Bytes::from(line))
+    }
+}
+
+/// Handles a batch of screenshot requests, sent as a JSON array of queries, and streams their results as
+/// newline-delimited JSON.
+///
+/// Each request is captured like [`screenshot`] would with the `json-data-url` response type, without the cache,
+/// and results in one line: `{ "index": 0, "url": "...", "ok": true, "dataUrl": "data:image/png;base64,..." }`,
+/// or `{ "index": 0, "url": "...", "ok": false, "status": 400, "cause": "...", "code": "..." }` if it failed.
+/// The lines are sent as soon as the captures complete, so not in the batch order, and the failure of a request
+/// does not stop the batch.
+/// The `response_type` of the queries is ignored and the `all-matching` mode is not supported.
+///
+/// The requests are captured concurrently, at most as many at once as the browser pool maximum size.
+/// Batches larger than [`MAX_BATCH_SIZE`] are rejected before the stream starts, as are the batches exceeding
+/// the rate limit of the client, which takes one token per request.
+/// The requests not captured within the request timeout, counted from the start of the batch, fail with
+/// a `request_timeout` code.
+#[instrument(name = "screenshot_batch", skip_all, fields(size = queries.len()))]
+pub async fn screenshot_batch(
+    State(state): State<state::State>,
+    client: Option<rate_limit::RateLimitedClient>,
+    JsonBody(queries): JsonBody<Vec<ScreenshotQuery>>,
+) -> api::Result {
+    info!("Screenshot batch");
+
+    if queries.len() > usize::from(MAX_BATCH_SIZE) {
+        return Err(api::Error::ExceedsMaximum(
+            "batch size".into(),
+            u32::from(MAX_BATCH_SIZE),
+        ));
+    }
+
+    if let Some(client) = client {
+        client.acquire_batch(queries.len())?;
+    }
+
+    let concurrency = state.browser_pool_status().max_size.max(1);
+    // The body is streamed after the handler returned, out of reach of the timeout layer.
+    let deadline = state
+        .request_timeout()
+        .map(|request_timeout| tokio::time::Instant::now() + request_timeout);
+    // The captures are polled with the body, after the handler returned, so they are bound to the request span.
+    let span = Span::current();
+
+    let lines = stream::iter(queries.into_iter().enumerate())
+        .map(move |(index, query)| {
+            let state = state.clone();
+
+            async move {
+                let capture = capture_png_base64(&state, &query);
+                let result = match deadline {
+                    Some(deadline) => tokio::time::timeout_at(deadline, capture)
+                        .await
+                        .unwrap_or(Err(api::Error::RequestTimeout)),
+                    None => capture.await,
+                }
+                .map(|base64| format!("data:image/png;base64,{base64}"));
+
+                BatchResult::new(index, query.url, result).to_line()
+            }
+            .instrument(span.clone())
+        })
+        .buffer_unordered(concurrency);
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
+
+/// Validates the query and captures the screenshot as a Base64-encoded PNG, once more with another browser
+/// if a transient Marionette failure occurred.
+///
+/// The request, its failure and the capture duration are counted in the [`Metrics`](crate::metrics::Metrics).
+async fn capture_png_base64(state: &state::State, query: &ScreenshotQuery) -> api::Result<String> {
+    let metrics = state.metrics();
+    metrics.screenshot();
+
+    let start = Instant::now();
+    let result = async {
+        let limits = state.screenshot_limits();
+        query.validate(limits)?;
+
+        if matches!(query.mode, Some(ScreenshotMode::AllMatching)) {
+            return Err(api::Error::Unsupported("mode=all-matching".into()));
+        }
+
+        retry_transient(move || async move {
+            let mut browser = RequestBrowser::acquire(state, query).await?;
+            let result = screenshot_base64(&mut browser, query, limits, state.host_policy()).await;
+
+            if matches!(&result, Err(error) if error.is_transient()) {
+                browser.discard();
+            }
+
+            result
+        })
+        .await
+    }
+    .await;
+
+    match result {
+        Ok(base64) => {
+            metrics.capture_duration(start.elapsed());
+            info!(url = query.url, "Screenshot captured");
+
+            Ok(base64)
+        },
+        Err(error) => {
+            error!(url = query.url, "{:?}", error);
+            metrics.screenshot_error();
+
+            Err(error)
+        },
+    }
+}
+
+/// The screenshot options of a [`screenshot_diff`] request, applied to both captures.
+#[derive(Debug, Default, Deserialize)]
+pub struct DiffOptions {
+    /// Delay in milliseconds after `DOMContentLoaded` before taking the screenshots (default: 0).
+    delay: Option<u16>,
+    /// Page load timeout in milliseconds (default: 300000, as Firefox).
+    nav_timeout: Option<u16>,
+    /// Screenshots width (default: 800).
+    width: Option<u16>,
+    /// Screenshots height (default: 600).
+    height: Option<u16>,
+    /// Whether to show the scrollbar on `html` and `body` elements (default: false).
+    scrollbar: Option<bool>,
+    /// Screenshot mode: one of 'full', 'viewport', 'selector' or 'xpath' (default: 'viewport').
+    mode: Option<ScreenshotMode>,
+    /// CSS selector (required if `mode` is 'selector').
+    selector: Option<String>,
+    /// `XPath` expression (required if `mode` is 'xpath').
+    xpath: Option<String>,
+    /// Maximum difference of the channels of two matching pixels, from 0 to 255 (default: 0).
+    threshold: Option<u8>,
+}
+
+/// The body of a [`screenshot_diff`] request.
+#[derive(Debug, Deserialize)]
+pub struct DiffRequest {
+    /// URL of the page of reference.
+    baseline_url: String,
+    /// URL of the page compared to the baseline.
+    candidate_url: String,
+    #[serde(default)]
+    options: DiffOptions,
+}
+
+impl DiffRequest {
+    /// Returns the screenshot query of the given URL, with the options of the request.
+    fn query(&self, url: &str) -> ScreenshotQuery {
+        let options = &self.options;
+
+        ScreenshotQuery {
+            url: url.into(),
+            delay: options.delay,
+            nav_timeout: options.nav_timeout,
+            width: options.width,
+            height: options.height,
+            scrollbar: options.scrollbar,
+            mode: options.mode,
+            selector: options.selector.clone(),
+            xpath: options.xpath.clone(),
+            ..ScreenshotQuery::default()
+        }
+    }
+}
+
+/// The response of a [`screenshot_diff`] request.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScreenshotDiff {
+    /// Ratio of mismatching pixels, from 0 (identical) to 1.
+    mismatch: f64,
+    /// Whether the screenshots have different dimensions.
+    size_mismatch: bool,
+    /// The diff image, as a `data:image/png;base64,...` URL.
+    diff_data_url: String,
+}
+
+/// Handles a screenshot diff request: captures the baseline and the candidate URLs, and compares them pixel by pixel.
+///
+/// Both screenshots are captured concurrently through the browser pool, like [`screenshot_batch`] would, with the
+/// same options. The response is a JSON `{ "data": { "mismatch": 0.03, "sizeMismatch": false,
+/// "diffDataUrl": "data:image/png;base64,..." } }`, where the diff image shows the baseline faded to light gray
+/// and the mismatching pixels in red.
+/// Screenshots of different sizes are compared on a canvas as large as both, where the pixels covered by only
+/// one of them mismatch, and flagged with `sizeMismatch`.
+/// Screenshots of more pixels than [`max_diff_pixels`] are rejected, and the `all-matching` mode is not supported.
+#[instrument(
+    name = "screenshot_diff",
+    skip_all,
+    fields(baseline_url = %request.baseline_url, candidate_url = %request.candidate_url)
+)]
+pub async fn screenshot_diff(
+    State(state): State<state::State>,
+    JsonBody(request): JsonBody<DiffRequest>,
+) -> api::Result {
+    info!(?request, "Screenshot diff");
+
+    let baseline = request.query(&request.baseline_url);
+    let candidate = request.query(&request.candidate_url);
+    let (baseline, candidate) = tokio::try_join!(
+        capture_png_base64(&state, &baseline),
+        capture_png_base64(&state, &candidate)
+    )?;
+
+    let threshold = request.options.threshold.unwrap_or(0);
+    let max_pixels = max_diff_pixels(state.screenshot_limits());
+    // Decoding, comparing and encoding the images is CPU-bound, and kept off the async runtime.
+    let diff = tokio::task::spawn_blocking(move || {
+        let diff = image_diff::diff(
+            &decode_png(&baseline, max_pixels)?,
+            &decode_png(&candidate, max_pixels)?,
+            threshold,
+        );
+        let mut png = Cursor::new(Vec::new());
+        diff.image.write_to(&mut png, image::ImageFormat::Png)?;
+
+        Ok::<_, api::Error>(ScreenshotDiff {
+            mismatch: diff.mismatch,
+            size_mismatch: diff.size_mismatch,
+            diff_data_url: format!(
+                "data:image/png;base64,{}",
+                BASE64_STANDARD.encode(png.into_inner())
+            ),
+        })
+    })
+    .await??;
+    info!(
+        mismatch = diff.mismatch,
+        size_mismatch = diff.size_mismatch,
+        "Screenshot diff computed"
+    );
+
+    Ok(Json(Success::new(diff)).into_response())
+}
+
+/// Maximum height in pixels of a compared screenshot when the maximum page height of the limits is unlimited.
+const MAX_DIFF_HEIGHT: u32 = 16_384;
+
+/// Returns the maximum number of pixels of a compared screenshot: the maximum width times the maximum page height
+/// of the limits (or [`MAX_DIFF_HEIGHT`] if unlimited).
+fn max_diff_pixels(limits: ScreenshotLimits) -> u32 {
+    let max_height = if limits.max_page_height > 0 {
+        limits.max_page_height
+    } else {
+        MAX_DIFF_HEIGHT
+    };
+
+    u32::from(limits.max_width).saturating_mul(max_height)
+}
+
+/// Decodes a Base64-encoded PNG into an RGBA image, rejecting the images of more than `max_pixels` pixels
+/// before allocating them.
+fn decode_png(base64: &str, max_pixels: u32) -> api::Result<image::RgbaImage> {
+    let png = BASE64_STANDARD.decode(base64)?;
+    let (width, height) =
+        image::ImageReader::with_format(Cursor::new(&png), image::ImageFormat::Png)
+            .into_dimensions()?;
+    if u64::from(width) * u64::from(height) > u64::from(max_pixels) {
+        return Err(api::Error::ExceedsMaximum(
+            "screenshot size in pixels".into(),
+            max_pixels,
+        ));
+    }
+
+    let image = image::load_from_memory_with_format(&png, image::ImageFormat::Png)?;
+
+    Ok(image.to_rgba8())
+}
+
+/// Prepares the page described by the query with the given browser and captures a single Base64-encoded PNG.
+async fn screenshot_base64(
+    browser: &mut Browser,
+    query: &ScreenshotQuery,
+    limits: ScreenshotLimits,
+    host_policy: &HostPolicy,
+) -> api::Result<String> {
+    let CaptureTarget::One(shot) =
+        capture(browser, query, limits, host_policy, &Progress::default()).await?
+    else {
+        return Err(api::Error::Unsupported("mode=all-matching".into()));
+    };
+
+    shot.base64(browser).await
+}
+
+/// Handles screenshot requests like [`screenshot`], but streams the capture progress as server-sent events.
+///
+/// The events are emitted in order: `navigating`, `waiting`, `capturing`, `encoding` and finally `done`
+/// with the Base64-encoded PNG as data. If the capture fails, an `error` event holding the cause ends the stream.
+/// The `response_type` query parameter is ignored, the `init_script` and the `all-matching` mode are not supported.
+/// Invalid queries are rejected before the stream starts.
+pub async fn screenshot_stream(
+    State(state): State<state::State>,
+    Query(query): Query<ScreenshotQuery>,
+) -> api::Result<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    info!(?query, "Screenshot stream");
+
+    reject_query_init_script(&query)?;
+    query.validate(state.screenshot_limits())?;
+
+    if matches!(query.mode, Some(ScreenshotMode::AllMatching)) {
+        return Err(api::Error::Unsupported("mode=all-matching".into()));
+    }
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let progress = Progress::new(sender);
+
+    // The capture keeps the request span, so its events still carry the request id.
+    tokio::spawn(
+        async move {
+            if let Err(error) = capture_base64(&state, query, &progress).await {
+                error!("{:?}", error);
+                progress.emit(ScreenshotEvent::Error(error.to_string()));
+            }
+        }
+        .in_current_span(),
+    );
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+
+        Some((Ok(Event::from(event)), receiver))
+    });
+
+    Ok(Sse::new(events))
+}
+
+/// Runs the whole capture pipeline and emits the resulting Base64-encoded PNG as a [`ScreenshotEvent::Done`].
+async fn capture_base64(
+    state: &state::State,
+    query: ScreenshotQuery,
+    progress: &Progress,
+) -> api::Result<()> {
+    let mut browser = RequestBrowser::acquire(state, &query).await?;
+    let limits = state.screenshot_limits();
+    let host_policy = state.host_policy();
+    let CaptureTarget::One(shot) =
+        capture(&mut browser, &query, limits, host_policy, progress).await?
+    else {
+        return Err(api::Error::Unsupported("mode=all-matching".into()));
+    };
+    let base64 = shot.base64(&mut browser).await?;
+    drop(browser);
+
+    progress.emit(ScreenshotEvent::Encoding);
+    progress.emit(ScreenshotEvent::Done(base64));
+
+    Ok(())
+}
+
+/// What [`capture`] resolved to capture.
+#[derive(Debug)]
+enum CaptureTarget {
+    /// A single screenshot: the full page (possibly clamped), the viewport or an element.
+    One(Shot),
+    /// One screenshot per element matching the selector, in document order.
+    Each(Vec<ScreenshotParameters>),
+}
+
+/// A single screenshot to take.
+#[derive(Debug)]
+enum Shot {
+    /// A screenshot taken as is by the browser.
+    Parameters(ScreenshotParameters),
+    /// A full page screenshot cropped to the given height, see [`Browser::screenshot_full_clamped`].
+    FullClamped(u32),
+}
+
+impl Shot {
+    /// Takes the screenshot and returns the PNG bytes.
+    async fn bytes(self, browser: &mut Browser) -> api::Result<Vec<u8>> {
+        let bytes = match self {
+            Self::Parameters(parameters) => browser.screenshot_bytes(parameters).await?,
+            Self::FullClamped(max_height) => browser.screenshot_full_clamped(max_height).await?,
+        };
+
+        Ok(bytes)
+    }
+
+    /// Takes the screenshot and returns the Base64-encoded PNG.
+    async fn base64(self, browser: &mut Browser) -> api::Result<String> {
+        match self {
+            Self::Parameters(parameters) => Ok(browser.screenshot_base64(parameters).await?),
+            Self::FullClamped(_) => Ok(BASE64_STANDARD.encode(self.bytes(browser).await?)),
+        }
+    }
+}
+
+/// Prepares the page described by the query and returns the parameters to capture the screenshot(s).
+///
+/// This navigates to the URL within the page load timeout, optionally deletes a cookie and hides scrollbars,
+/// sets the window size, waits for the delay, scrolls to the offset (viewport mode) and resolves the element(s)
+/// to capture, reporting each step to the given [`Progress`]. Pages landing (e.g. after a redirect) on a host rejected
+/// by the host policy, checked after the navigation and again right before the capture, and full page captures taller
+/// than the maximum page height of the limits are rejected.
+async fn capture(
+    browser: &mut Browser,
+    query: &ScreenshotQuery,
+    limits: ScreenshotLimits,
+    host_policy: &HostPolicy,
+    progress: &Progress,
+) -> api::Result<CaptureTarget> {
+    progress.emit(ScreenshotEvent::Navigating);
+    // Always set, pooled browsers would otherwise keep the timeout of a previous request.
+    let page_load = query.nav_timeout.map_or(DEFAULT_NAV_TIMEOUT, u64::from);
+    browser
+        .set_timeouts(Timeouts {
+            page_load: Some(page_load),
+            ..Timeouts::default()
+        })
+        .await?;
+
+    apply_overrides(browser, query).await?;
+
+    let start = Instant::now();
+    browser.navigate(query.url.as_str()).await?;
+    record_elapsed_ms("navigate_ms", start);
+
+    if host_policy.is_enabled() {
+        host_policy.check(&browser.current_url().await?)?;
+    }
+
+    if let Some(name) = &query.delete_cookie {
+        browser.delete_cookie(name).await?;
+    }
+
+    progress.emit(ScreenshotEvent::Waiting);
     let scrollbar = query.scrollbar.unwrap_or(false);
     if !scrollbar {
         browser.hide_body_scrollbar().await?;
@@ -112,104 +1424,237 @@ pub async fn screenshot(
 
     let width = query.width.unwrap_or(800);
     let height = query.height.unwrap_or(600);
+    let start = Instant::now();
     browser.set_window_size(width, height).await?;
+    record_elapsed_ms("resize_ms", start);
 
     let delay = query.delay.unwrap_or(0);
     if delay > 0 {
         tokio::time::sleep(Duration::from_millis(u64::from(delay))).await;
     }
 
+    progress.emit(ScreenshotEvent::Capturing);
+    let target = resolve_target(browser, query, limits).await?;
+
+    // Checked again right before the capture, as the page may have navigated away (e.g. with a script or
+    // a meta refresh) during the delay.
+    if host_policy.is_enabled() {
+        host_policy.check(&browser.current_url().await?)?;
+    }
+
+    Ok(target)
+}
+
+/// Resolves what to capture in the mode of the query: the full page (rejected if taller than the maximum page
+/// height of the limits), the viewport (scrolled to the offset), or the element(s) matching the selector.
+async fn resolve_target(
+    browser: &mut Browser,
+    query: &ScreenshotQuery,
+    limits: ScreenshotLimits,
+) -> api::Result<CaptureTarget> {
     let mode = query.mode.unwrap_or(ScreenshotMode::Viewport);
-    let parameters = match mode {
-        ScreenshotMode::Full => ScreenshotParameters::full(),
-        ScreenshotMode::Viewport => ScreenshotParameters::viewport(),
+    let target = match mode {
+        ScreenshotMode::Full => {
+            if limits.max_page_height > 0 {
+                let height = browser.full_page_height().await?;
+                if height > limits.max_page_height {
+                    debug!(height, "Page too tall for a full page screenshot");
+                    return Err(api::Error::ExceedsMaximum(
+                        "page height".into(),
+                        limits.max_page_height,
+                    ));
+                }
+            }
+
+            CaptureTarget::One(Shot::Parameters(ScreenshotParameters::full()))
+        },
+        ScreenshotMode::FullClamped => {
+            let max_height = query
+                .max_full_height
+                .ok_or_else(|| api::Error::MissingField("max_full_height".into()))?;
+
+            CaptureTarget::One(Shot::FullClamped(max_height))
+        },
+        ScreenshotMode::Viewport => {
+            if let Some((x, y)) = query.scroll_offset() {
+                browser.scroll_to(x, y).await?;
+            }
+
+            CaptureTarget::One(Shot::Parameters(ScreenshotParameters::viewport()))
+        },
+        ScreenshotMode::AllMatching => {
+            let selector = query
+                .selector
+                .as_deref()
+                .ok_or_else(|| api::Error::MissingField("selector".into()))?;
+            let elements = browser
+                .find_elements(ScreenshotFindElementUsing::CssSelector, selector)
+                .await?;
+
+            CaptureTarget::Each(
+                elements
+                    .into_iter()
+                    .take(usize::from(query.max_elements()))
+                    .map(|element| ScreenshotParameters::element(element.id))
+                    .collect(),
+            )
+        },
         ScreenshotMode::Selector => {
             let selector = query
                 .selector
+                .as_deref()
                 .ok_or_else(|| api::Error::MissingField("selector".into()))?;
             let element = browser
                 .find_element(ScreenshotFindElementUsing::CssSelector, selector)
                 .await?;
 
-            ScreenshotParameters::element(element.id)
+            CaptureTarget::One(Shot::Parameters(ScreenshotParameters::element(element.id)))
         },
         ScreenshotMode::XPath => {
             let xpath = query
                 .xpath
+                .as_deref()
                 .ok_or_else(|| api::Error::MissingField("xpath".into()))?;
             let element = browser
                 .find_element(ScreenshotFindElementUsing::XPath, xpath)
                 .await?;
 
-            ScreenshotParameters::element(element.id)
+            CaptureTarget::One(Shot::Parameters(ScreenshotParameters::element(element.id)))
         },
     };
 
-    let response_type = query
-        .response_type
-        .unwrap_or(ScreenshotResponseType::ImagePngBytes);
+    Ok(target)
+}
 
-    let response = match response_type {
-        ScreenshotResponseType::ImagePngBytes => {
-            screenshot_image_bytes(&mut browser, parameters).await?
-        },
+/// Applies the overrides of the query that must be set before the navigation: the geolocation, the color scheme,
+/// the referer and the init script.
+///
+/// They are all set at runtime, and removed when a pooled browser is recycled (see [`Browser::reset_state`]).
+async fn apply_overrides(browser: &mut Browser, query: &ScreenshotQuery) -> api::Result<()> {
+    if let (Some(lat), Some(lon)) = (query.geo_lat, query.geo_lon) {
+        browser
+            .set_geolocation(lat, lon, GEOLOCATION_ACCURACY)
+            .await?;
+    }
+
+    if let Some(color_scheme) = query.color_scheme {
+        browser.set_color_scheme(color_scheme).await?;
+    }
+
+    if let Some(referer) = &query.referer {
+        browser.set_referer(referer).await?;
+    }
+
+    if let Some(script) = &query.init_script {
+        browser.add_init_script(script.clone()).await?;
+    }
+
+    Ok(())
+}
+
+/// Size of the chunks the image bytes responses are streamed in.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Builds the HTTP response of a captured (or cached) screenshot.
+///
+/// The image bytes responses (`image-png-bytes`, `image-jpeg`, `image-webp` and `attachment`) are streamed
+/// in chunks, with a `Content-Length` header set from the size of the image.
+fn screenshot_response(
+    response_type: ScreenshotResponseType,
+    format: ImageFormat,
+    screenshot: CachedScreenshot,
+) -> Response {
+    let content_type = [(header::CONTENT_TYPE, screenshot.content_type)];
+    let content_length = [(header::CONTENT_LENGTH, screenshot.bytes.len())];
+
+    match response_type {
         ScreenshotResponseType::Attachment => {
-            screenshot_attachment(&mut browser, parameters).await?
-        },
-        ScreenshotResponseType::ImagePngBase64 => {
-            screenshot_image_base64(&mut browser, parameters).await?
-        },
-        ScreenshotResponseType::JsonPngBase64 => {
-            screenshot_json_base64(&mut browser, parameters).await?
-        },
-        ScreenshotResponseType::JsonPngBytes => {
-            screenshot_json_bytes(&mut browser, parameters).await?
+            let content_disposition = [(
+                header::CONTENT_DISPOSITION,
+                // TODO: make `filename` configurable ?!
+                format!("attachment; filename=\"screenshot.{}\"", format.extension()),
+            )];
+
+            (
+                StatusCode::OK,
+                content_type,
+                content_disposition,
+                content_length,
+                stream_body(screenshot.bytes),
+            )
+                .into_response()
         },
-    };
+        ScreenshotResponseType::ImagePngBytes
+        | ScreenshotResponseType::ImageJpeg
+        | ScreenshotResponseType::ImageWebp => (
+            StatusCode::OK,
+            content_type,
+            content_length,
+            stream_body(screenshot.bytes),
+        )
+            .into_response(),
+        _ => (StatusCode::OK, content_type, screenshot.bytes).into_response(),
+    }
+}
+
+/// Returns a body streaming the bytes in chunks of [`STREAM_CHUNK_SIZE`], without copying them.
+fn stream_body(bytes: Bytes) -> Body {
+    let chunks = (0..bytes.len())
+        .step_by(STREAM_CHUNK_SIZE)
+        .map(move |start| {
+            let end = (start + STREAM_CHUNK_SIZE).min(bytes.len());
 
-    Ok(response)
+            Ok::<_, Infallible>(bytes.slice(start..end))
+        });
+
+    Body::from_stream(stream::iter(chunks))
 }
 
 async fn screenshot_image_bytes(
     browser: &mut Browser,
-    parameters: ScreenshotParameters,
-) -> api::Result {
-    let bytes = browser.screenshot_bytes(parameters).await?;
-    let headers = [(header::CONTENT_TYPE, "image/png")];
+    shot: Shot,
+    format: ImageFormat,
+) -> api::Result<CachedScreenshot> {
+    let mut bytes = shot.bytes(browser).await?;
+    if format != ImageFormat::Png {
+        // Decoding and encoding the image is CPU-bound, and kept off the async runtime.
+        bytes = tokio::task::spawn_blocking(move || transcode(bytes, format)).await??;
+    }
 
-    Ok((StatusCode::OK, headers, bytes).into_response())
+    Ok(CachedScreenshot::new(format.content_type(), bytes))
 }
 
-async fn screenshot_attachment(
-    browser: &mut Browser,
-    parameters: ScreenshotParameters,
-) -> api::Result {
-    let bytes = browser.screenshot_bytes(parameters).await?;
-    let headers = [
-        (header::CONTENT_TYPE, "image/png"),
-        (
-            header::CONTENT_DISPOSITION,
-            // TODO: make `filename` configurable ?!
-            "attachment; filename=\"screenshot.png\"",
-        ),
-    ];
+/// Transcodes the PNG bytes of a screenshot to the given format, PNG bytes are returned as is.
+///
+/// The transparency is dropped for JPEG, which does not support it.
+fn transcode(png: Vec<u8>, format: ImageFormat) -> api::Result<Vec<u8>> {
+    let output_format = match format {
+        ImageFormat::Png => return Ok(png),
+        ImageFormat::Jpeg => image::ImageFormat::Jpeg,
+        ImageFormat::Webp => image::ImageFormat::WebP,
+    };
+
+    let mut image = image::load_from_memory_with_format(&png, image::ImageFormat::Png)?;
+    if format == ImageFormat::Jpeg {
+        image = image.to_rgb8().into();
+    }
+
+    let mut output = Cursor::new(Vec::new());
+    image.write_to(&mut output, output_format)?;
 
-    Ok((StatusCode::OK, headers, bytes).into_response())
+    Ok(output.into_inner())
 }
 
 async fn screenshot_image_base64(
     browser: &mut Browser,
-    parameters: ScreenshotParameters,
-) -> api::Result {
-    let base64 = browser.screenshot_base64(parameters).await?;
-    let headers = [(header::CONTENT_TYPE, "text/plain")];
+    shot: Shot,
+) -> api::Result<CachedScreenshot> {
+    let base64 = shot.base64(browser).await?;
 
-    Ok((
-        StatusCode::OK,
-        headers,
+    Ok(CachedScreenshot::new(
+        "text/plain",
         format!("data:image/png;base64,{base64}"),
-    )
-        .into_response())
+    ))
 }
 
 #[derive(Debug, Serialize)]
@@ -219,11 +1664,11 @@ struct JsonPngBase64 {
 
 async fn screenshot_json_base64(
     browser: &mut Browser,
-    parameters: ScreenshotParameters,
-) -> api::Result {
-    let base64 = browser.screenshot_base64(parameters).await?;
+    shot: Shot,
+) -> api::Result<CachedScreenshot> {
+    let base64 = shot.base64(browser).await?;
 
-    Ok((StatusCode::OK, Json(JsonPngBase64 { base64 })).into_response())
+    json_screenshot(&JsonPngBase64 { base64 })
 }
 
 #[derive(Debug, Serialize)]
@@ -231,11 +1676,923 @@ struct JsonPngBytes {
     bytes: Vec<u8>,
 }
 
-async fn screenshot_json_bytes(
+async fn screenshot_json_bytes(browser: &mut Browser, shot: Shot) -> api::Result<CachedScreenshot> {
+    let bytes = shot.bytes(browser).await?;
+
+    json_screenshot(&JsonPngBytes { bytes })
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonDataUrl {
+    data_url: String,
+}
+
+async fn screenshot_json_data_url(
     browser: &mut Browser,
-    parameters: ScreenshotParameters,
-) -> api::Result {
-    let bytes = browser.screenshot_bytes(parameters).await?;
+    shot: Shot,
+) -> api::Result<CachedScreenshot> {
+    let base64 = shot.base64(browser).await?;
+
+    json_screenshot(&Success::new(JsonDataUrl {
+        data_url: format!("data:image/png;base64,{base64}"),
+    }))
+}
 
-    Ok((StatusCode::OK, Json(JsonPngBytes { bytes })).into_response())
+#[derive(Debug, Serialize)]
+struct JsonImages {
+    images: Vec<String>,
+}
+
+async fn screenshot_json_images(
+    browser: &mut Browser,
+    parameters: Vec<ScreenshotParameters>,
+) -> api::Result<CachedScreenshot> {
+    let mut images = Vec::with_capacity(parameters.len());
+
+    for parameters in parameters {
+        images.push(browser.screenshot_base64(parameters).await?);
+    }
+
+    json_screenshot(&Success::new(JsonImages { images }))
+}
+
+async fn page_source_html(browser: &mut Browser) -> api::Result<CachedScreenshot> {
+    let html = browser.page_source().await?;
+
+    Ok(CachedScreenshot::new("text/html; charset=utf-8", html))
+}
+
+/// Boundary of the parts of the `multipart` responses, which cannot occur in the JSON metadata part.
+const MULTIPART_BOUNDARY: &str = "pantin-screenshot-boundary";
+
+/// Content type of the `multipart` responses, with the [`MULTIPART_BOUNDARY`].
+const MULTIPART_CONTENT_TYPE: &str = "multipart/mixed; boundary=pantin-screenshot-boundary";
+
+/// The metadata part of the `multipart` responses.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultipartMetadata {
+    /// The URL of the page once the navigation (and its redirects) completed.
+    url: String,
+    /// Width of the PNG image in pixels.
+    width: u32,
+    /// Height of the PNG image in pixels.
+    height: u32,
+    /// Milliseconds elapsed from the navigation to the end of the capture.
+    duration_ms: u64,
+}
+
+/// Captures a single PNG screenshot and returns it in a `multipart/mixed` body, after a JSON part holding
+/// the [`MultipartMetadata`] of the capture.
+async fn screenshot_multipart(
+    browser: &mut Browser,
+    shot: Shot,
+    started: Instant,
+) -> api::Result<CachedScreenshot> {
+    let png = shot.bytes(browser).await?;
+    let duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let (width, height) =
+        image::ImageReader::with_format(Cursor::new(&png), image::ImageFormat::Png)
+            .into_dimensions()?;
+
+    let metadata = MultipartMetadata {
+        url: browser.current_url().await?,
+        width,
+        height,
+        duration_ms,
+    };
+
+    Ok(CachedScreenshot::new(
+        MULTIPART_CONTENT_TYPE,
+        multipart_body(&serde_json::to_vec(&metadata)?, &png),
+    ))
+}
+
+/// Returns a `multipart/mixed` body made of the JSON metadata part and the PNG image part.
+fn multipart_body(metadata: &[u8], png: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(metadata.len() + png.len() + 256);
+
+    for (content_type, part) in [("application/json", metadata), ("image/png", png)] {
+        body.extend_from_slice(
+            format!(
+                "--{MULTIPART_BOUNDARY}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\r\n",
+                part.len()
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(part);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{MULTIPART_BOUNDARY}--\r\n").as_bytes());
+
+    body
+}
+
+fn json_screenshot<T: Serialize>(value: &T) -> api::Result<CachedScreenshot> {
+    let bytes = serde_json::to_vec(value)?;
+
+    Ok(CachedScreenshot::new("application/json", bytes))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use axum::body;
+    use deadpool::managed::Pool;
+
+    use super::*;
+    use crate::{browser_pool::BrowserPool, cache::ScreenshotCache};
+
+    fn query(url: &str) -> ScreenshotQuery {
+        ScreenshotQuery {
+            url: url.into(),
+            delay: None,
+            nav_timeout: None,
+            width: None,
+            height: None,
+            scrollbar: None,
+            response_type: None,
+            mode: None,
+            selector: None,
+            max_elements: None,
+            xpath: None,
+            max_full_height: None,
+            scroll_x: None,
+            scroll_y: None,
+            geo_lat: None,
+            geo_lon: None,
+            tz: None,
+            locale: None,
+            color_scheme: None,
+            referer: None,
+            delete_cookie: None,
+            init_script: None,
+            format: None,
+        }
+    }
+
+    #[test]
+    fn test_screenshot_query_cache_key() {
+        let defaults = query("https://example.com");
+        let explicit = ScreenshotQuery {
+            delay: Some(0),
+            width: Some(800),
+            height: Some(600),
+            scrollbar: Some(false),
+            response_type: Some(ScreenshotResponseType::ImagePngBytes),
+            mode: Some(ScreenshotMode::Viewport),
+            selector: Some("body".into()),
+            ..query("https://example.com")
+        };
+
+        assert_eq!(defaults.cache_key(), explicit.cache_key());
+        assert_ne!(
+            defaults.cache_key(),
+            query("https://example.org").cache_key()
+        );
+        assert_ne!(
+            defaults.cache_key(),
+            ScreenshotQuery {
+                response_type: Some(ScreenshotResponseType::JsonPngBase64),
+                ..query("https://example.com")
+            }
+            .cache_key()
+        );
+        assert_ne!(
+            ScreenshotQuery {
+                mode: Some(ScreenshotMode::Selector),
+                selector: Some("body".into()),
+                ..query("https://example.com")
+            }
+            .cache_key(),
+            ScreenshotQuery {
+                mode: Some(ScreenshotMode::Selector),
+                selector: Some("main".into()),
+                ..query("https://example.com")
+            }
+            .cache_key()
+        );
+        assert_ne!(
+            ScreenshotQuery {
+                mode: Some(ScreenshotMode::AllMatching),
+                selector: Some(".sprite".into()),
+                ..query("https://example.com")
+            }
+            .cache_key(),
+            ScreenshotQuery {
+                mode: Some(ScreenshotMode::AllMatching),
+                selector: Some(".sprite".into()),
+                max_elements: Some(3),
+                ..query("https://example.com")
+            }
+            .cache_key()
+        );
+        assert_ne!(
+            defaults.cache_key(),
+            ScreenshotQuery {
+                tz: Some("Asia/Tokyo".into()),
+                ..query("https://example.com")
+            }
+            .cache_key()
+        );
+        assert_ne!(
+            defaults.cache_key(),
+            ScreenshotQuery {
+                locale: Some("fr-CH".into()),
+                ..query("https://example.com")
+            }
+            .cache_key()
+        );
+        assert_ne!(
+            defaults.cache_key(),
+            ScreenshotQuery {
+                init_script: Some("window.polyfilled = true;".into()),
+                ..query("https://example.com")
+            }
+            .cache_key()
+        );
+
+        assert_eq!(
+            defaults.cache_key(),
+            ScreenshotQuery {
+                scroll_x: Some(0),
+                ..query("https://example.com")
+            }
+            .cache_key()
+        );
+        assert_ne!(
+            defaults.cache_key(),
+            ScreenshotQuery {
+                scroll_y: Some(1200),
+                ..query("https://example.com")
+            }
+            .cache_key()
+        );
+    }
+
+    #[test]
+    fn test_screenshot_query_cache_key_color_scheme() {
+        let keys = [None, Some(ColorScheme::Dark), Some(ColorScheme::Light)].map(|color_scheme| {
+            ScreenshotQuery {
+                color_scheme,
+                ..query("https://example.com")
+            }
+            .cache_key()
+        });
+
+        assert_ne!(keys[0], keys[1]);
+        assert_ne!(keys[0], keys[2]);
+        assert_ne!(keys[1], keys[2]);
+    }
+
+    #[test]
+    fn test_screenshot_query_cache_key_referer() {
+        let without = query("https://example.com");
+        let with = ScreenshotQuery {
+            referer: Some("https://referer.example".into()),
+            ..query("https://example.com")
+        };
+
+        assert_ne!(without.cache_key(), with.cache_key());
+    }
+
+    #[test]
+    fn test_screenshot_query_validate_delay() {
+        let limits = ScreenshotLimits {
+            max_delay: 5000,
+            ..ScreenshotLimits::default()
+        };
+
+        assert!(
+            ScreenshotQuery {
+                delay: Some(5000),
+                ..query("https://example.com")
+            }
+            .validate(limits)
+            .is_ok()
+        );
+        assert!(matches!(
+            ScreenshotQuery {
+                delay: Some(5001),
+                ..query("https://example.com")
+            }
+            .validate(limits),
+            Err(api::Error::ExceedsMaximum(field, 5000)) if field == "delay"
+        ));
+    }
+
+    #[test]
+    fn test_screenshot_query_validate_tz_and_locale() {
+        let limits = ScreenshotLimits::default();
+        let localized = |tz: Option<&str>, locale: Option<&str>| ScreenshotQuery {
+            tz: tz.map(Into::into),
+            locale: locale.map(Into::into),
+            ..query("https://example.com")
+        };
+
+        for tz in [
+            "Europe/Paris",
+            "Asia/Tokyo",
+            "America/Argentina/Buenos_Aires",
+            "UTC",
+        ] {
+            assert!(localized(Some(tz), None).validate(limits).is_ok(), "{tz}");
+        }
+        for locale in ["fr-CH", "en", "zh-Hant-TW", "de-DE-1996"] {
+            assert!(
+                localized(None, Some(locale)).validate(limits).is_ok(),
+                "{locale}"
+            );
+        }
+
+        for tz in ["europe/paris", "Mars/Olympus", ":/etc/passwd", "../UTC", ""] {
+            assert!(
+                matches!(
+                    localized(Some(tz), None).validate(limits),
+                    Err(api::Error::Unsupported(parameter)) if parameter.starts_with("tz ")
+                ),
+                "{tz}"
+            );
+        }
+        for locale in ["fr_CH", "xx-ZZZZ", "fr-CH\"", "en--US", ""] {
+            assert!(
+                matches!(
+                    localized(None, Some(locale)).validate(limits),
+                    Err(api::Error::Unsupported(parameter)) if parameter.starts_with("locale ")
+                ),
+                "{locale}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_screenshot_query_validate_max_full_height() {
+        let limits = ScreenshotLimits {
+            max_page_height: 20000,
+            ..ScreenshotLimits::default()
+        };
+        let clamped = |max_full_height| ScreenshotQuery {
+            mode: Some(ScreenshotMode::FullClamped),
+            max_full_height: Some(max_full_height),
+            ..query("https://example.com")
+        };
+
+        assert!(clamped(20000).validate(limits).is_ok());
+        assert!(clamped(30000).validate(ScreenshotLimits::default()).is_ok());
+        assert!(matches!(
+            clamped(20001).validate(limits),
+            Err(api::Error::ExceedsMaximum(field, 20000)) if field == "max_full_height"
+        ));
+        assert!(matches!(
+            clamped(0).validate(limits),
+            Err(api::Error::OutOfRange(field)) if field == "max_full_height"
+        ));
+        assert_ne!(clamped(1000).cache_key(), clamped(2000).cache_key());
+        assert!(matches!(
+            ScreenshotQuery {
+                mode: Some(ScreenshotMode::Full),
+                max_full_height: Some(1000),
+                ..query("https://example.com")
+            }
+            .validate(limits),
+            Err(api::Error::Unsupported(parameter)) if parameter == "max_full_height requires mode=full-clamped"
+        ));
+    }
+
+    #[test]
+    fn test_screenshot_query_validate_max_elements() {
+        let limits = ScreenshotLimits {
+            max_elements: 20,
+            ..ScreenshotLimits::default()
+        };
+
+        assert!(matches!(
+            ScreenshotQuery {
+                mode: Some(ScreenshotMode::AllMatching),
+                max_elements: Some(21),
+                ..query("https://example.com")
+            }
+            .validate(limits),
+            Err(api::Error::ExceedsMaximum(field, 20)) if field == "max_elements"
+        ));
+        assert!(
+            ScreenshotQuery {
+                max_elements: Some(21),
+                ..query("https://example.com")
+            }
+            .validate(limits)
+            .is_ok(),
+            "max_elements should only be limited in all-matching mode"
+        );
+    }
+
+    #[test]
+    fn test_screenshot_query_validate() {
+        let limits = ScreenshotLimits {
+            max_width: 1024,
+            max_height: 768,
+            max_elements: 20,
+            max_page_height: 0,
+            max_delay: 10000,
+        };
+
+        assert!(query("https://example.com").validate(limits).is_ok());
+        assert!(
+            ScreenshotQuery {
+                width: Some(1024),
+                height: Some(768),
+                ..query("https://example.com")
+            }
+            .validate(limits)
+            .is_ok()
+        );
+        assert!(matches!(
+            ScreenshotQuery {
+                width: Some(1025),
+                ..query("https://example.com")
+            }
+            .validate(limits),
+            Err(api::Error::ExceedsMaximum(field, 1024)) if field == "width"
+        ));
+        assert!(matches!(
+            ScreenshotQuery {
+                height: Some(769),
+                ..query("https://example.com")
+            }
+            .validate(limits),
+            Err(api::Error::ExceedsMaximum(field, 768)) if field == "height"
+        ));
+        assert!(
+            ScreenshotQuery {
+                geo_lat: Some(46.2044),
+                geo_lon: Some(-6.1432),
+                ..query("https://example.com")
+            }
+            .validate(limits)
+            .is_ok()
+        );
+        assert!(matches!(
+            ScreenshotQuery {
+                geo_lat: Some(46.2044),
+                ..query("https://example.com")
+            }
+            .validate(limits),
+            Err(api::Error::MissingField(field)) if field == "geo_lon"
+        ));
+        assert!(matches!(
+            ScreenshotQuery {
+                geo_lat: Some(91.0),
+                geo_lon: Some(6.1432),
+                ..query("https://example.com")
+            }
+            .validate(limits),
+            Err(api::Error::OutOfRange(field)) if field == "geo_lat"
+        ));
+        assert!(matches!(
+            ScreenshotQuery {
+                geo_lat: Some(46.2044),
+                geo_lon: Some(180.5),
+                ..query("https://example.com")
+            }
+            .validate(limits),
+            Err(api::Error::OutOfRange(field)) if field == "geo_lon"
+        ));
+        assert!(
+            ScreenshotQuery {
+                scroll_y: Some(1200),
+                ..query("https://example.com")
+            }
+            .validate(limits)
+            .is_ok()
+        );
+        assert!(matches!(
+            ScreenshotQuery {
+                mode: Some(ScreenshotMode::Full),
+                scroll_y: Some(1200),
+                ..query("https://example.com")
+            }
+            .validate(limits),
+            Err(api::Error::Unsupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_screenshot_cache_hit() {
+        // The pool can't create any browser, so only a cache hit can answer successfully.
+        let pool: BrowserPool = Pool::builder(BrowserManager::new("invalid-browser-command"))
+            .max_size(1)
+            .build()
+            .expect("Failed to build pool");
+        let cache = ScreenshotCache::new(1, Duration::from_secs(60));
+        let state = state::State::new(
+            pool,
+            Duration::from_secs(1),
+            cache.clone(),
+            ScreenshotLimits::default(),
+        );
+
+        let error = screenshot(
+            State(state.clone()),
+            HeaderMap::new(),
+            Query(query("https://example.com")),
+        )
+        .await
+        .expect_err("Cache miss should use the pool");
+        assert!(matches!(
+            error,
+            api::Error::State(state::Error::PoolError(_))
+        ));
+
+        cache.insert(
+            query("https://example.com").cache_key(),
+            CachedScreenshot::new("image/png", b"png".as_slice()),
+        );
+
+        let response = screenshot(
+            State(state),
+            HeaderMap::new(),
+            Query(query("https://example.com")),
+        )
+        .await
+        .expect("Cache hit should not use the pool");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "image/png"
+        );
+        let body = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        assert_eq!(body.as_ref(), b"png");
+    }
+
+    #[tokio::test]
+    async fn test_screenshot_response_content_length() {
+        let bytes: Vec<u8> = (0..=u8::MAX)
+            .cycle()
+            .take(STREAM_CHUNK_SIZE * 3 + 42)
+            .collect();
+
+        for response_type in [
+            ScreenshotResponseType::ImagePngBytes,
+            ScreenshotResponseType::Attachment,
+        ] {
+            let response = screenshot_response(
+                response_type,
+                ImageFormat::Png,
+                CachedScreenshot::new("image/png", bytes.clone()),
+            );
+
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                response.headers().get(header::CONTENT_LENGTH).unwrap(),
+                bytes.len().to_string().as_str()
+            );
+            let body = body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("Failed to read body");
+            assert_eq!(body.as_ref(), bytes.as_slice());
+        }
+    }
+
+    fn transient_error() -> api::Error {
+        api::Error::Browser(pantin_browser::Error::Marionette(
+            pantin_marionette::Error::Request(pantin_marionette::request::Error::Response(
+                pantin_marionette::response::Error::UnexpectedEndOfResponse,
+            )),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient() {
+        let mut attempts = 0;
+        let result = retry_transient(|| {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                match attempt {
+                    1 => Err(transient_error()),
+                    _ => Ok(attempt),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.expect("Should succeed on retry"), 2);
+        assert_eq!(attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_only_once() {
+        let mut attempts = 0;
+        let result: api::Result<()> = retry_transient(|| {
+            attempts += 1;
+            async { Err(transient_error()) }
+        })
+        .await;
+
+        assert!(result.is_err_and(|error| error.is_transient()));
+        assert_eq!(attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_non_transient() {
+        let mut attempts = 0;
+        let result: api::Result<()> = retry_transient(|| {
+            attempts += 1;
+            async { Err(api::Error::MissingField("selector".into())) }
+        })
+        .await;
+
+        assert!(matches!(result, Err(api::Error::MissingField(_))));
+        assert_eq!(attempts, 1, "Non transient errors should not be retried");
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_screenshot_summary_event() {
+        // The pool can't create any browser, so only a cache hit can answer successfully.
+        let pool: BrowserPool = Pool::builder(BrowserManager::new("invalid-browser-command"))
+            .max_size(1)
+            .build()
+            .expect("Failed to build pool");
+        let cache = ScreenshotCache::new(1, Duration::from_secs(60));
+        let state = state::State::new(
+            pool,
+            Duration::from_secs(1),
+            cache.clone(),
+            ScreenshotLimits::default(),
+        );
+
+        let too_wide = ScreenshotQuery {
+            width: Some(5000),
+            ..query("https://example.com")
+        };
+        screenshot(State(state.clone()), HeaderMap::new(), Query(too_wide))
+            .await
+            .expect_err("Width should exceed the maximum");
+
+        assert!(logs_contain("url=https://example.com"));
+        assert!(logs_contain("Screenshot completed status_code=400"));
+
+        cache.insert(
+            query("https://example.com").cache_key(),
+            CachedScreenshot::new("image/png", b"png".as_slice()),
+        );
+        screenshot(
+            State(state),
+            HeaderMap::new(),
+            Query(query("https://example.com")),
+        )
+        .await
+        .expect("Cache hit should not use the pool");
+
+        assert!(logs_contain(
+            "Screenshot completed status_code=200 bytes_len=3"
+        ));
+    }
+
+    #[test]
+    fn test_negotiate_response_type() {
+        let negotiate = |accept: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, accept.parse().unwrap());
+
+            negotiate_response_type(&headers)
+        };
+
+        assert_eq!(negotiate_response_type(&HeaderMap::new()), None);
+        assert_eq!(negotiate("*/*"), None);
+        assert_eq!(negotiate("text/html"), None);
+        assert_eq!(
+            negotiate("application/json"),
+            Some(ScreenshotResponseType::JsonDataUrl)
+        );
+        assert_eq!(
+            negotiate("image/png"),
+            Some(ScreenshotResponseType::ImagePngBytes)
+        );
+        assert_eq!(
+            negotiate("text/html, image/webp, image/png, */*;q=0.8"),
+            Some(ScreenshotResponseType::ImageWebp)
+        );
+        assert_eq!(
+            negotiate("image/png;q=0.5, application/json;q=0.9"),
+            Some(ScreenshotResponseType::JsonDataUrl)
+        );
+        assert_eq!(negotiate("image/jpeg;q=0"), None);
+    }
+
+    #[test]
+    fn test_batch_result_line() {
+        let line = BatchResult::new(
+            0,
+            "https://example.com".into(),
+            Ok("data:image/png;base64,AA==".into()),
+        )
+        .to_line()
+        .expect("Serialize result");
+        assert_eq!(
+            line,
+            "{\"index\":0,\"url\":\"https://example.com\",\"ok\":true,\"dataUrl\":\"data:image/png;base64,AA==\"}\n"
+        );
+
+        let line = BatchResult::new(
+            1,
+            "https://example.com".into(),
+            Err(api::Error::MissingField("selector".into())),
+        )
+        .to_line()
+        .expect("Serialize result");
+        assert_eq!(
+            line,
+            "{\"index\":1,\"url\":\"https://example.com\",\"ok\":false,\"status\":400,\"cause\":\"missing field: selector\",\"code\":\"missing_field\"}\n"
+        );
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_record_elapsed_ms() {
+        let span = tracing::info_span!("screenshot", navigate_ms = field::Empty);
+        let _guard = span.enter();
+
+        record_elapsed_ms("navigate_ms", Instant::now());
+        info!("Screenshot captured");
+
+        assert!(logs_contain("navigate_ms=0"));
+    }
+
+    #[test]
+    fn test_screenshot_response_attachment() {
+        let response = screenshot_response(
+            ScreenshotResponseType::Attachment,
+            ImageFormat::Png,
+            CachedScreenshot::new("image/png", b"png".as_slice()),
+        );
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+            "attachment; filename=\"screenshot.png\""
+        );
+
+        let response = screenshot_response(
+            ScreenshotResponseType::Attachment,
+            ImageFormat::Jpeg,
+            CachedScreenshot::new("image/jpeg", b"jpeg".as_slice()),
+        );
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+            "attachment; filename=\"screenshot.jpg\""
+        );
+    }
+
+    #[test]
+    fn test_screenshot_query_image_format() {
+        let cases = [
+            (
+                None,
+                None,
+                ScreenshotResponseType::ImagePngBytes,
+                ImageFormat::Png,
+            ),
+            (
+                None,
+                Some(ImageFormat::Webp),
+                ScreenshotResponseType::ImageWebp,
+                ImageFormat::Webp,
+            ),
+            (
+                Some(ScreenshotResponseType::ImageJpeg),
+                None,
+                ScreenshotResponseType::ImageJpeg,
+                ImageFormat::Jpeg,
+            ),
+            (
+                Some(ScreenshotResponseType::Attachment),
+                Some(ImageFormat::Jpeg),
+                ScreenshotResponseType::Attachment,
+                ImageFormat::Jpeg,
+            ),
+            (
+                Some(ScreenshotResponseType::JsonPngBase64),
+                None,
+                ScreenshotResponseType::JsonPngBase64,
+                ImageFormat::Png,
+            ),
+        ];
+
+        for (response_type, format, expected_response_type, expected_format) in cases {
+            let query = ScreenshotQuery {
+                response_type,
+                format,
+                ..query("https://example.com")
+            };
+
+            assert_eq!(query.response_type(), expected_response_type);
+            assert_eq!(query.image_format(), expected_format);
+            assert!(query.validate(ScreenshotLimits::default()).is_ok());
+        }
+
+        for (response_type, format) in [
+            (ScreenshotResponseType::ImagePngBytes, ImageFormat::Jpeg),
+            (ScreenshotResponseType::ImageJpeg, ImageFormat::Webp),
+            (ScreenshotResponseType::JsonDataUrl, ImageFormat::Webp),
+        ] {
+            let query = ScreenshotQuery {
+                response_type: Some(response_type),
+                format: Some(format),
+                ..query("https://example.com")
+            };
+
+            assert!(matches!(
+                query.validate(ScreenshotLimits::default()),
+                Err(api::Error::Unsupported(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_transcode() {
+        let png = {
+            let image = image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 128]));
+            let mut output = Cursor::new(Vec::new());
+            image
+                .write_to(&mut output, image::ImageFormat::Png)
+                .expect("Failed to encode PNG");
+            output.into_inner()
+        };
+
+        assert_eq!(transcode(png.clone(), ImageFormat::Png).unwrap(), png);
+
+        for (format, expected) in [
+            (ImageFormat::Jpeg, image::ImageFormat::Jpeg),
+            (ImageFormat::Webp, image::ImageFormat::WebP),
+        ] {
+            let bytes = transcode(png.clone(), format).expect("Failed to transcode");
+
+            assert_eq!(image::guess_format(&bytes).unwrap(), expected);
+        }
+
+        assert!(matches!(
+            transcode(b"not a png".to_vec(), ImageFormat::Jpeg),
+            Err(api::Error::Transcode(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_png() {
+        let base64 = {
+            let image = image::RgbaImage::from_pixel(4, 3, image::Rgba([255, 0, 0, 255]));
+            let mut output = Cursor::new(Vec::new());
+            image
+                .write_to(&mut output, image::ImageFormat::Png)
+                .expect("Failed to encode PNG");
+            BASE64_STANDARD.encode(output.into_inner())
+        };
+
+        assert_eq!(decode_png(&base64, 12).unwrap().dimensions(), (4, 3));
+        assert!(matches!(
+            decode_png(&base64, 11),
+            Err(api::Error::ExceedsMaximum(name, 11)) if name == "screenshot size in pixels"
+        ));
+        assert!(matches!(
+            decode_png("not base64!", 12),
+            Err(api::Error::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_max_diff_pixels() {
+        let limits = ScreenshotLimits {
+            max_width: 1000,
+            ..ScreenshotLimits::default()
+        };
+        assert_eq!(max_diff_pixels(limits), 1000 * MAX_DIFF_HEIGHT);
+
+        let limits = ScreenshotLimits {
+            max_width: 1000,
+            max_page_height: 2000,
+            ..ScreenshotLimits::default()
+        };
+        assert_eq!(max_diff_pixels(limits), 2_000_000);
+    }
+
+    #[test]
+    fn test_multipart_body() {
+        let body = multipart_body(br#"{"width":2}"#, b"\x89PNG");
+
+        assert!(MULTIPART_CONTENT_TYPE.ends_with(&format!("boundary={MULTIPART_BOUNDARY}")));
+        assert_eq!(
+            body,
+            b"--pantin-screenshot-boundary\r\nContent-Type: application/json\r\nContent-Length: 11\r\n\r\n\
+              {\"width\":2}\r\n\
+              --pantin-screenshot-boundary\r\nContent-Type: image/png\r\nContent-Length: 4\r\n\r\n\
+              \x89PNG\r\n\
+              --pantin-screenshot-boundary--\r\n"
+        );
+    }
 }
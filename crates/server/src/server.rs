@@ -1,35 +1,63 @@
 //! This module starts the Pantin Server.
 //!
-//! It builds the Axum router with middleware (request IDs, tracing, timeouts), initializes the browser pool,
-//! and runs the server with graceful shutdown support. Background tasks are spawned to recycle and clean up
-//! browser instances.
+//! It builds the Axum router with middleware (request IDs, tracing, timeouts, compression, CORS, rate limiting),
+//! initializes the browser pool,
+//! and runs the server over TCP or a Unix domain socket with graceful shutdown support.
+//! Background tasks are spawned to warm up, replenish, recycle and clean up browser instances.
 
-use std::time::Duration;
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use axum::{
-    Router,
+    BoxError, Json, Router,
     body::Body,
-    http::{HeaderName, HeaderValue, Request},
-    routing::get,
+    error_handling::HandleErrorLayer,
+    http::{HeaderName, HeaderValue, Method, Request, StatusCode, header},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
 };
 use color_eyre::Result;
+use deadpool::managed::{Object, PoolError, Timeouts};
+use futures_util::future::join_all;
+use pantin_browser::profile::UserJs;
 use tokio::net::TcpListener;
-use tower::ServiceBuilder;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tower::{
+    ServiceBuilder,
+    timeout::{TimeoutLayer, error::Elapsed},
+};
 use tower_http::{
+    compression::CompressionLayer,
+    cors::{AllowOrigin, CorsLayer},
     request_id,
     request_id::{PropagateRequestIdLayer, SetRequestIdLayer},
-    timeout::TimeoutLayer,
     trace::TraceLayer,
 };
-use tracing::{debug, debug_span, error, info};
+use tracing::{Span, debug, debug_span, error, info, warn};
 use uuid::Uuid;
 
 use crate::{
+    api,
     browser_pool::{BrowserManager, BrowserPool},
-    cli, routes, signal,
-    state::State,
+    cache::ScreenshotCache,
+    cli, draining,
+    host_policy::HostPolicy,
+    metrics::Metrics,
+    rate_limit,
+    rate_limit::RateLimiter,
+    routes, signal,
+    state::{ScreenshotLimits, State},
 };
 
+/// The header holding the request id, generated if the client did not set it.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
 #[derive(Clone)]
 struct MakeRequestId;
 
@@ -47,11 +75,16 @@ impl request_id::MakeRequestId for MakeRequestId {
 /// Starts the Pantin Server with the given configuration settings.
 ///
 /// This function:
-/// 1. Configures middleware layers for request IDs, tracing, and timeouts.
-/// 2. Initializes the browser pool and shared state.
-/// 3. Builds the Axum router with routes (e.g. `/ping`, `/screenshot`) and fallback handling.
-/// 4. Spawns background tasks to recycle and clean up browser instances.
-/// 5. Binds a TCP listener to the configured host and port and serves the router with graceful shutdown.
+/// 1. Configures middleware layers for request IDs, tracing, timeouts (answered with a JSON 408), response compression
+///    and CORS (only if allowed origins are configured), plus a rate limit on the screenshot routes (only if configured).
+/// 2. Reads the custom `user.js` template if configured, then initializes the browser pool and shared state.
+/// 3. Builds the Axum router with routes (e.g. `/ping`, `/version`, `/screenshot`, `/screenshot/stream`,
+///    `/screenshot/batch`, `/screenshot/diff`, `/metrics/prometheus`) and fallback handling.
+/// 4. Spawns background tasks to warm up, replenish, recycle and clean up browser instances.
+/// 5. Binds a Unix domain socket listener if a socket path is configured, otherwise a TCP listener
+///    to the configured host and port, and serves the router with graceful shutdown.
+///
+/// In dry-run mode, it only binds the listener and opens and closes a browser after the step 3, then returns.
 ///
 /// # Arguments
 ///
@@ -60,43 +93,285 @@ impl request_id::MakeRequestId for MakeRequestId {
 /// # Errors
 ///
 /// Returns an [`Error`] if binding to the address fails, or if any initialization step
-/// (e.g. setting up middleware or the browser pool) encounters an error.
+/// (e.g. reading the `user.js` template, setting up middleware or the browser pool) encounters an error.
 pub async fn start(settings: cli::PantinSettings) -> Result<()> {
     debug!(?settings, "Starting...");
 
-    let x_request_id = HeaderName::from_static("x-request-id");
+    let x_request_id = HeaderName::from_static(REQUEST_ID_HEADER);
     let request_id_layer = SetRequestIdLayer::new(x_request_id.clone(), MakeRequestId);
-    let propagate_request_id_layer = PropagateRequestIdLayer::new(x_request_id.clone());
+    let propagate_request_id_layer = PropagateRequestIdLayer::new(x_request_id);
 
-    let trace_layer = TraceLayer::new_for_http().make_span_with(move |request: &Request<Body>| {
-        let default_value = HeaderValue::from_static("none");
-        let uuid = request.headers().get(&x_request_id).unwrap_or(&default_value);
-        debug_span!("request", ?uuid, method=?request.method(), uri=?request.uri(), version=?request.version())
-    });
+    let trace_layer = TraceLayer::new_for_http().make_span_with(request_span);
 
     let timeout_layer = TimeoutLayer::new(Duration::from_secs(u64::from(settings.request_timeout)));
 
+    // Compress according to the client's `Accept-Encoding`, images (already compressed) and SSE are left untouched.
+    let compression_layer = CompressionLayer::new();
+
     let service_builder = ServiceBuilder::new()
         .layer(request_id_layer)
         .layer(propagate_request_id_layer)
         .layer(trace_layer)
-        .layer(timeout_layer);
+        .layer(HandleErrorLayer::new(handle_timeout_error))
+        .layer(timeout_layer)
+        .layer(compression_layer);
 
-    let browser_pool = BrowserPool::builder(BrowserManager::new(settings.browser_program.clone()))
+    let user_js = match &settings.user_js_path {
+        Some(path) => UserJs::read(path).await?,
+        None => UserJs::default(),
+    };
+
+    let browser_manager = BrowserManager::new(settings.browser_program.clone())
+        .with_proxy(settings.browser_proxy.clone())
+        .with_args(settings.browser_args.clone())
+        .with_headless(!settings.no_headless)
+        .with_user_js(user_js)
+        .with_clear_cache(settings.browser_clear_cache);
+    let browser_pool = BrowserPool::builder(browser_manager)
         .max_size(usize::from(settings.browser_pool_max_size))
         .build()?;
 
-    let state = State::new(browser_pool.clone());
+    let state = app_state(&settings, browser_pool.clone())?;
+    let metrics = state.metrics().clone();
+    let rate_limiter =
+        RateLimiter::new(settings.rate_limit).with_api_keys(&settings.rate_limit_api_keys);
+
+    if rate_limiter.is_enabled() {
+        debug!(per_minute = settings.rate_limit, "Rate limit enabled");
+    }
+
+    let rate_limit_layer = middleware::from_fn_with_state(rate_limiter, rate_limit::limit);
 
     let router = Router::new()
+        .route(
+            "/screenshot",
+            get(routes::screenshot).post(routes::screenshot_post),
+        )
+        .route("/screenshot/stream", get(routes::screenshot_stream))
+        .route("/screenshot/batch", post(routes::screenshot_batch))
+        .route("/screenshot/diff", post(routes::screenshot_diff))
+        .route_layer(rate_limit_layer.clone())
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            draining::reject,
+        ))
         .route("/ping", get(routes::ping))
-        .route("/screenshot", get(routes::screenshot))
+        .route("/health", get(routes::health))
+        .route("/version", get(routes::version))
+        .route("/metrics/prometheus", get(routes::metrics_prometheus))
+        // Rate limited too, so the admin API key cannot be brute forced.
+        .route(
+            "/admin/drain",
+            post(routes::admin_drain).route_layer(rate_limit_layer),
+        )
         .fallback(routes::not_found)
-        .layer(service_builder)
-        .with_state(state);
+        .layer(service_builder);
+
+    let router = match cors_layer(&settings.cors_origins)? {
+        Some(cors_layer) => router.layer(cors_layer),
+        None => router,
+    }
+    .with_state(state);
+
+    if settings.dry_run {
+        return dry_run(&settings, &browser_pool).await;
+    }
+
+    let min_idle = usize::from(settings.browser_pool_min_idle);
+    tokio::spawn(warm_up(browser_pool.clone(), min_idle));
+
+    if min_idle > 0 {
+        tokio::spawn(replenish_loop(browser_pool.clone(), min_idle));
+    }
+
+    tokio::spawn(retain_loop(settings.clone(), browser_pool.clone(), metrics));
+
+    match settings.server_socket.clone() {
+        Some(socket) => serve_unix(socket, router).await?,
+        None => serve_tcp(&settings, router).await?,
+    }
+
+    debug!("Cleaning browser pool...");
+    cleaning_loop(
+        browser_pool,
+        Duration::from_secs(u64::from(settings.shutdown_timeout)),
+    )
+    .await;
+
+    info!("Exited gracefully !");
+
+    Ok(())
+}
+
+/// Builds the state shared by the handlers: the browser pool, the screenshot cache, limits and host policy.
+fn app_state(settings: &cli::PantinSettings, browser_pool: BrowserPool) -> Result<State> {
+    let screenshot_cache = ScreenshotCache::new(
+        usize::from(settings.cache_capacity),
+        Duration::from_secs(u64::from(settings.cache_ttl)),
+    );
+
+    if screenshot_cache.is_enabled() {
+        debug!(
+            capacity = settings.cache_capacity,
+            ttl = settings.cache_ttl,
+            "Screenshot cache enabled"
+        );
+    }
+
+    let state = State::new(
+        browser_pool,
+        Duration::from_secs(u64::from(settings.browser_acquire_timeout)),
+        screenshot_cache,
+        ScreenshotLimits {
+            max_width: settings.max_width,
+            max_height: settings.max_height,
+            max_elements: settings.max_elements,
+            max_page_height: settings.max_page_height,
+            max_delay: settings.max_delay,
+        },
+    )
+    .with_host_policy(HostPolicy::new(
+        &settings.allowed_hosts,
+        &settings.blocked_hosts,
+    )?)
+    .with_retry_after(Duration::from_secs(u64::from(settings.browser_retry_after)))
+    .with_request_timeout(Duration::from_secs(u64::from(settings.request_timeout)))
+    .with_admin_api_key(settings.admin_api_key.clone());
+
+    if state.host_policy().is_enabled() {
+        debug!(
+            allowed_hosts = ?settings.allowed_hosts,
+            blocked_hosts = ?settings.blocked_hosts,
+            "Host policy enabled"
+        );
+    }
+
+    Ok(state)
+}
+
+/// Builds the CORS layer allowing `GET` and `POST` requests (with a JSON body) from the given origins,
+/// or `None` if there is no origin.
+fn cors_layer(origins: &[String]) -> Result<Option<CorsLayer>> {
+    if origins.is_empty() {
+        return Ok(None);
+    }
+
+    let origins = origins
+        .iter()
+        .map(|origin| HeaderValue::from_str(origin.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    debug!(?origins, "Enabling CORS...");
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([header::CONTENT_TYPE]),
+    ))
+}
+
+/// Creates the span of a request, holding its `request_id` (see [`REQUEST_ID_HEADER`]).
+///
+/// The handlers run within this span, so every event logged while serving the request, down to the Marionette
+/// commands sent to the browser, carries the request id.
+fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("none");
+
+    debug_span!("request", request_id = %request_id, method=?request.method(), uri=?request.uri(), version=?request.version())
+}
+
+/// Converts the errors raised by the timeout layer into JSON [`Failure`](api::Failure) responses.
+///
+/// An elapsed timeout results in `REQUEST_TIMEOUT`, any other error in `INTERNAL_SERVER_ERROR`.
+async fn handle_timeout_error(error: BoxError) -> Response {
+    if error.is::<Elapsed>() {
+        (
+            StatusCode::REQUEST_TIMEOUT,
+            Json(api::Failure::new("request_timeout", "request timeout")),
+        )
+            .into_response()
+    } else {
+        error!(?error, "Unhandled middleware error");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(api::Failure::new("internal_error", error.to_string())),
+        )
+            .into_response()
+    }
+}
+
+/// Validates the configuration without serving: binds (then releases) the listener, and opens then closes
+/// a browser configured like the pooled ones.
+async fn dry_run(settings: &cli::PantinSettings, browser_pool: &BrowserPool) -> Result<()> {
+    let listen = if let Some(socket) = &settings.server_socket {
+        check_unix_socket(socket)?;
+        format!("unix:{}", socket.display())
+    } else {
+        let listener =
+            TcpListener::bind((settings.server_host.clone(), settings.server_port)).await?;
+        drop(listener);
+        format!("http://{}:{}", settings.server_host, settings.server_port)
+    };
+
+    let browser = browser_pool.manager().open(None, None).await?;
+    let pid = browser.pid();
+    browser.close().await?;
+
+    info!(
+        listen,
+        browser_program = settings.browser_program,
+        ?pid,
+        browser_pool_max_size = settings.browser_pool_max_size,
+        "Dry run succeeded, the configuration is valid"
+    );
+
+    Ok(())
+}
+
+/// Binds a Unix domain socket listener to the path, then removes the socket file.
+#[cfg(unix)]
+fn check_unix_socket(socket: &Path) -> Result<()> {
+    let listener = bind_unix_socket(socket)?;
+    drop(listener);
+    std::fs::remove_file(socket)?;
+
+    Ok(())
+}
+
+/// Binds a Unix domain socket listener to the path.
+///
+/// A stale socket file, left by a server that did not shut down gracefully (nothing accepts connections on it),
+/// is removed first. Other files, and the sockets of a running server, are kept and the bind fails.
+#[cfg(unix)]
+fn bind_unix_socket(socket: &Path) -> Result<UnixListener> {
+    use std::os::unix::{fs::FileTypeExt, net::UnixStream};
+
+    let is_socket =
+        std::fs::symlink_metadata(socket).is_ok_and(|metadata| metadata.file_type().is_socket());
+    if is_socket
+        && UnixStream::connect(socket)
+            .is_err_and(|error| error.kind() == std::io::ErrorKind::ConnectionRefused)
+    {
+        warn!("Removing stale socket file at: {}", socket.display());
+        std::fs::remove_file(socket)?;
+    }
+
+    Ok(UnixListener::bind(socket)?)
+}
 
-    tokio::spawn(retain_loop(settings.clone(), browser_pool.clone()));
+#[cfg(not(unix))]
+fn check_unix_socket(_socket: &Path) -> Result<()> {
+    Err(color_eyre::eyre::eyre!(
+        "Unix domain sockets are only supported on Unix platforms"
+    ))
+}
 
+async fn serve_tcp(settings: &cli::PantinSettings, router: Router) -> Result<()> {
     let listener = TcpListener::bind((settings.server_host.clone(), settings.server_port)).await?;
     info!(
         "Listening at http://{}:{}",
@@ -104,16 +379,39 @@ pub async fn start(settings: cli::PantinSettings) -> Result<()> {
     );
 
     info!("Press [CTRL+C] to exit gracefully.");
-    axum::serve(listener, router)
+    // The peer address identifies the clients without API key for the rate limit.
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn serve_unix(socket: PathBuf, router: Router) -> Result<()> {
+    let listener = bind_unix_socket(&socket)?;
+    info!("Listening at unix:{}", socket.display());
+
+    info!("Press [CTRL+C] to exit gracefully.");
+    let served = axum::serve(listener, router)
         .with_graceful_shutdown(shutdown_signal())
-        .await?;
+        .await;
 
-    debug!("Cleaning browser pool...");
-    cleaning_loop(browser_pool).await?;
+    debug!("Removing socket file at: {}", socket.display());
+    tokio::fs::remove_file(&socket).await?;
 
-    info!("Exited gracefully !");
+    Ok(served?)
+}
 
-    Ok(())
+#[cfg(not(unix))]
+#[allow(clippy::unused_async)]
+async fn serve_unix(_socket: PathBuf, _router: Router) -> Result<()> {
+    Err(color_eyre::eyre::eyre!(
+        "Unix domain sockets are only supported on Unix platforms"
+    ))
 }
 
 async fn shutdown_signal() {
@@ -123,32 +421,203 @@ async fn shutdown_signal() {
     }
 }
 
-async fn retain_loop(settings: cli::PantinSettings, browser_pool: BrowserPool) -> Result<()> {
+/// Creates up to `count` new browsers in the pool (bounded by its maximum size) so they are warm for the next requests.
+///
+/// The pool only creates a browser when no idle one is left, so the idle browsers are acquired along with
+/// the missing ones, all at once. Each browser is released as soon as it is ready (the created ones included),
+/// and none is waited for: the warm-up never holds a browser a request is waiting for.
+async fn warm_up(browser_pool: BrowserPool, count: usize) {
+    let status = browser_pool.status();
+    let count = count.min(status.max_size.saturating_sub(status.size));
+    let timeouts = Timeouts::wait_millis(0);
+
+    debug!(count, "Warming up browser pool...");
+
+    let results = join_all((0..status.available + count).map(|_| async {
+        browser_pool
+            .timeout_get(&timeouts)
+            .await
+            // A created browser has never been recycled.
+            .map(|browser| Object::metrics(&browser).recycled.is_none())
+    }))
+    .await;
+
+    let mut created = 0_usize;
+    for result in results {
+        match result {
+            Ok(is_created) => created += usize::from(is_created),
+            // The requests took the browsers (or the slots) first.
+            Err(PoolError::Timeout(_)) => {},
+            Err(error) => error!(?error, "Failed to warm up browser pool !"),
+        }
+    }
+
+    debug!(created, "Browser pool warmed up");
+}
+
+/// Interval between two checks of the idle browsers by [`replenish_loop`].
+const REPLENISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The maximum time an idle browser has to answer a ping of the [`retain_loop`].
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Periodically tops the pool back up to `min_idle` idle browsers, bounded by the pool maximum size.
+///
+/// The browsers discarded by a failed recycle (or removed by the [`retain_loop`]) are replaced in the background,
+/// so the next requests don't pay the cold start.
+async fn replenish_loop(browser_pool: BrowserPool, min_idle: usize) {
+    loop {
+        tokio::time::sleep(REPLENISH_INTERVAL).await;
+
+        let status = browser_pool.status();
+        let room = status.max_size.saturating_sub(status.size);
+        let missing = min_idle.saturating_sub(status.available).min(room);
+
+        if missing > 0 {
+            debug!(missing, ?status, "Replenishing browser pool...");
+            warm_up(browser_pool.clone(), missing).await;
+        }
+    }
+}
+
+/// Periodically removes from the pool the browsers unused for longer than the max age, or recycled too many times,
+/// or that failed to answer a ping, and closes them concurrently within the shutdown timeout.
+///
+/// Each cycle logs the number of removed browsers by reason, and counts them in the [`Metrics`].
+async fn retain_loop(
+    settings: cli::PantinSettings,
+    browser_pool: BrowserPool,
+    metrics: Metrics,
+) -> Result<()> {
     let browser_max_age = Duration::from_secs(u64::from(settings.browser_max_age));
     let browser_max_recycle_count = usize::from(settings.browser_max_recycle_count);
+    let shutdown_timeout = Duration::from_secs(u64::from(settings.shutdown_timeout));
 
     loop {
         tokio::time::sleep(browser_max_age).await;
 
+        let mut by_age = 0_usize;
+        let mut by_recycle_count = 0_usize;
         let retain_result = browser_pool.retain(|_, metrics| {
-            metrics.recycle_count < browser_max_recycle_count
-                && metrics.last_used() < browser_max_age
+            if metrics.recycle_count >= browser_max_recycle_count {
+                by_recycle_count += 1;
+                false
+            } else if metrics.last_used() >= browser_max_age {
+                by_age += 1;
+                false
+            } else {
+                true
+            }
         });
 
-        for browser in retain_result.removed {
-            browser.close().await?;
+        // The remaining idle browsers are pinged, so the ones whose session died are not handed to a request.
+        let unresponsive = ping_idle(&browser_pool).await;
+        let ping_result = browser_pool.retain(|browser, _| !unresponsive.contains(&browser.uuid()));
+        let by_ping = ping_result.removed.len();
+
+        let removed = retain_result.removed.len() + by_ping;
+        info!(
+            removed,
+            by_age, by_recycle_count, by_ping, "Browser pool retained"
+        );
+        metrics.browsers_recycled(u64::try_from(removed).unwrap_or(u64::MAX));
+
+        // A browser that failed its ping has usually crashed and fails to close, which is only logged.
+        let closing = retain_result
+            .removed
+            .into_iter()
+            .chain(ping_result.removed)
+            .map(|browser| (browser.uuid(), browser.close()));
+        drain(closing, shutdown_timeout).await;
+    }
+}
+
+/// Pings the idle browsers of the pool concurrently, each one bounded by the ping timeout, and returns the
+/// identifiers of the ones that failed to answer.
+///
+/// A browser a request acquired since it was collected is skipped if the request is using its connection
+/// (see [`pantin_browser::Pinger::try_ping`]), so a busy browser is never taken for an unresponsive one.
+/// The browsers that failed to answer are discarded too (see [`BrowserManager::discard`]), so the ones a request
+/// acquired during the ping are never handed out again.
+async fn ping_idle(browser_pool: &BrowserPool) -> HashSet<Uuid> {
+    let mut pingers = Vec::new();
+    // Only the idle browsers are visited by `retain`, and all of them are kept.
+    browser_pool.retain(|browser, _| {
+        pingers.push(browser.pinger());
+        true
+    });
+
+    let results = join_all(pingers.iter().map(|pinger| async move {
+        let uuid = pinger.uuid();
+
+        match tokio::time::timeout(PING_TIMEOUT, pinger.try_ping()).await {
+            Ok(None) => {
+                debug!(?uuid, "Browser busy, ping skipped");
+                None
+            },
+            Ok(Some(Ok(()))) => None,
+            Ok(Some(Err(error))) => {
+                warn!(?uuid, ?error, "Browser ping failed !");
+                Some(uuid)
+            },
+            Err(_) => {
+                warn!(
+                    ?uuid,
+                    ?PING_TIMEOUT,
+                    "Browser did not answer the ping in time !"
+                );
+                Some(uuid)
+            },
         }
+    }))
+    .await;
+
+    let unresponsive: HashSet<Uuid> = results.into_iter().flatten().collect();
+    for uuid in &unresponsive {
+        browser_pool.manager().discard(*uuid);
     }
+
+    unresponsive
 }
 
-async fn cleaning_loop(browser_pool: BrowserPool) -> Result<()> {
+/// Removes all the browsers from the pool and closes them concurrently, within the shutdown timeout.
+async fn cleaning_loop(browser_pool: BrowserPool, shutdown_timeout: Duration) {
     let retain_result = browser_pool.retain(|_, _| false);
+    let closing = retain_result
+        .removed
+        .into_iter()
+        .map(|browser| (browser.uuid(), browser.close()));
 
-    for browser in retain_result.removed {
-        browser.close().await?;
-    }
+    let closed = drain(closing, shutdown_timeout).await;
+    debug!(closed, "Browser pool drained");
+}
 
-    Ok(())
+/// Awaits the closing futures concurrently, each one bounded by the shutdown timeout, and logs the failures.
+///
+/// A closing future that does not complete in time is dropped, which kills the browser process.
+/// Returns the number of browsers closed successfully.
+async fn drain<I, F, T, E>(closing: I, shutdown_timeout: Duration) -> usize
+where
+    I: IntoIterator<Item = (Uuid, F)>,
+    F: Future<Output = std::result::Result<T, E>>,
+    E: std::fmt::Debug,
+{
+    let results = join_all(closing.into_iter().map(|(uuid, close)| async move {
+        match tokio::time::timeout(shutdown_timeout, close).await {
+            Ok(Ok(_)) => true,
+            Ok(Err(error)) => {
+                error!(?uuid, ?error, "Failed to close browser !");
+                false
+            },
+            Err(_) => {
+                error!(?uuid, ?shutdown_timeout, "Browser did not close in time !");
+                false
+            },
+        }
+    }))
+    .await;
+
+    results.into_iter().filter(|closed| *closed).count()
 }
 
 #[cfg(test)]
@@ -158,10 +627,11 @@ mod tests {
     use std::sync::atomic::{AtomicU16, Ordering};
 
     use tokio::task::JoinHandle;
+    use tracing::Instrument;
     use url::Url;
 
     use super::*;
-    use crate::cli::{LogLevel, PantinSettings};
+    use crate::cli::{LogFormat, LogLevel, PantinSettings};
 
     static PORT_COUNTER: AtomicU16 = AtomicU16::new(3000);
 
@@ -177,14 +647,41 @@ mod tests {
     impl ServerAssert {
         fn new() -> Self {
             let settings = PantinSettings {
+                config: None,
                 server_host: "127.0.0.1".into(),
                 server_port: get_next_port(),
+                server_socket: None,
+                cors_origins: Vec::new(),
+                max_width: 4096,
+                max_height: 4096,
+                max_elements: 100,
+                max_delay: 10000,
+                max_page_height: 0,
+                cache_capacity: 0,
+                shutdown_timeout: 10,
+                cache_ttl: 60,
+                rate_limit: 0,
+                rate_limit_api_keys: Vec::new(),
+                allowed_hosts: Vec::new(),
+                blocked_hosts: Vec::new(),
+                admin_api_key: None,
                 request_timeout: 1,
                 browser_pool_max_size: 1,
+                browser_pool_min_idle: 0,
                 browser_max_age: 1,
                 browser_max_recycle_count: 1,
+                browser_acquire_timeout: 1,
+                browser_retry_after: 0,
                 browser_program: "firefox".into(),
+                browser_proxy: None,
+                browser_args: Vec::new(),
+                browser_clear_cache: false,
+                user_js_path: None,
+                no_headless: false,
+                dry_run: false,
                 log_level: LogLevel::Trace,
+                log_format: LogFormat::Pretty,
+                command: None,
             };
             let url_string = format!("http://{}:{}", settings.server_host, settings.server_port);
             let base_url = Url::parse(url_string.as_str()).expect("Parse base url");
@@ -206,6 +703,270 @@ mod tests {
         }
     }
 
+    async fn fake_close(
+        delay: Duration,
+        result: std::result::Result<(), ()>,
+    ) -> std::result::Result<(), ()> {
+        tokio::time::sleep(delay).await;
+        result
+    }
+
+    #[tokio::test]
+    async fn test_drain_shutdown_timeout() {
+        let slow = Duration::from_secs(10);
+        let closing = vec![
+            (Uuid::new_v4(), fake_close(Duration::ZERO, Ok(()))),
+            (Uuid::new_v4(), fake_close(slow, Ok(()))),
+            (Uuid::new_v4(), fake_close(Duration::ZERO, Err(()))),
+            (Uuid::new_v4(), fake_close(slow, Ok(()))),
+            (
+                Uuid::new_v4(),
+                fake_close(Duration::from_millis(50), Ok(())),
+            ),
+        ];
+
+        let start = std::time::Instant::now();
+        let closed = drain(closing, Duration::from_millis(200)).await;
+        let elapsed = start.elapsed();
+
+        assert_eq!(closed, 2);
+        assert!(
+            elapsed < Duration::from_millis(500),
+            "Slow browsers should not block the shutdown, waited: {elapsed:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_warm_up() {
+        let browser_pool = BrowserPool::builder(BrowserManager::new("firefox"))
+            .max_size(2)
+            .build()
+            .expect("Failed to build pool");
+
+        warm_up(browser_pool.clone(), 2).await;
+
+        assert!(
+            browser_pool.status().available >= 2,
+            "Pool should have at least 2 available browsers, got: {:?}",
+            browser_pool.status()
+        );
+
+        cleaning_loop(browser_pool, Duration::from_secs(10)).await;
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_retain_loop() {
+        let mut settings = ServerAssert::new().settings;
+        settings.browser_max_age = 1;
+        let browser_pool = BrowserPool::builder(BrowserManager::new("firefox"))
+            .max_size(1)
+            .build()
+            .expect("Failed to build pool");
+        let metrics = Metrics::default();
+
+        warm_up(browser_pool.clone(), 1).await;
+        assert_eq!(browser_pool.status().size, 1);
+
+        let retained = tokio::time::timeout(
+            Duration::from_millis(2500),
+            retain_loop(settings, browser_pool.clone(), metrics.clone()),
+        )
+        .await;
+
+        assert!(
+            retained.is_err(),
+            "Retain loop should run until the timeout"
+        );
+        assert_eq!(browser_pool.status().size, 0);
+        assert!(logs_contain(
+            "Browser pool retained removed=1 by_age=1 by_recycle_count=0"
+        ));
+        assert!(
+            metrics
+                .prometheus(browser_pool.status())
+                .contains("pantin_browsers_recycled_total 1")
+        );
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_replenish_loop() {
+        use nix::{sys::signal, unistd::Pid};
+
+        let browser_pool = BrowserPool::builder(BrowserManager::new("firefox"))
+            .max_size(2)
+            .build()
+            .expect("Failed to build pool");
+
+        warm_up(browser_pool.clone(), 1).await;
+        let pid = browser_pool
+            .get()
+            .await
+            .expect("Pooled browser")
+            .pid()
+            .expect("Browser pid");
+        signal::kill(
+            Pid::from_raw(i32::try_from(pid).unwrap()),
+            signal::Signal::SIGKILL,
+        )
+        .expect("Kill browser");
+
+        // The recycle of the killed browser fails, so a new one is created for this request.
+        let browser = browser_pool.get().await.expect("Replaced browser");
+        assert_ne!(browser.pid(), Some(pid));
+        assert_eq!(browser_pool.status().available, 0);
+
+        let replenish = tokio::spawn(replenish_loop(browser_pool.clone(), 1));
+        let replenished = tokio::time::timeout(Duration::from_secs(30), async {
+            while browser_pool.status().available < 1 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await;
+        replenish.abort();
+
+        assert!(
+            replenished.is_ok(),
+            "Pool should be replenished, got: {:?}",
+            browser_pool.status()
+        );
+        assert_eq!(browser_pool.status().size, 2);
+
+        drop(browser);
+        cleaning_loop(browser_pool, Duration::from_secs(10)).await;
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_ping_idle() {
+        use nix::{sys::signal, unistd::Pid};
+
+        let browser_pool = BrowserPool::builder(BrowserManager::new("firefox"))
+            .max_size(2)
+            .build()
+            .expect("Failed to build pool");
+
+        warm_up(browser_pool.clone(), 2).await;
+        let (killed, alive) = {
+            let first = browser_pool.get().await.expect("Pooled browser");
+            let second = browser_pool.get().await.expect("Pooled browser");
+            (first.uuid(), second.uuid())
+        };
+        let mut pid = None;
+        browser_pool.retain(|browser, _| {
+            if browser.uuid() == killed {
+                pid = browser.pid();
+            }
+            true
+        });
+        signal::kill(
+            Pid::from_raw(i32::try_from(pid.expect("Browser pid")).unwrap()),
+            signal::Signal::SIGKILL,
+        )
+        .expect("Kill browser");
+
+        let unresponsive = ping_idle(&browser_pool).await;
+        assert_eq!(unresponsive, HashSet::from([killed]));
+        assert_eq!(
+            browser_pool.status().available,
+            2,
+            "Ping should keep the browsers idle"
+        );
+
+        let retain_result =
+            browser_pool.retain(|browser, _| !unresponsive.contains(&browser.uuid()));
+        assert_eq!(retain_result.removed.len(), 1);
+        for browser in retain_result.removed {
+            browser.close().await.expect("Closing browser");
+        }
+
+        let mut browser = browser_pool.get().await.expect("Pooled browser");
+        assert_eq!(browser.uuid(), alive);
+        browser
+            .ping()
+            .await
+            .expect("Pinged browser should stay usable");
+
+        drop(browser);
+        cleaning_loop(browser_pool, Duration::from_secs(10)).await;
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_request_span_marionette_logs() {
+        let request = Request::builder()
+            .header(REQUEST_ID_HEADER, "test-request-id")
+            .body(Body::empty())
+            .expect("Request");
+        let mut browser = BrowserManager::new("firefox")
+            .open(None, None)
+            .await
+            .expect("Opening browser");
+
+        browser
+            .current_url()
+            .instrument(request_span(&request))
+            .await
+            .expect("Current URL");
+        browser.close().await.expect("Closing browser");
+
+        logs_assert(|lines: &[&str]| {
+            lines
+                .iter()
+                .any(|line| {
+                    line.contains("request{request_id=test-request-id")
+                        && line.contains("pantin_marionette::request: Write request")
+                })
+                .then_some(())
+                .ok_or_else(|| "No Marionette log carries the request id".to_string())
+        });
+    }
+
+    #[tokio::test]
+    async fn test_server_dry_run() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.dry_run = true;
+
+        let result = tokio::time::timeout(Duration::from_secs(30), start(server_assert.settings))
+            .await
+            .expect("Dry run should not serve");
+
+        assert!(result.is_ok(), "Dry run should succeed, got: {result:?}");
+    }
+
+    #[tokio::test]
+    async fn test_server_dry_run_invalid_browser_program() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.dry_run = true;
+        server_assert.settings.browser_program = "invalid-browser-command".into();
+
+        let result = tokio::time::timeout(Duration::from_secs(30), start(server_assert.settings))
+            .await
+            .expect("Dry run should not serve");
+
+        assert!(
+            result.is_err(),
+            "Dry run should fail with an invalid browser"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_missing_user_js_path() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.dry_run = true;
+        server_assert.settings.user_js_path = Some(PathBuf::from("/missing/pantin/user.js"));
+
+        let error = start(server_assert.settings)
+            .await
+            .expect_err("Start should fail with a missing user.js template");
+
+        assert!(
+            error.to_string().contains("/missing/pantin/user.js"),
+            "Error should name the template path, got: {error}"
+        );
+    }
+
     #[tokio::test]
     async fn test_server_ping() {
         let server_assert = ServerAssert::new();
@@ -226,40 +987,1237 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_server_not_found() {
+    async fn test_server_version() {
         let server_assert = ServerAssert::new();
         let server_handle = server_assert.spawn().await;
 
-        let response = reqwest::get(server_assert.url("this-route-does-not-exists"))
+        let response = reqwest::get(server_assert.url("version"))
             .await
             .expect("Failed to send GET request");
-        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
 
         let body = response.text().await.expect("Failed to read response body");
+        let json: serde_json::Value = serde_json::from_str(&body).expect("Invalid JSON response");
         assert_eq!(
-            body, r#"{"cause":"not found"}"#,
-            "Expected not found JSON response, got: {body}"
+            json["data"]["version"],
+            env!("CARGO_PKG_VERSION"),
+            "Unexpected version, got: {body}"
+        );
+        assert_eq!(
+            json["data"]["gitHash"].as_str(),
+            option_env!("PANTIN_GIT_HASH")
         );
 
         server_handle.abort();
     }
 
     #[tokio::test]
-    async fn test_server_screenshot() {
+    async fn test_server_metrics_prometheus() {
         let server_assert = ServerAssert::new();
         let server_handle = server_assert.spawn().await;
 
-        let response = reqwest::get(server_assert.url("screenshot"))
+        // Exceeds the maximum width, so it fails without using a browser.
+        let response =
+            reqwest::get(server_assert.url("screenshot?url=https://example.com&width=5000"))
+                .await
+                .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let response = reqwest::get(server_assert.url("metrics/prometheus"))
             .await
             .expect("Failed to send GET request");
-        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers().get(reqwest::header::CONTENT_TYPE),
+            Some(&reqwest::header::HeaderValue::from_static(
+                "text/plain; version=0.0.4; charset=utf-8"
+            )),
+        );
+
+        let body = response.text().await.expect("Failed to read response body");
+        for line in [
+            "# TYPE pantin_screenshots_total counter",
+            "pantin_screenshots_total 1",
+            "# TYPE pantin_screenshot_errors_total counter",
+            "pantin_screenshot_errors_total 1",
+            "# TYPE pantin_browser_pool_max_size gauge",
+            "pantin_browser_pool_max_size 1",
+            "# TYPE pantin_browser_pool_size gauge",
+            "pantin_browser_pool_size 0",
+            "# TYPE pantin_browser_pool_available gauge",
+            "pantin_browser_pool_available 0",
+            "# TYPE pantin_screenshot_capture_duration_seconds histogram",
+            "pantin_screenshot_capture_duration_seconds_bucket{le=\"+Inf\"} 0",
+            "pantin_screenshot_capture_duration_seconds_count 0",
+        ] {
+            assert!(
+                body.lines().any(|actual| actual == line),
+                "Missing line {line:?} in:\n{body}"
+            );
+        }
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_health() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.request_timeout = 30;
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(server_assert.url("health"))
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
 
         let body = response.text().await.expect("Failed to read response body");
         assert_eq!(
-            body, r#"{"cause":"Failed to deserialize query string: missing field `url`"}"#,
-            "Expected not found JSON response, got: {body}"
+            body, r#"{"data":"healthy"}"#,
+            "Expected healthy JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_health_invalid_browser_program() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.browser_program = "invalid-browser-command".into();
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(server_assert.url("health"))
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert!(
+            body.starts_with(r#"{"cause":"unhealthy: "#),
+            "Expected unhealthy JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_server_unix_socket_ping() {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::UnixStream,
+        };
+
+        let socket = std::env::temp_dir().join(format!("pantin-{}.sock", Uuid::new_v4()));
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.server_socket = Some(socket.clone());
+        let server_handle = server_assert.spawn().await;
+
+        let mut stream = UnixStream::connect(&socket)
+            .await
+            .expect("Failed to connect to socket");
+        stream
+            .write_all(b"GET /ping HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .expect("Failed to send GET request");
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .await
+            .expect("Failed to read response");
+
+        assert!(
+            response.starts_with("HTTP/1.1 200 OK"),
+            "Expected 200 OK, got: {response}"
+        );
+        assert!(
+            response.ends_with(r#"{"data":"pong"}"#),
+            "Expected pong JSON response, got: {response}"
         );
 
+        server_handle.abort();
+        std::fs::remove_file(&socket).expect("Remove socket file");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_bind_unix_socket_stale() {
+        let socket = std::env::temp_dir().join(format!("pantin-{}.sock", Uuid::new_v4()));
+
+        // A listener dropped without removing its file, like after a crash.
+        drop(std::os::unix::net::UnixListener::bind(&socket).expect("Bind socket"));
+        assert!(socket.exists());
+
+        let listener = bind_unix_socket(&socket).expect("Should replace the stale socket");
+        assert!(
+            bind_unix_socket(&socket).is_err(),
+            "Should not replace the socket of a running server"
+        );
+        assert!(socket.exists());
+
+        drop(listener);
+        std::fs::remove_file(&socket).expect("Remove socket file");
+    }
+
+    #[tokio::test]
+    async fn test_server_request_timeout() {
+        let router = Router::new()
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                    "too late"
+                }),
+            )
+            .layer(
+                ServiceBuilder::new()
+                    .layer(HandleErrorLayer::new(handle_timeout_error))
+                    .layer(TimeoutLayer::new(Duration::from_secs(1))),
+            );
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind listener");
+        let address = listener.local_addr().expect("Failed to get local address");
+        let server_handle = tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let response = reqwest::get(format!("http://{address}/slow"))
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::REQUEST_TIMEOUT);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body, r#"{"cause":"request timeout","code":"request_timeout"}"#,
+            "Expected request timeout JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_pool_exhausted_retry_after() {
+        let mut server_assert = ServerAssert::new();
+        // A pool without any browser is always exhausted.
+        server_assert.settings.browser_pool_max_size = 0;
+        server_assert.settings.request_timeout = 10;
+        server_assert.settings.browser_retry_after = 4;
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(server_assert.url("screenshot?url=http://example.com"))
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .expect("Numeric Retry-After header");
+        assert_eq!(retry_after, 4);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert!(
+            body.contains(r#""code":"pool_timeout""#),
+            "Expected pool timeout JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_admin_drain() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.admin_api_key = Some("secret".into());
+        let server_handle = server_assert.spawn().await;
+        let client = reqwest::Client::new();
+
+        for api_key in [None, Some("wrong")] {
+            let mut request = client.post(server_assert.url("admin/drain"));
+            if let Some(api_key) = api_key {
+                request = request.header("x-api-key", api_key);
+            }
+            let response = request.send().await.expect("Failed to send POST request");
+            assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        }
+
+        // Not draining yet: rejected by the route.
+        let response = client
+            .get(server_assert.url("screenshot"))
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let response = client
+            .post(server_assert.url("admin/drain"))
+            .header("x-api-key", "secret")
+            .send()
+            .await
+            .expect("Failed to send POST request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        for path in ["screenshot", "health"] {
+            let response = client
+                .get(server_assert.url(path))
+                .send()
+                .await
+                .expect("Failed to send GET request");
+            assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+            let body = response.text().await.expect("Failed to read response body");
+            assert!(
+                body.contains(r#""code":"draining""#),
+                "Expected draining JSON response, got: {body}"
+            );
+        }
+
+        let response = client
+            .get(server_assert.url("ping"))
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_admin_drain_rate_limit() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.admin_api_key = Some("secret".into());
+        server_assert.settings.rate_limit = 1;
+        let server_handle = server_assert.spawn().await;
+        let client = reqwest::Client::new();
+
+        for expected_status in [
+            reqwest::StatusCode::UNAUTHORIZED,
+            reqwest::StatusCode::TOO_MANY_REQUESTS,
+        ] {
+            let response = client
+                .post(server_assert.url("admin/drain"))
+                .header("x-api-key", "wrong")
+                .send()
+                .await
+                .expect("Failed to send POST request");
+            assert_eq!(response.status(), expected_status);
+        }
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_admin_drain_in_flight() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.admin_api_key = Some("secret".into());
+        server_assert.settings.request_timeout = 30;
+        let server_handle = server_assert.spawn().await;
+        let client = reqwest::Client::new();
+
+        let mut url = server_assert.url("screenshot");
+        url.query_pairs_mut()
+            .append_pair("url", server_assert.url("ping").as_str())
+            .append_pair("delay", "1000");
+        let in_flight = tokio::spawn(client.get(url.clone()).send());
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let response = client
+            .post(server_assert.url("admin/drain"))
+            .header("x-api-key", "secret")
+            .send()
+            .await
+            .expect("Failed to send POST request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = in_flight
+            .await
+            .expect("In flight request task")
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_rate_limit() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.rate_limit = 2;
+        server_assert.settings.rate_limit_api_keys = vec!["another-client".into()];
+        let server_handle = server_assert.spawn().await;
+        let client = reqwest::Client::new();
+
+        // Missing `url`: rejected by the route, after the rate limit.
+        for _ in 0..2 {
+            let response = client
+                .get(server_assert.url("screenshot"))
+                .send()
+                .await
+                .expect("Failed to send GET request");
+            assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        }
+
+        let response = client
+            .get(server_assert.url("screenshot"))
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .expect("Retry-After header");
+        assert!(
+            (1..=30).contains(&retry_after),
+            "Unexpected Retry-After: {retry_after}"
+        );
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert!(
+            body.starts_with(r#"{"cause":"too many requests"#),
+            "Expected too many requests JSON response, got: {body}"
+        );
+
+        let response = client
+            .get(server_assert.url("screenshot"))
+            .header("x-api-key", "unknown-client")
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(
+            response.status(),
+            reqwest::StatusCode::TOO_MANY_REQUESTS,
+            "Unknown API keys should share the bucket of the IP address"
+        );
+
+        let response = client
+            .get(server_assert.url("screenshot"))
+            .header("x-api-key", "another-client")
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(
+            response.status(),
+            reqwest::StatusCode::BAD_REQUEST,
+            "API keys should have their own bucket"
+        );
+
+        let response = client
+            .get(server_assert.url("ping"))
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(
+            response.status(),
+            reqwest::StatusCode::OK,
+            "Ping should not be rate limited"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_cors() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.cors_origins = vec!["http://dashboard.example".into()];
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::Client::new()
+            .request(reqwest::Method::OPTIONS, server_assert.url("screenshot"))
+            .header(reqwest::header::ORIGIN, "http://dashboard.example")
+            .header(reqwest::header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .send()
+            .await
+            .expect("Failed to send OPTIONS request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response
+                .headers()
+                .get(reqwest::header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some(&reqwest::header::HeaderValue::from_static(
+                "http://dashboard.example"
+            )),
+        );
+
+        let response = reqwest::Client::new()
+            .request(
+                reqwest::Method::OPTIONS,
+                server_assert.url("screenshot/batch"),
+            )
+            .header(reqwest::header::ORIGIN, "http://dashboard.example")
+            .header(reqwest::header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(
+                reqwest::header::ACCESS_CONTROL_REQUEST_HEADERS,
+                "content-type",
+            )
+            .send()
+            .await
+            .expect("Failed to send OPTIONS request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let headers = response.headers();
+        assert!(
+            headers[reqwest::header::ACCESS_CONTROL_ALLOW_METHODS]
+                .to_str()
+                .unwrap()
+                .contains("POST")
+        );
+        assert_eq!(
+            headers[reqwest::header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type"
+        );
+
+        let response = reqwest::Client::new()
+            .get(server_assert.url("ping"))
+            .header(reqwest::header::ORIGIN, "http://evil.example")
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(
+            response
+                .headers()
+                .get(reqwest::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .is_none(),
+            "Origin should not be allowed"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_not_found() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(server_assert.url("this-route-does-not-exists"))
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body, r#"{"cause":"not found","code":"not_found"}"#,
+            "Expected not found JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(server_assert.url("screenshot"))
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body,
+            r#"{"cause":"Failed to deserialize query string: missing field `url`","code":"invalid_query"}"#,
+            "Expected not found JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_max_delay() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.max_delay = 2000;
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(
+            server_assert.url("screenshot?url=https://www.infomaniak.com&delay=65535"),
+        )
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body, r#"{"cause":"delay exceeds the maximum of 2000","code":"exceeds_maximum"}"#,
+            "Expected delay failure JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_max_dimensions() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.request_timeout = 30;
+        server_assert.settings.max_width = 1024;
+        server_assert.settings.max_height = 768;
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(
+            server_assert.url("screenshot?url=https://www.infomaniak.com&width=1025&height=768"),
+        )
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body, r#"{"cause":"width exceeds the maximum of 1024","code":"exceeds_maximum"}"#,
+            "Expected width failure JSON response, got: {body}"
+        );
+
+        let response = reqwest::get(
+            server_assert.url("screenshot/stream?url=https://www.infomaniak.com&height=769"),
+        )
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let response = reqwest::get(
+            server_assert.url("screenshot?url=https://www.infomaniak.com&width=1024&height=768"),
+        )
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_max_page_height() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.request_timeout = 30;
+        server_assert.settings.max_page_height = 100;
+        let server_handle = server_assert.spawn().await;
+
+        let response =
+            reqwest::get(server_assert.url("screenshot?url=https://www.infomaniak.com&mode=full"))
+                .await
+                .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body, r#"{"cause":"page height exceeds the maximum of 100","code":"exceeds_maximum"}"#,
+            "Expected page height failure JSON response, got: {body}"
+        );
+
+        let response = reqwest::get(
+            server_assert.url("screenshot?url=https://www.infomaniak.com&mode=viewport"),
+        )
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(
+            response.status(),
+            reqwest::StatusCode::OK,
+            "Viewport captures should not be bounded by the page height"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_nav_timeout() {
+        // A server accepting connections but never answering.
+        let slow_listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind slow listener");
+        let slow_address = slow_listener.local_addr().expect("Slow listener address");
+        let slow_handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = slow_listener.accept().await {
+                tokio::spawn(async move {
+                    let _stream = stream;
+                    std::future::pending::<()>().await;
+                });
+            }
+        });
+
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.request_timeout = 30;
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(server_assert.url(format!(
+            "screenshot?url=http://{slow_address}&nav_timeout=500"
+        )))
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert!(
+            body.starts_with(r#"{"cause":"timeout: "#),
+            "Expected timeout JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+        slow_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_blocked_host_redirect() {
+        // A page redirecting from an accepted host (`127.0.0.1`) to a blocked one (`localhost`).
+        let page_listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind page listener");
+        let page_port = page_listener.local_addr().expect("Page address").port();
+        let page_router = Router::new()
+            .route(
+                "/",
+                get(move || async move {
+                    axum::response::Redirect::temporary(&format!(
+                        "http://localhost:{page_port}/internal"
+                    ))
+                }),
+            )
+            .route("/internal", get(|| async { "<h1>Internal</h1>" }));
+        let page_handle =
+            tokio::spawn(async move { axum::serve(page_listener, page_router).await });
+
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.request_timeout = 30;
+        server_assert.settings.blocked_hosts = vec!["localhost".into()];
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(
+            server_assert.url(format!("screenshot?url=http://127.0.0.1:{page_port}/")),
+        )
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body,
+            r#"{"cause":"forbidden host: 'localhost' is not allowed","code":"forbidden_host"}"#,
+            "Expected forbidden host JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+        page_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_blocked_host_meta_refresh() {
+        // A page refreshing from an accepted host (`127.0.0.1`) to a blocked one (`localhost`) during the delay.
+        let page_listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind page listener");
+        let page_port = page_listener.local_addr().expect("Page address").port();
+        let page_router = Router::new()
+            .route(
+                "/",
+                get(move || async move {
+                    axum::response::Html(format!(
+                        r#"<meta http-equiv="refresh" content="0; url=http://localhost:{page_port}/internal">"#
+                    ))
+                }),
+            )
+            .route("/internal", get(|| async { "<h1>Internal</h1>" }));
+        let page_handle =
+            tokio::spawn(async move { axum::serve(page_listener, page_router).await });
+
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.request_timeout = 30;
+        server_assert.settings.blocked_hosts = vec!["localhost".into()];
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(server_assert.url(format!(
+            "screenshot?url=http://127.0.0.1:{page_port}/&delay=2000"
+        )))
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body,
+            r#"{"cause":"forbidden host: 'localhost' is not allowed","code":"forbidden_host"}"#,
+            "Expected forbidden host JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+        page_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_compression() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.request_timeout = 30;
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::Client::new()
+            .get(
+                server_assert
+                    .url("screenshot?url=https://www.infomaniak.com&response_type=json-png-base64"),
+            )
+            .header(reqwest::header::ACCEPT_ENCODING, "gzip")
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers().get(reqwest::header::CONTENT_ENCODING),
+            Some(&reqwest::header::HeaderValue::from_static("gzip")),
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_json_data_url() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(
+            server_assert
+                .url("screenshot?url=https://www.infomaniak.com&response_type=json-data-url"),
+        )
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let body = response.text().await.expect("Failed to read response body");
+        let json: serde_json::Value = serde_json::from_str(&body).expect("Invalid JSON response");
+        let data_url = json["data"]["dataUrl"]
+            .as_str()
+            .expect("Expected a data.dataUrl string");
+
+        assert!(
+            data_url.starts_with("data:image/png;base64,"),
+            "Expected a PNG data URL, got: {data_url}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_multipart() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(
+            server_assert.url("screenshot?url=https://www.infomaniak.com&response_type=multipart"),
+        )
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let content_type = response.headers()[reqwest::header::CONTENT_TYPE]
+            .to_str()
+            .expect("Invalid Content-Type header");
+        let boundary = content_type
+            .strip_prefix("multipart/mixed; boundary=")
+            .expect("Expected a multipart/mixed Content-Type")
+            .to_owned();
+
+        let body = response
+            .bytes()
+            .await
+            .expect("Failed to read response body");
+        let body = body
+            .strip_suffix(format!("\r\n--{boundary}--\r\n").as_bytes())
+            .expect("Expected the closing delimiter");
+        let delimiter = format!("--{boundary}\r\n");
+        let parts = split_parts(body, &delimiter);
+        assert_eq!(parts.len(), 2, "Expected a metadata part and an image part");
+
+        let (metadata_headers, metadata) = split_part(parts[0]);
+        assert!(metadata_headers.contains("Content-Type: application/json"));
+        let metadata: serde_json::Value =
+            serde_json::from_slice(metadata).expect("Invalid JSON metadata part");
+        assert!(
+            metadata["url"]
+                .as_str()
+                .is_some_and(|url| url.contains("infomaniak.com"))
+        );
+        assert_eq!(metadata["width"], 800);
+        assert_eq!(metadata["height"], 600);
+        assert!(metadata["durationMs"].is_u64());
+
+        let (image_headers, image) = split_part(parts[1]);
+        assert!(image_headers.contains("Content-Type: image/png"));
+        assert!(
+            image.starts_with(b"\x89PNG\r\n\x1a\n"),
+            "Expected PNG bytes"
+        );
+
+        server_handle.abort();
+    }
+
+    /// Splits a multipart body, without its closing delimiter, into its parts.
+    fn split_parts<'a>(body: &'a [u8], delimiter: &str) -> Vec<&'a [u8]> {
+        let mut parts = Vec::new();
+        let mut rest = body
+            .strip_prefix(delimiter.as_bytes())
+            .expect("Expected a leading delimiter");
+        let delimiter = format!("\r\n{delimiter}");
+
+        while let Some(end) = rest
+            .windows(delimiter.len())
+            .position(|window| window == delimiter.as_bytes())
+        {
+            parts.push(&rest[..end]);
+            rest = &rest[end + delimiter.len()..];
+        }
+        parts.push(rest);
+
+        parts
+    }
+
+    /// Splits a multipart part into its headers and its body.
+    fn split_part(part: &[u8]) -> (String, &[u8]) {
+        let separator = part
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .expect("Expected the end of the part headers");
+
+        (
+            String::from_utf8_lossy(&part[..separator]).into_owned(),
+            &part[separator + 4..],
+        )
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_content_length() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::get(
+            server_assert
+                .url("screenshot?url=https://www.infomaniak.com&response_type=image-png-bytes"),
+        )
+        .await
+        .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let content_length = response
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .expect("Expected a Content-Length header")
+            .to_str()
+            .expect("Content-Length should be ASCII")
+            .parse::<usize>()
+            .expect("Content-Length should be a number");
+        let body = response
+            .bytes()
+            .await
+            .expect("Failed to read response body");
+
+        assert_eq!(content_length, body.len());
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_image_formats() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        for (response_type, content_type) in
+            [("image-jpeg", "image/jpeg"), ("image-webp", "image/webp")]
+        {
+            let response = reqwest::get(server_assert.url(format!(
+                "screenshot?url=https://www.infomaniak.com&response_type={response_type}"
+            )))
+            .await
+            .expect("Failed to send GET request");
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+            assert_eq!(
+                response.headers()[reqwest::header::CONTENT_TYPE],
+                content_type
+            );
+        }
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_format_conflict() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response =
+            reqwest::get(server_assert.url(
+                "screenshot?url=https://www.infomaniak.com&response_type=image-jpeg&format=webp",
+            ))
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body,
+            r#"{"cause":"unsupported parameter: format conflicting with response_type","code":"unsupported_parameter"}"#
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_stream() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response =
+            reqwest::get(server_assert.url("screenshot/stream?url=https://www.infomaniak.com"))
+                .await
+                .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers().get(reqwest::header::CONTENT_TYPE),
+            Some(&reqwest::header::HeaderValue::from_static(
+                "text/event-stream"
+            )),
+        );
+
+        let body = response.text().await.expect("Failed to read response body");
+        let events: Vec<&str> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("event: "))
+            .collect();
+        assert_eq!(
+            events,
+            ["navigating", "waiting", "capturing", "encoding", "done"],
+            "Unexpected event sequence, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_batch() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::Client::new()
+            .post(server_assert.url("screenshot/batch"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(r#"[{"url":"https://www.infomaniak.com"},{"url":"https://www.infomaniak.com","width":640}]"#)
+            .send()
+            .await
+            .expect("Failed to send POST request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_TYPE],
+            "application/x-ndjson"
+        );
+
+        let body = response.text().await.expect("Failed to read response body");
+        let mut indexes: Vec<u64> = body
+            .lines()
+            .map(|line| {
+                let result: serde_json::Value =
+                    serde_json::from_str(line).expect("Invalid JSON line");
+                assert_eq!(result["ok"], true, "Unexpected result: {line}");
+                assert_eq!(result["url"], "https://www.infomaniak.com");
+                assert!(
+                    result["dataUrl"]
+                        .as_str()
+                        .is_some_and(|data_url| data_url.starts_with("data:image/png;base64,")),
+                    "Expected a PNG data URL, got: {line}"
+                );
+
+                result["index"].as_u64().expect("Expected an index")
+            })
+            .collect();
+        indexes.sort_unstable();
+        assert_eq!(indexes, [0, 1]);
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_init_script() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(server_assert.url(
+                "screenshot?url=https://www.infomaniak.com&init_script=window.polyfilled%3Dtrue",
+            ))
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body,
+            r#"{"cause":"unsupported parameter: init_script in the query string, send it in a POST body","code":"unsupported_parameter"}"#
+        );
+
+        // Validated like the query string, before any browser is used.
+        let response = client
+            .post(server_assert.url("screenshot"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(r#"{"url":"https://www.infomaniak.com","init_script":"window.polyfilled = true;","width":65535}"#)
+            .send()
+            .await
+            .expect("Failed to send POST request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body = response.text().await.expect("Failed to read response body");
+        assert!(
+            body.contains(r#""code":"exceeds_maximum""#),
+            "Expected exceeds maximum JSON response, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_batch_rate_limit() {
+        let mut server_assert = ServerAssert::new();
+        server_assert.settings.rate_limit = 3;
+        let server_handle = server_assert.spawn().await;
+        let client = reqwest::Client::new();
+        let batch = |size: usize| {
+            client
+                .post(server_assert.url("screenshot/batch"))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(format!(
+                    "[{}]",
+                    vec![r#"{"url":"http://localhost"}"#; size].join(",")
+                ))
+                .send()
+        };
+
+        let response = batch(4).await.expect("Failed to send POST request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body = response.text().await.expect("Failed to read response body");
+        assert_eq!(
+            body,
+            r#"{"cause":"batch size exceeds the maximum of 3","code":"exceeds_maximum"}"#
+        );
+
+        // The rejected batch took a token, one is left for a batch of 3 requests.
+        let response = batch(3).await.expect("Failed to send POST request");
+        assert_eq!(response.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_batch_invalid_body() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::Client::new()
+            .post(server_assert.url("screenshot/batch"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(r#"{"url":"https://www.infomaniak.com"}"#)
+            .send()
+            .await
+            .expect("Failed to send POST request");
+        assert!(response.status().is_client_error());
+
+        let body = response.text().await.expect("Failed to read response body");
+        let json: serde_json::Value = serde_json::from_str(&body).expect("Invalid JSON response");
+        assert!(json["cause"].is_string(), "Expected a cause, got: {body}");
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_diff_identical() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        // The static page of the server itself, so both captures are identical.
+        let url = server_assert.url("ping");
+        let response = reqwest::Client::new()
+            .post(server_assert.url("screenshot/diff"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(
+                serde_json::json!({
+                    "baseline_url": url.as_str(),
+                    "candidate_url": url.as_str(),
+                    "options": { "width": 320, "height": 240 },
+                })
+                .to_string(),
+            )
+            .send()
+            .await
+            .expect("Failed to send POST request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let body = response.text().await.expect("Failed to read response body");
+        let json: serde_json::Value = serde_json::from_str(&body).expect("Invalid JSON response");
+        assert_eq!(json["data"]["mismatch"], 0.0, "Unexpected diff: {body}");
+        assert_eq!(json["data"]["sizeMismatch"], false);
+        assert!(
+            json["data"]["diffDataUrl"]
+                .as_str()
+                .is_some_and(|data_url| data_url.starts_with("data:image/png;base64,")),
+            "Expected a PNG data URL, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_accept_json() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::Client::new()
+            .get(server_assert.url("screenshot?url=https://www.infomaniak.com"))
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_TYPE],
+            "application/json"
+        );
+        assert_eq!(response.headers()[reqwest::header::VARY], "accept");
+
+        let body = response.text().await.expect("Failed to read response body");
+        let json: serde_json::Value = serde_json::from_str(&body).expect("Invalid JSON response");
+        assert!(
+            json["data"]["dataUrl"]
+                .as_str()
+                .is_some_and(|data_url| data_url.starts_with("data:image/png;base64,")),
+            "Expected a PNG data URL, got: {body}"
+        );
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_screenshot_accept_png() {
+        let server_assert = ServerAssert::new();
+        let server_handle = server_assert.spawn().await;
+
+        let response = reqwest::Client::new()
+            .get(server_assert.url("screenshot?url=https://www.infomaniak.com"))
+            .header(reqwest::header::ACCEPT, "image/png")
+            .send()
+            .await
+            .expect("Failed to send GET request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_TYPE],
+            "image/png"
+        );
+        assert_eq!(response.headers()[reqwest::header::VARY], "accept");
+
+        let body = response
+            .bytes()
+            .await
+            .expect("Failed to read response body");
+        assert!(body.starts_with(b"\x89PNG"), "Expected PNG bytes");
+
         server_handle.abort();
     }
 }
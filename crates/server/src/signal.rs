@@ -91,6 +91,7 @@ pub async fn shutdown() -> Result<()> {
 
 #[cfg(test)]
 #[cfg_attr(coverage, coverage(off))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 mod tests {
     use tokio::time::{Duration, timeout};
 
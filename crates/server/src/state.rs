@@ -2,39 +2,240 @@
 //!
 //! This module provides an integration with [deadpool](https://crates.io/crates/deadpool)
 //! to manage a pool of browser instances. The [`State`] struct wraps a [`BrowserPool`] and
-//! provides an asynchronous method to retrieve a browser from the pool.
+//! provides an asynchronous method to retrieve a browser from the pool within a bounded time.
+//! It also holds the [`ScreenshotCache`] and the [`Metrics`] shared by all requests, the [`ScreenshotLimits`]
+//! and [`HostPolicy`] they must respect, the permits bounding the browsers opened outside of the pool,
+//! and whether the server is draining.
 
-use deadpool::managed::{Object, PoolError};
-use pantin_browser::browser;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
-use crate::browser_pool::{BrowserManager, BrowserPool};
+use deadpool::{
+    Status,
+    managed::{Object, PoolError},
+};
+use pantin_browser::{Browser, browser};
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    browser_pool::{BrowserManager, BrowserPool},
+    cache::ScreenshotCache,
+    host_policy::HostPolicy,
+    metrics::Metrics,
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error(transparent)]
     PoolError(#[from] PoolError<browser::Error>),
+    /// No browser was available in time, the client may retry after `retry_after` seconds.
+    #[error("acquire browser timeout: no browser available after {timeout:?}")]
+    AcquireTimeout { timeout: Duration, retry_after: u64 },
+    #[error("acquire dedicated browser permit failed: {0}")]
+    DedicatedPermit(#[from] AcquireError),
+}
+
+/// The bounds enforced on the screenshot requests parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_field_names)]
+pub struct ScreenshotLimits {
+    /// Maximum window width in pixels.
+    pub max_width: u16,
+    /// Maximum window height in pixels.
+    pub max_height: u16,
+    /// Maximum number of elements captured by a single request.
+    pub max_elements: u16,
+    /// Maximum page height in pixels of a full page screenshot (unlimited if 0).
+    pub max_page_height: u32,
+    /// Maximum delay in milliseconds before the capture.
+    pub max_delay: u16,
+}
+
+impl Default for ScreenshotLimits {
+    fn default() -> Self {
+        Self {
+            max_width: 4096,
+            max_height: 4096,
+            max_elements: 100,
+            max_page_height: 0,
+            max_delay: 10000,
+        }
+    }
+}
+
+/// Returns `true` if both byte strings are equal, in a time that only depends on their lengths.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+
+    // Every byte is compared, and the hint keeps the compiler from short-circuiting the fold.
+    let difference = left
+        .iter()
+        .zip(right)
+        .fold(0, |difference, (left, right)| difference | (left ^ right));
+
+    std::hint::black_box(difference) == 0
 }
 
 /// Represents the application state that holds the browser pool.
 ///
-/// This state encapsulates a [`BrowserPool`] and provides methods to retrieve browser instances.
+/// This state encapsulates a [`BrowserPool`] and provides methods to retrieve browser instances,
+/// along with the [`ScreenshotCache`], the [`ScreenshotLimits`], the [`HostPolicy`] and the [`Metrics`].
 #[derive(Clone)]
 pub struct State {
     browser_pool: BrowserPool,
+    browser_acquire_timeout: Duration,
+    dedicated_browsers: Arc<Semaphore>,
+    screenshot_cache: ScreenshotCache,
+    screenshot_limits: ScreenshotLimits,
+    host_policy: HostPolicy,
+    retry_after: Option<Duration>,
+    request_timeout: Option<Duration>,
+    admin_api_key: Option<Arc<str>>,
+    draining: Arc<AtomicBool>,
+    metrics: Metrics,
 }
 
 impl State {
-    /// Creates a new state instance with the given browser pool.
+    /// Creates a new state instance with the given browser pool, no host policy, and empty metrics.
+    ///
+    /// As many browsers can be opened outside of the pool (see [`State::open_browser`]) as the pool maximum size.
     ///
     /// # Arguments
     ///
     /// * `browser_pool` - A [`BrowserPool`] instance to be managed.
+    /// * `browser_acquire_timeout` - The maximum time to wait for an available browser.
+    /// * `screenshot_cache` - The [`ScreenshotCache`] shared by the screenshot requests.
+    /// * `screenshot_limits` - The [`ScreenshotLimits`] enforced on the screenshot requests.
     ///
     /// # Returns
     ///
     /// A new [`State`] instance.
-    pub const fn new(browser_pool: BrowserPool) -> Self {
-        Self { browser_pool }
+    pub fn new(
+        browser_pool: BrowserPool,
+        browser_acquire_timeout: Duration,
+        screenshot_cache: ScreenshotCache,
+        screenshot_limits: ScreenshotLimits,
+    ) -> Self {
+        let dedicated_browsers = Arc::new(Semaphore::new(browser_pool.status().max_size));
+
+        Self {
+            browser_pool,
+            browser_acquire_timeout,
+            dedicated_browsers,
+            screenshot_cache,
+            screenshot_limits,
+            host_policy: HostPolicy::default(),
+            retry_after: None,
+            request_timeout: None,
+            admin_api_key: None,
+            draining: Arc::default(),
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Sets the [`HostPolicy`] the final URL of the captured pages is checked against.
+    #[must_use]
+    pub fn with_host_policy(mut self, host_policy: HostPolicy) -> Self {
+        self.host_policy = host_policy;
+        self
+    }
+
+    /// Sets the time the clients are asked to wait before retrying when no browser is available in time.
+    ///
+    /// If zero (the default), it is derived from the average capture time.
+    #[must_use]
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = (!retry_after.is_zero()).then_some(retry_after);
+        self
+    }
+
+    /// Returns the seconds the clients should wait before retrying when no browser is available in time.
+    ///
+    /// This is the configured value (see [`State::with_retry_after`]), otherwise the average capture time
+    /// rounded up, and at least 1 second.
+    pub fn retry_after(&self) -> u64 {
+        let retry_after = self
+            .retry_after
+            .or_else(|| self.metrics.average_capture_duration())
+            .unwrap_or_default();
+
+        (retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)).max(1)
+    }
+
+    /// Sets the maximum time to serve a request, including the responses streamed after the handler returned
+    /// (e.g. the batch results).
+    #[must_use]
+    pub const fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = Some(request_timeout);
+        self
+    }
+
+    /// Returns the maximum time to serve a request, if any (see [`State::with_request_timeout`]).
+    pub const fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+
+    /// Sets the API key expected by the admin endpoints, which reject all requests without it.
+    #[must_use]
+    pub fn with_admin_api_key(mut self, admin_api_key: Option<String>) -> Self {
+        self.admin_api_key = admin_api_key.map(Into::into);
+        self
+    }
+
+    /// Returns `true` if the given API key is the admin one (always `false` if there is no admin API key).
+    ///
+    /// The keys are compared in constant time, so the response time does not tell how much of the key is right.
+    pub fn is_admin(&self, api_key: Option<&str>) -> bool {
+        match (self.admin_api_key.as_deref(), api_key) {
+            (Some(admin_api_key), Some(api_key)) => {
+                constant_time_eq(admin_api_key.as_bytes(), api_key.as_bytes())
+            },
+            _ => false,
+        }
+    }
+
+    /// Starts draining the server: the new screenshot requests are rejected from now on.
+    ///
+    /// Returns `true` if the server was already draining.
+    pub fn drain(&self) -> bool {
+        self.draining.swap(true, Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the server is draining (see [`State::drain`]).
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Returns the screenshot cache.
+    pub const fn screenshot_cache(&self) -> &ScreenshotCache {
+        &self.screenshot_cache
+    }
+
+    /// Returns the limits enforced on the screenshot requests.
+    pub const fn screenshot_limits(&self) -> ScreenshotLimits {
+        self.screenshot_limits
+    }
+
+    /// Returns the policy restricting the hosts of the captured pages.
+    pub const fn host_policy(&self) -> &HostPolicy {
+        &self.host_policy
+    }
+
+    /// Returns the server metrics.
+    pub const fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Returns the current status (size, available browsers...) of the browser pool.
+    pub fn browser_pool_status(&self) -> Status {
+        self.browser_pool.status()
     }
 
     /// Asynchronously retrieves a browser instance from the pool.
@@ -43,9 +244,71 @@ impl State {
     ///
     /// # Errors
     ///
-    /// Returns an [`Error::PoolError`] if the browser pool fails to provide a browser instance.
+    /// Returns an [`Error::AcquireTimeout`] if no browser is available within the acquire timeout,
+    /// or an [`Error::PoolError`] if the browser pool fails to provide a browser instance.
     pub async fn get_browser(&self) -> Result<Object<BrowserManager>, Error> {
-        Ok(Box::pin(self.browser_pool.get()).await?)
+        self.get_browser_within(self.browser_acquire_timeout).await
+    }
+
+    /// Asynchronously retrieves a browser instance from the pool, waiting at most `acquire_timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::AcquireTimeout`] if no browser is available within the given timeout,
+    /// or an [`Error::PoolError`] if the browser pool fails to provide a browser instance.
+    pub async fn get_browser_within(
+        &self,
+        acquire_timeout: Duration,
+    ) -> Result<Object<BrowserManager>, Error> {
+        let browser = tokio::time::timeout(acquire_timeout, Box::pin(self.browser_pool.get()))
+            .await
+            .map_err(|_| Error::AcquireTimeout {
+                timeout: acquire_timeout,
+                retry_after: self.retry_after(),
+            })??;
+
+        Ok(browser)
+    }
+
+    /// Asynchronously retrieves a permit to open a browser outside of the pool (see [`State::open_browser`]),
+    /// waiting at most the acquire timeout.
+    ///
+    /// The permit must be held until the browser is closed, so there are never more dedicated browsers
+    /// than the pool maximum size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::AcquireTimeout`] if no permit is available within the acquire timeout.
+    pub async fn dedicated_browser_permit(&self) -> Result<OwnedSemaphorePermit, Error> {
+        let acquire_timeout = self.browser_acquire_timeout;
+        let permit = tokio::time::timeout(
+            acquire_timeout,
+            Arc::clone(&self.dedicated_browsers).acquire_owned(),
+        )
+        .await
+        .map_err(|_| Error::AcquireTimeout {
+            timeout: acquire_timeout,
+            retry_after: self.retry_after(),
+        })??;
+
+        Ok(permit)
+    }
+
+    /// Opens a browser outside of the pool, configured like the pooled ones but with the given timezone and locale.
+    ///
+    /// Both are set when launching the browser, so it cannot be recycled for other requests:
+    /// the caller owns it and it is closed when dropped. The caller must hold a permit
+    /// (see [`State::dedicated_browser_permit`]) while the browser is open.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`browser::Error`] if the browser cannot be opened.
+    pub async fn open_browser(
+        &self,
+        timezone: Option<String>,
+        locale: Option<String>,
+    ) -> Result<Browser, browser::Error> {
+        self.browser_pool.manager().open(timezone, locale).await
     }
 }
 
@@ -66,7 +329,12 @@ mod tests {
             .build()
             .expect("Failed to build pool");
 
-        let state = State::new(pool);
+        let state = State::new(
+            pool,
+            Duration::from_secs(10),
+            ScreenshotCache::default(),
+            ScreenshotLimits::default(),
+        );
 
         {
             let browser = state.get_browser().await.expect("Firefox browser");
@@ -92,9 +360,130 @@ mod tests {
             .build()
             .expect("Failed to build pool");
 
-        let state = State::new(pool);
+        let state = State::new(
+            pool,
+            Duration::from_secs(10),
+            ScreenshotCache::default(),
+            ScreenshotLimits::default(),
+        );
         let browser = state.get_browser().await;
 
         assert!(matches!(browser, Err(Error::PoolError(_))));
     }
+
+    #[test]
+    fn test_state_drain() {
+        let manager = BrowserManager::new("firefox");
+        let pool: BrowserPool = Pool::builder(manager)
+            .max_size(1)
+            .build()
+            .expect("Failed to build pool");
+
+        let state = State::new(
+            pool,
+            Duration::from_secs(10),
+            ScreenshotCache::default(),
+            ScreenshotLimits::default(),
+        );
+        assert!(!state.is_admin(None));
+        assert!(!state.is_admin(Some("")));
+
+        let state = state.with_admin_api_key(Some("secret".into()));
+        assert!(state.is_admin(Some("secret")));
+        assert!(!state.is_admin(Some("other")));
+        assert!(!state.is_admin(Some("secret2")));
+        assert!(!state.is_admin(Some("Secret")));
+        assert!(!state.is_admin(None));
+
+        let shared = state.clone();
+        assert!(!state.is_draining());
+        assert!(!shared.drain());
+        assert!(state.is_draining());
+        assert!(state.drain());
+    }
+
+    #[test]
+    fn test_state_retry_after() {
+        let manager = BrowserManager::new("firefox");
+        let pool: BrowserPool = Pool::builder(manager)
+            .max_size(1)
+            .build()
+            .expect("Failed to build pool");
+
+        let state = State::new(
+            pool,
+            Duration::from_secs(10),
+            ScreenshotCache::default(),
+            ScreenshotLimits::default(),
+        );
+        assert_eq!(state.retry_after(), 1);
+
+        state
+            .metrics()
+            .capture_duration(Duration::from_millis(2500));
+        assert_eq!(state.retry_after(), 3);
+
+        let state = state.with_retry_after(Duration::from_secs(7));
+        assert_eq!(state.retry_after(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_state_dedicated_browser_permit() {
+        let manager = BrowserManager::new("firefox");
+        let pool: BrowserPool = Pool::builder(manager)
+            .max_size(1)
+            .build()
+            .expect("Failed to build pool");
+
+        let state = State::new(
+            pool,
+            Duration::from_millis(100),
+            ScreenshotCache::default(),
+            ScreenshotLimits::default(),
+        );
+
+        let permit = state
+            .dedicated_browser_permit()
+            .await
+            .expect("Dedicated browser permit");
+
+        assert!(
+            matches!(state.dedicated_browser_permit().await, Err(Error::AcquireTimeout { timeout, retry_after: 1 }) if timeout == Duration::from_millis(100)),
+            "Expected AcquireTimeout error"
+        );
+
+        drop(permit);
+        assert!(state.dedicated_browser_permit().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_state_get_browser_acquire_timeout() {
+        let manager = BrowserManager::new("firefox");
+
+        let pool: BrowserPool = Pool::builder(manager)
+            .max_size(1)
+            .build()
+            .expect("Failed to build pool");
+
+        let state = State::new(
+            pool,
+            Duration::from_millis(100),
+            ScreenshotCache::default(),
+            ScreenshotLimits::default(),
+        );
+
+        {
+            let _browser = state.get_browser().await.expect("Firefox browser");
+            let browser = state.get_browser().await;
+
+            assert!(
+                matches!(browser, Err(Error::AcquireTimeout { timeout, retry_after: 1 }) if timeout == Duration::from_millis(100)),
+                "Expected AcquireTimeout error"
+            );
+        }
+
+        for browser in state.browser_pool.retain(|_, _| false).removed {
+            browser.close().await.expect("Browser close");
+        }
+    }
 }